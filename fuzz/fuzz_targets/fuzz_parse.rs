@@ -0,0 +1,20 @@
+#![no_main]
+
+// cargo +nightly fuzz run fuzz_parse
+//
+// Scans and parses, the parser has to cope with whatever tokens the scanner recovers with.
+
+use libfuzzer_sys::fuzz_target;
+use regg::parser::Parser;
+use regg::scanner::Scanner;
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(source) = std::str::from_utf8(data) else {
+        return;
+    };
+    let mut scanner = Scanner::new(source);
+    let tokens = scanner.scan_tokens();
+    let mut parser = Parser::new(tokens);
+    parser.parse();
+    parser.diagnostics();
+});
@@ -0,0 +1,18 @@
+#![no_main]
+
+// cargo +nightly fuzz run fuzz_scan
+//
+// The scanner takes `&str`, invalid UTF-8 is left to the caller. Any panic is a bug: copy
+// the input from `artifacts/fuzz_scan` to `regressions` so `tests/fuzz.rs` keeps it fixed.
+
+use libfuzzer_sys::fuzz_target;
+use regg::scanner::Scanner;
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(source) = std::str::from_utf8(data) else {
+        return;
+    };
+    let mut scanner = Scanner::new(source);
+    scanner.scan_tokens();
+    scanner.diagnostics();
+});
@@ -0,0 +1,114 @@
+use serde_json::{json, Value};
+
+use crate::intern::Atom;
+use crate::span::Span;
+
+#[derive(Debug)]
+pub enum Node {
+    CodeBlock(String),   // --- foo ---
+    Element(Element),    // <foo> ... </foo>
+    Fragment(Vec<Node>), // <> ... </>
+    Text(String),
+    CData(String),       // <![CDATA[ foo ]]>, the text as written
+    Expression(String),  // { foo }, HTML-escaped when rendered
+    RawHTML(String),     // {@html foo}, rendered without escaping
+    HTMLExpr(Vec<Node>), // (` ... `)
+}
+
+// Everything between the tag name and `>`
+#[derive(Debug)]
+pub enum Attribute {
+    Text(String),                           // class="foo"
+    Expression(String),                     // { foo }
+    Spread(String),                         // {...foo}
+    Shorthand(String),                      // {foo}, short for foo={foo}
+    Boolean(Atom),                          // foo, without a value
+    DataSet(String), // data:set={ foo }, expands into a data-* attribute per property
+    SetHTML(String), // set:html={ foo }, the element's children as raw HTML
+    TransitionName(String), // transition:name="foo"
+    TransitionPersist(Option<String>), // transition:persist or transition:persist="foo"
+    Client(String, Option<DirectiveValue>), // client:load, the name without `client:`
+}
+
+// The value of a `client:*` directive, for tooling that hydrates the component
+#[derive(Debug)]
+pub enum DirectiveValue {
+    Text(String),       // client:media="(max-width: 50em)"
+    Expression(String), // client:visible={{ rootMargin: "200px" }}
+}
+
+#[derive(Debug)]
+pub struct Element {
+    pub name: Atom,                 // the tag name, or its expression if `dynamic`
+    pub dynamic: bool,              // `<{foo}>`
+    pub attributes: Vec<Attribute>, // in source order, never sorted, so output diffs stay small
+    pub children: Vec<Node>,
+    pub self_closing: bool,
+    pub line: usize,
+    pub start_tag: Span, // `<foo ...>`
+    pub span: Span,      // `<foo ...> ... </foo>`, the start tag only for void elements
+    // Identifies the element across pages for view transitions, set by the `transition:*` attributes
+    pub transition_id: Option<String>,
+}
+
+impl Node {
+    /// The node and its children as a JSON object
+    pub fn to_json(&self) -> Value {
+        match self {
+            Node::CodeBlock(code) => return json!({ "type": "CodeBlock", "code": code }),
+            Node::Element(element) => {
+                let attributes: Vec<Value> =
+                    element.attributes.iter().map(attribute_json).collect();
+                let children: Vec<Value> = element.children.iter().map(Node::to_json).collect();
+                return json!({
+                    "type": "Element",
+                    "name": element.name.as_str(),
+                    "dynamic": element.dynamic,
+                    "attributes": attributes,
+                    "children": children,
+                    "selfClosing": element.self_closing,
+                    "line": element.line,
+                    "span": { "start": element.span.start, "end": element.span.end },
+                    "transitionId": element.transition_id,
+                });
+            }
+            Node::Fragment(children) => {
+                let children: Vec<Value> = children.iter().map(Node::to_json).collect();
+                return json!({ "type": "Fragment", "children": children });
+            }
+            Node::Text(text) => return json!({ "type": "Text", "text": text }),
+            Node::CData(text) => return json!({ "type": "CData", "text": text }),
+            Node::Expression(code) => return json!({ "type": "Expression", "code": code }),
+            Node::RawHTML(code) => return json!({ "type": "RawHTML", "code": code }),
+            Node::HTMLExpr(children) => {
+                let children: Vec<Value> = children.iter().map(Node::to_json).collect();
+                return json!({ "type": "HTMLExpr", "children": children });
+            }
+        }
+    }
+}
+
+fn attribute_json(attribute: &Attribute) -> Value {
+    let (kind, value): (&str, Option<&str>) = match attribute {
+        Attribute::Text(text) => ("Text", Some(text)),
+        Attribute::Expression(code) => ("Expression", Some(code)),
+        Attribute::Spread(code) => ("Spread", Some(code)),
+        Attribute::Shorthand(name) => ("Shorthand", Some(name)),
+        Attribute::Boolean(name) => ("Boolean", Some(name)),
+        Attribute::DataSet(code) => ("DataSet", Some(code)),
+        Attribute::SetHTML(code) => ("SetHTML", Some(code)),
+        Attribute::TransitionName(name) => ("TransitionName", Some(name)),
+        Attribute::TransitionPersist(name) => ("TransitionPersist", name.as_deref()),
+        Attribute::Client(name, value) => {
+            let value = match value {
+                Some(DirectiveValue::Text(text)) => json!({ "type": "Text", "value": text }),
+                Some(DirectiveValue::Expression(code)) => {
+                    json!({ "type": "Expression", "code": code })
+                }
+                None => Value::Null,
+            };
+            return json!({ "type": "Client", "name": name, "value": value });
+        }
+    };
+    return json!({ "type": kind, "value": value });
+}
@@ -0,0 +1,117 @@
+use std::sync::Mutex;
+
+use crate::diagnostic::{Diagnostic, ErrorFormat};
+use crate::encoding::Encoding;
+use crate::flags::Flags;
+use crate::scanner::ScannerOptions;
+use crate::Regg;
+
+/// Gets every diagnostic instead of stderr, with the name of the file it's in. Warnings
+/// are already errors in strict mode, and diagnostics past `max_errors` never get here.
+pub type ErrorSink = Box<dyn FnMut(&str, &Diagnostic) + Send>;
+
+/// Configures a `Regg`, like `Regg::builder().strict(true).fail_fast(true).build()`.
+/// Everything that isn't set is as in `Regg::new()`.
+#[derive(Default)]
+pub struct ReggBuilder {
+    strict: bool,
+    color: bool,
+    fail_fast: bool,
+    max_errors: Option<usize>,
+    max_warnings: Option<usize>,
+    error_format: ErrorFormat,
+    error_sink: Option<ErrorSink>,
+    source_name: Option<String>,
+    encoding: Encoding,
+    flags: Flags,
+    scanner_options: ScannerOptions,
+}
+
+impl ReggBuilder {
+    /// Reports warnings as errors, like `-D warnings`
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        return self;
+    }
+
+    /// Prints diagnostics in color, off by default
+    pub fn color(mut self, color: bool) -> Self {
+        self.color = color;
+        return self;
+    }
+
+    /// Stops at the first error, like `max_errors(Some(1))`
+    pub fn fail_fast(mut self, fail_fast: bool) -> Self {
+        self.fail_fast = fail_fast;
+        return self;
+    }
+
+    /// Stops reporting diagnostics, and checking more files, after `max_errors` errors
+    pub fn max_errors(mut self, max_errors: Option<usize>) -> Self {
+        self.max_errors = max_errors;
+        return self;
+    }
+
+    /// Fails when there are more than `max_warnings` warnings
+    pub fn max_warnings(mut self, max_warnings: Option<usize>) -> Self {
+        self.max_warnings = max_warnings;
+        return self;
+    }
+
+    /// Prints diagnostics as text or as one JSON object per line
+    pub fn error_format(mut self, error_format: ErrorFormat) -> Self {
+        self.error_format = error_format;
+        return self;
+    }
+
+    /// Hands diagnostics to `sink` instead of printing them, see `ErrorSink`
+    pub fn error_sink(mut self, sink: impl FnMut(&str, &Diagnostic) + Send + 'static) -> Self {
+        self.error_sink = Some(Box::new(sink));
+        return self;
+    }
+
+    /// The file `run` says diagnostics are in, `<input>` by default. `run_file` and
+    /// `check` use the paths of the files.
+    pub fn source_name(mut self, source_name: &str) -> Self {
+        self.source_name = Some(source_name.to_string());
+        return self;
+    }
+
+    /// The encoding files are decoded with, UTF-8 by default
+    pub fn encoding(mut self, encoding: Encoding) -> Self {
+        self.encoding = encoding;
+        return self;
+    }
+
+    /// The flags `{#if flag("...")}` blocks are resolved with
+    pub fn flags(mut self, flags: Flags) -> Self {
+        self.flags = flags;
+        return self;
+    }
+
+    /// The limits sources are scanned with
+    pub fn scanner_options(mut self, scanner_options: ScannerOptions) -> Self {
+        self.scanner_options = scanner_options;
+        return self;
+    }
+
+    pub fn build(self) -> Regg {
+        let mut regg = Regg::new();
+        regg.deny_warnings = self.strict;
+        regg.color = self.color;
+        regg.max_errors = match self.fail_fast {
+            true => Some(1),
+            false => self.max_errors,
+        };
+        regg.max_warnings = self.max_warnings;
+        regg.error_format = self.error_format;
+        regg.error_sink = self.error_sink.map(Mutex::new);
+        if let Some(source_name) = self.source_name {
+            regg.source_name = source_name;
+        }
+        regg.encoding = self.encoding;
+        regg.flags = self.flags;
+        regg.scanner_options = self.scanner_options;
+        return regg;
+    }
+}
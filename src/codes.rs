@@ -0,0 +1,533 @@
+// The codes of diagnostics, and the longer explanations `regg explain` prints for them.
+// Codes are stable: a code is never reused for another diagnostic, even after the
+// diagnostic it belonged to is removed. Errors are `E`, warnings `W`.
+
+pub const UNTERMINATED_FRONTMATTER: &str = "E0001";
+pub const UNTERMINATED_EXPRESSION: &str = "E0002";
+pub const UNTERMINATED_DYNAMIC_TAG: &str = "E0003";
+pub const UNTERMINATED_ATTRIBUTE_VALUE: &str = "E0004";
+pub const UNTERMINATED_ATTRIBUTE_EXPRESSION: &str = "E0005";
+pub const UNTERMINATED_TAG: &str = "E0006";
+pub const UNTERMINATED_MARKUP_EXPRESSION: &str = "E0007";
+pub const MISMATCHED_CLOSING_TAG: &str = "E0008";
+pub const UNEXPECTED_CLOSING_TAG: &str = "E0009";
+pub const ATTRIBUTE_OUTSIDE_TAG: &str = "E0010";
+pub const VOID_ELEMENT_CHILDREN: &str = "E0011";
+pub const INVALID_DATA_SET: &str = "E0012";
+pub const INVALID_SET_HTML: &str = "E0013";
+pub const INVALID_TRANSITION_NAME: &str = "E0014";
+pub const UNCLOSED_IF: &str = "E0015";
+pub const UNEXPECTED_BLOCK: &str = "E0016";
+pub const UNKNOWN_CONDITION: &str = "E0017";
+pub const INVALID_ENCODING: &str = "E0018";
+pub const MISSING_LAYOUT: &str = "E0019";
+pub const SCANNER_OUT_OF_BOUNDS: &str = "E0020";
+pub const UNREADABLE_FILE: &str = "E0021";
+pub const UNTERMINATED_CLOSING_TAG: &str = "E0022";
+pub const UNTERMINATED_CDATA: &str = "E0023";
+pub const CIRCULAR_IMPORT: &str = "E0024";
+pub const LIMIT_EXCEEDED: &str = "E0025";
+pub const NESTING_TOO_DEEP: &str = "E0026";
+
+pub const UNCLOSED_ELEMENT: &str = "W0001";
+pub const UNCLOSED_FRAGMENT: &str = "W0002";
+pub const UNDECLARED_SHORTHAND: &str = "W0003";
+pub const UNKNOWN_TRANSITION_DIRECTIVE: &str = "W0004";
+pub const UNDEFINED_FLAG: &str = "W0005";
+pub const RAW_HTML: &str = "W0006";
+pub const UNKNOWN_CLIENT_DIRECTIVE: &str = "W0007";
+pub const MISSING_PROP: &str = "W0008";
+pub const UNKNOWN_PROP: &str = "W0009";
+pub const PROP_TYPE_MISMATCH: &str = "W0010";
+pub const UNUSED_IMPORT: &str = "W0011";
+
+/// What a diagnostic code means, with an example and how to fix it
+pub fn explain(code: &str) -> Option<&'static str> {
+    let explanation = match code.to_ascii_uppercase().as_str() {
+        UNTERMINATED_FRONTMATTER => {
+            "The frontmatter is never closed.
+
+The code at the start of a template goes between two `---` fences. Without the
+second one, the whole template is read as code:
+
+    ---
+    const title = \"Home\";
+    <h1>{title}</h1>
+
+Close the frontmatter with `---` before the markup:
+
+    ---
+    const title = \"Home\";
+    ---
+    <h1>{title}</h1>"
+        }
+        UNTERMINATED_EXPRESSION => {
+            "An expression is never closed.
+
+Every `{` that starts an expression needs a `}` that ends it. Here the rest of the
+template is read as part of the expression:
+
+    <h1>{title</h1>
+
+Add the missing `}`:
+
+    <h1>{title}</h1>"
+        }
+        UNTERMINATED_DYNAMIC_TAG => {
+            "The name of a dynamic tag is never closed.
+
+A dynamic tag takes its name from an expression, `<{tag}>`, which needs a `}`:
+
+    <{heading class=\"title\">
+
+Close the expression:
+
+    <{heading} class=\"title\">"
+        }
+        UNTERMINATED_ATTRIBUTE_VALUE => {
+            "An attribute value is never closed.
+
+A quoted attribute value ends with the same quote it starts with:
+
+    <a href=\"/about>About</a>
+
+Add the closing quote:
+
+    <a href=\"/about\">About</a>"
+        }
+        UNTERMINATED_ATTRIBUTE_EXPRESSION => {
+            "A spread or shorthand attribute is never closed.
+
+`{...props}` and `{title}` in a tag need their closing `}`:
+
+    <Card {...props />
+
+Add it:
+
+    <Card {...props} />"
+        }
+        UNTERMINATED_TAG => {
+            "A tag is never closed.
+
+An opening tag ends with `>`, or `/>` for a self-closing tag. Here the `<p` is
+never ended, so its content is read as attributes:
+
+    <p class=\"intro\"
+    Hello
+    </p>
+
+Add the `>`:
+
+    <p class=\"intro\">
+    Hello
+    </p>"
+        }
+        UNTERMINATED_MARKUP_EXPRESSION => {
+            "A markup expression is never closed.
+
+Markup inside an expression goes between `` (` `` and `` `) ``:
+
+    {items.map(item => (`<li>{item}</li>))}
+
+Close it with `` `) ``:
+
+    {items.map(item => (`<li>{item}</li>`))}"
+        }
+        MISMATCHED_CLOSING_TAG => {
+            "A closing tag doesn't match the element it closes.
+
+The closing tag has a different name than the innermost open element, and no
+other open element has that name, so it's most likely a typo:
+
+    <ul>
+      <li>One</lo>
+    </ul>
+
+Use the name of the element:
+
+    <ul>
+      <li>One</li>
+    </ul>"
+        }
+        UNEXPECTED_CLOSING_TAG => {
+            "A closing tag doesn't close anything.
+
+There's no open element or fragment for the closing tag to close:
+
+    <p>Hello</p></div>
+
+Remove it, or add the opening tag it belongs to:
+
+    <div><p>Hello</p></div>"
+        }
+        ATTRIBUTE_OUTSIDE_TAG => {
+            "An attribute is outside of a tag.
+
+Spread and shorthand attributes like `{...props}` and `{title}` only go in tags:
+
+    <Card></Card> {...props}
+
+Move it into the tag it belongs to:
+
+    <Card {...props}></Card>"
+        }
+        VOID_ELEMENT_CHILDREN => {
+            "A void element has children.
+
+Void elements like `<img>`, `<br>` and `<input>` can't have content, they have
+no closing tag in HTML:
+
+    <img src=\"/logo.png\">Logo</img>
+
+Remove the content and the closing tag, and use an attribute for text:
+
+    <img src=\"/logo.png\" alt=\"Logo\">"
+        }
+        INVALID_DATA_SET => {
+            "`data:set` isn't given an object.
+
+`data:set` adds a `data-*` attribute for every property of an object, so it
+takes an expression:
+
+    <div data:set=\"user\"></div>
+
+Pass the object:
+
+    <div data:set={user}></div>"
+        }
+        INVALID_SET_HTML => {
+            "`set:html` isn't given an expression.
+
+`set:html` sets the children of an element to HTML computed by an expression:
+
+    <article set:html=\"<p>Hello</p>\"></article>
+
+Write static HTML as the element's children instead:
+
+    <article><p>Hello</p></article>"
+        }
+        INVALID_TRANSITION_NAME => {
+            "`transition:name` isn't a string.
+
+Elements are matched across pages by their `transition:name`, so the name has to
+be the same on every page and can't come from an expression:
+
+    <img transition:name={name} src=\"/hero.png\">
+
+Use a fixed name:
+
+    <img transition:name=\"hero\" src=\"/hero.png\">"
+        }
+        UNCLOSED_IF => {
+            "An `{#if}` block is never closed.
+
+Every `{#if ...}` needs a `{/if}`:
+
+    {#if flag(\"beta\")}
+      <BetaBanner />
+
+Close the block:
+
+    {#if flag(\"beta\")}
+      <BetaBanner />
+    {/if}"
+        }
+        UNEXPECTED_BLOCK => {
+            "An `{:else}` or `{/if}` isn't in an `{#if}` block.
+
+    <BetaBanner />
+    {/if}
+
+Remove it, or add the `{#if ...}` it belongs to:
+
+    {#if flag(\"beta\")}
+      <BetaBanner />
+    {/if}"
+        }
+        UNKNOWN_CONDITION => {
+            "The condition of an `{#if}` block can't be evaluated at build time.
+
+`{#if}` is resolved when the template is built, so it only takes feature flags,
+`flag(\"name\")` or `!flag(\"name\")`:
+
+    {#if user.isAdmin}
+      <AdminPanel />
+    {/if}
+
+Use an expression for conditions that depend on data:
+
+    { user.isAdmin && (`<AdminPanel />`) }"
+        }
+        INVALID_ENCODING => {
+            "The file isn't valid in the encoding it's read with.
+
+Files are read as UTF-8, invalid byte sequences are replaced with `\u{FFFD}`. A
+file saved as Latin-1 (ISO-8859-1), like some older files are, has to be read
+with:
+
+    regg --encoding latin-1 <FILE>
+
+Or convert the file to UTF-8."
+        }
+        MISSING_LAYOUT => {
+            "The layout of a Markdown page doesn't exist.
+
+The `layout` in the frontmatter of a Markdown page is a path relative to the
+page:
+
+    ---
+    layout: ../layouts/Post.regg
+    ---
+
+Check that the file exists at that path."
+        }
+        SCANNER_OUT_OF_BOUNDS => {
+            "The scanner read past the end of the template.
+
+This is a bug in Regg, not in the template. Please report it with the template
+that causes it."
+        }
+        UNREADABLE_FILE => {
+            "A file can't be read.
+
+The file doesn't exist, is a directory that can't be listed, or Regg doesn't have
+the permission to read it. The diagnostic has the reason the system gave."
+        }
+        UNTERMINATED_CLOSING_TAG => {
+            "A closing tag is never closed.
+
+A closing tag ends with `>`. Here the template ends before it does:
+
+    <main>
+      <p>Hello</p>
+    </main
+
+Add the `>`:
+
+    <main>
+      <p>Hello</p>
+    </main>"
+        }
+        UNTERMINATED_CDATA => {
+            "A CDATA section is never closed.
+
+Everything after `<![CDATA[` is text up to `]]>`. Here the template ends before it:
+
+    <svg>
+      <style><![CDATA[ circle { fill: red } ]]</style>
+    </svg>
+
+Add the `]]>`:
+
+    <svg>
+      <style><![CDATA[ circle { fill: red } ]]></style>
+    </svg>"
+        }
+        CIRCULAR_IMPORT => {
+            "Templates import each other in a circle.
+
+Here `Card.regg` imports `Button.regg`, which imports `Card.regg` again, so
+neither can be rendered without the other being rendered first:
+
+    ---
+    // Card.regg
+    import Button from \"./Button.regg\";
+    ---
+
+    ---
+    // Button.regg
+    import Card from \"./Card.regg\";
+    ---
+
+Break the chain: move what both need into a third component, or pass it in a slot
+instead of importing it."
+        }
+        LIMIT_EXCEEDED => {
+            "The template is too big or too deeply nested to scan.
+
+The scanner stops at limits far above what a real template needs, so that input
+like a million nested elements or a gigabyte of text fails fast instead of
+taking all the time or memory there is:
+
+    <div><div><div><div><div><div><div><div> ...
+
+By default a source can be 256 MB, with up to 1024 elements nested, 16 million
+tokens and 16 MB in a single text or expression. Split the template up, or, if it
+is generated and fine, raise the limits with `ScannerOptions`."
+        }
+        NESTING_TOO_DEEP => {
+            "Elements are nested too deep to parse.
+
+The parser goes one level deeper for every element, fragment, markup expression
+and `{#if}` block that is open, and stops at 256 levels so that a template like
+
+    <div><div><div><div><div><div><div><div> ...
+
+can't crash it. The content of the node that is one level too deep is skipped.
+Real templates are rarely more than a few dozen levels deep: split the markup up
+into components, or close the elements that were meant to be closed."
+        }
+        UNCLOSED_ELEMENT => {
+            "An element is never closed.
+
+An element without a closing tag ends where its parent does, which is often not
+what was meant:
+
+    <ul>
+      <li>One
+      <li>Two
+    </ul>
+
+Close every element:
+
+    <ul>
+      <li>One</li>
+      <li>Two</li>
+    </ul>
+
+Void elements like `<br>` and `<img>` don't need closing, and any element can be
+self-closing, like `<div />`."
+        }
+        UNCLOSED_FRAGMENT => {
+            "A fragment is never closed.
+
+A fragment groups elements without a wrapper, from `<>` to `</>`:
+
+    <>
+      <h1>Title</h1>
+      <p>Text</p>
+
+Close it:
+
+    <>
+      <h1>Title</h1>
+      <p>Text</p>
+    </>"
+        }
+        UNDECLARED_SHORTHAND => {
+            "A shorthand attribute uses a name that isn't declared.
+
+`{title}` in a tag is short for `title={title}`, but nothing declares `title`:
+
+    <Card {title} />
+
+Declare it in the frontmatter:
+
+    ---
+    const title = \"Home\";
+    ---
+    <Card {title} />"
+        }
+        UNKNOWN_TRANSITION_DIRECTIVE => {
+            "A `transition:` attribute isn't a known directive.
+
+The directives are `transition:name` and `transition:persist`:
+
+    <header transition:keep>
+
+Use one of them:
+
+    <header transition:persist>"
+        }
+        UNKNOWN_CLIENT_DIRECTIVE => {
+            "A `client:` attribute isn't a known directive.
+
+The directives, for when a component is hydrated, are `client:load`, `client:idle`,
+`client:visible`, `client:media` and `client:only`:
+
+    <Counter client:lazy />
+
+Use one of them:
+
+    <Counter client:visible />"
+        }
+        MISSING_PROP => {
+            "A component is used without a prop it requires.
+
+The props of a component are declared in its frontmatter, the ones without `?` have
+to be passed:
+
+    ---
+    export interface Props {
+      title: string;
+      subtitle?: string;
+    }
+    ---
+
+    <Card subtitle=\"Hello\" />
+
+Pass it:
+
+    <Card title=\"Welcome\" subtitle=\"Hello\" />
+
+Or make it optional with `title?: string` in the component."
+        }
+        UNKNOWN_PROP => {
+            "A component is passed a prop it doesn't declare.
+
+The component's `Props` don't have it, so it's most likely a typo:
+
+    <Card titel=\"Welcome\" />
+
+Fix the name, or declare the prop in the component's `Props`:
+
+    <Card title=\"Welcome\" />"
+        }
+        PROP_TYPE_MISMATCH => {
+            "A component is passed a value of the wrong type.
+
+A quoted value is always a string, even if it looks like a number, and an attribute
+without a value passes `true`:
+
+    <Counter start=\"3\" />
+
+Pass a number, a boolean or an object with an expression:
+
+    <Counter start={3} />"
+        }
+        UNUSED_IMPORT => {
+            "The frontmatter imports a name that nothing uses.
+
+Neither the frontmatter nor the template refers to `Card` here:
+
+    ---
+    import Card from \"./Card.regg\";
+    ---
+    <p>Welcome</p>
+
+Remove the import, or use what it imports:
+
+    <Card title=\"Welcome\" />"
+        }
+        UNDEFINED_FLAG => {
+            "A feature flag isn't defined.
+
+Flags that are neither in the `[flags]` of `regg.toml` nor in the `REGG_FLAGS`
+environment variable are off:
+
+    {#if flag(\"beta\")}
+      <BetaBanner />
+    {/if}
+
+Define it in `regg.toml`:
+
+    [flags]
+    beta = true"
+        }
+        RAW_HTML => {
+            "An expression is rendered as raw HTML.
+
+`set:html` renders its expression without escaping, so HTML from user input could
+run scripts on the page:
+
+    <div set:html={comment.body}></div>
+
+Make sure the HTML can't come from users, or use an expression, which is escaped:
+
+    <div>{comment.body}</div>"
+        }
+        _ => return None,
+    };
+
+    return Some(explanation);
+}
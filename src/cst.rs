@@ -0,0 +1,297 @@
+// Lossless concrete syntax tree: every byte of the source is in exactly one token, including
+// the whitespace between tokens (trivia), so printing the tree gives back the source. Unlike
+// the AST, the tree keeps the tags, fences and braces themselves, which is what tools that
+// rewrite templates, like a formatter, need.
+
+use std::fmt;
+
+use crate::html::is_void_element;
+use crate::scanner::Scanner;
+use crate::span::Span;
+use crate::token::Token;
+use crate::token_type::TokenType;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NodeKind {
+    Root,
+    Element,  // StartTag, children and the closing tag
+    StartTag, // `<foo`, attributes and `>` or `/>`
+    Fragment, // `<>`, children and `</>`
+    HTMLExpr, // (`, children and `)
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum TokenKind {
+    Token(TokenType),
+    Whitespace,
+    Skipped, // source the scanner didn't produce a token for
+}
+
+#[derive(Debug, Clone)]
+pub struct SyntaxToken {
+    pub kind: TokenKind,
+    pub text: String,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone)]
+pub enum SyntaxElement {
+    Node(SyntaxNode),
+    Token(SyntaxToken),
+}
+
+#[derive(Debug, Clone)]
+pub struct SyntaxNode {
+    pub kind: NodeKind,
+    pub children: Vec<SyntaxElement>,
+    pub span: Span,
+}
+
+/// Scans `source` and builds its syntax tree, `tree.to_string() == source` always holds.
+///
+/// Errors don't stop the tree from being built: an unclosed element ends with its parent,
+/// and a closing tag without an open element is a token of its parent.
+pub fn parse(source: &str) -> SyntaxNode {
+    let mut scanner = Scanner::new(source);
+    let tokens = scanner.scan_tokens();
+    return Builder::new(source).build(tokens);
+}
+
+impl SyntaxNode {
+    fn new(kind: NodeKind, start: usize) -> Self {
+        Self {
+            kind,
+            children: Vec::new(),
+            span: Span::new(start, start),
+        }
+    }
+
+    /// The tokens of the node and its descendants, in source order
+    pub fn tokens(&self) -> Vec<&SyntaxToken> {
+        let mut tokens = Vec::new();
+        for child in &self.children {
+            match child {
+                SyntaxElement::Node(node) => tokens.extend(node.tokens()),
+                SyntaxElement::Token(token) => tokens.push(token),
+            }
+        }
+        return tokens;
+    }
+
+    /// The nodes directly inside this one
+    pub fn child_nodes(&self) -> impl Iterator<Item = &SyntaxNode> {
+        return self.children.iter().filter_map(|child| match child {
+            SyntaxElement::Node(node) => Some(node),
+            SyntaxElement::Token(_) => None,
+        });
+    }
+
+    fn push(&mut self, element: SyntaxElement) {
+        let span = match &element {
+            SyntaxElement::Node(node) => node.span,
+            SyntaxElement::Token(token) => token.span,
+        };
+        self.span.end = self.span.end.max(span.end);
+        self.children.push(element);
+    }
+}
+
+impl fmt::Display for SyntaxNode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for token in self.tokens() {
+            write!(f, "{}", token.text)?;
+        }
+        Ok(())
+    }
+}
+
+impl SyntaxToken {
+    pub fn is_trivia(&self) -> bool {
+        return matches!(self.kind, TokenKind::Whitespace);
+    }
+}
+
+// What an open node is waiting to be closed by, like `parser::Open`
+struct OpenNode {
+    node: SyntaxNode,
+    name: Option<String>, // the tag name of an Element, `{foo}` for `<{foo}>`
+}
+
+struct Builder<'a> {
+    source: &'a str,
+    position: usize, // where the next token starts, everything before is in the tree
+    open: Vec<OpenNode>,
+}
+
+impl<'a> Builder<'a> {
+    fn new(source: &'a str) -> Self {
+        Self {
+            source,
+            position: 0,
+            open: vec![OpenNode {
+                node: SyntaxNode::new(NodeKind::Root, 0),
+                name: None,
+            }],
+        }
+    }
+
+    fn build(mut self, tokens: &[Token]) -> SyntaxNode {
+        let mut tokens = tokens.iter().peekable();
+
+        while let Some(token) = tokens.next() {
+            match token.kind {
+                TokenType::EOF => break,
+                TokenType::OpeningTagStart | TokenType::DynamicTagStart => {
+                    let name = match token.kind {
+                        TokenType::DynamicTagStart => {
+                            format!("{{{}}}", token.literal_string(self.source))
+                        }
+                        _ => token.literal_string(self.source),
+                    };
+                    let dynamic = matches!(token.kind, TokenType::DynamicTagStart);
+
+                    self.trivia(token.span.start);
+                    self.open(NodeKind::Element, Some(name.clone()));
+                    self.open(NodeKind::StartTag, None);
+                    self.token(token);
+
+                    // the attributes, up to `>` or `/>`
+                    let mut self_closing = false;
+                    while let Some(attribute) = tokens.next_if(|next| is_attribute(next)) {
+                        self.trivia(attribute.span.start);
+                        self.token(attribute);
+                    }
+                    if let Some(end) = tokens.next_if(|next| {
+                        matches!(
+                            next.kind,
+                            TokenType::OpeningTagEnd | TokenType::SelfClosingTagEnd
+                        )
+                    }) {
+                        self_closing = matches!(end.kind, TokenType::SelfClosingTagEnd);
+                        self.trivia(end.span.start);
+                        self.token(end);
+                    }
+                    self.close(); // StartTag
+
+                    if self_closing || (!dynamic && is_void_element(&name)) {
+                        self.close(); // Element
+                    }
+                }
+                TokenType::FragmentOpen | TokenType::HTMLExprStart => {
+                    let kind = match token.kind {
+                        TokenType::FragmentOpen => NodeKind::Fragment,
+                        _ => NodeKind::HTMLExpr,
+                    };
+                    self.trivia(token.span.start);
+                    self.open(kind, None);
+                    self.token(token);
+                }
+                TokenType::ClosingTag | TokenType::FragmentClose | TokenType::HTMLExprEnd => {
+                    self.trivia(token.span.start);
+                    match self.closed_by(token) {
+                        Some(depth) => {
+                            // elements left open inside end here, like `<p>` in `<div><p></div>`
+                            while self.open.len() > depth + 1 {
+                                self.close();
+                            }
+                            self.token(token);
+                            self.close();
+                        }
+                        None => self.token(token),
+                    }
+                }
+                _ => {
+                    self.trivia(token.span.start);
+                    self.token(token);
+                }
+            }
+        }
+
+        self.trivia(self.source.len());
+        while self.open.len() > 1 {
+            self.close();
+        }
+        return self.open.pop().unwrap().node;
+    }
+
+    // The index in `open` of the node `token` closes
+    fn closed_by(&self, token: &Token) -> Option<usize> {
+        return self
+            .open
+            .iter()
+            .rposition(|open| match (open.node.kind, &token.kind) {
+                (NodeKind::Element, TokenType::ClosingTag) => {
+                    open.name.as_deref().is_some_and(|name| {
+                        name.eq_ignore_ascii_case(&token.literal_string(self.source))
+                    })
+                }
+                (NodeKind::Fragment, TokenType::FragmentClose) => true,
+                (NodeKind::HTMLExpr, TokenType::HTMLExprEnd) => true,
+                _ => false,
+            });
+    }
+
+    fn open(&mut self, kind: NodeKind, name: Option<String>) {
+        self.open.push(OpenNode {
+            node: SyntaxNode::new(kind, self.position),
+            name,
+        });
+    }
+
+    fn close(&mut self) {
+        let closed = self.open.pop().unwrap().node;
+        self.current().push(SyntaxElement::Node(closed));
+    }
+
+    fn current(&mut self) -> &mut SyntaxNode {
+        return &mut self.open.last_mut().unwrap().node;
+    }
+
+    fn token(&mut self, token: &Token) {
+        // Tokens following an HTMLExprEnd can start inside it
+        let start = self.position.max(token.span.start);
+        if start >= token.span.end {
+            return;
+        }
+        self.push(TokenKind::Token(token.kind), start, token.span.end);
+    }
+
+    // The source between the last token and `end`, split into whitespace and skipped text
+    fn trivia(&mut self, end: usize) {
+        while self.position < end {
+            let rest = &self.source[self.position..end];
+            let first_is_whitespace = rest.starts_with(char::is_whitespace);
+            let length = rest
+                .find(|c: char| c.is_whitespace() != first_is_whitespace)
+                .unwrap_or(rest.len());
+
+            let kind = match first_is_whitespace {
+                true => TokenKind::Whitespace,
+                false => TokenKind::Skipped,
+            };
+            self.push(kind, self.position, self.position + length);
+        }
+    }
+
+    fn push(&mut self, kind: TokenKind, start: usize, end: usize) {
+        let token = SyntaxToken {
+            kind,
+            text: self.source[start..end].to_string(),
+            span: Span::new(start, end),
+        };
+        self.position = end;
+        self.current().push(SyntaxElement::Token(token));
+    }
+}
+
+fn is_attribute(token: &Token) -> bool {
+    return matches!(
+        token.kind,
+        TokenType::TextToken
+            | TokenType::Expression
+            | TokenType::SpreadAttribute
+            | TokenType::ShorthandAttribute
+            | TokenType::BooleanAttribute
+            | TokenType::ClientDirective
+    );
+}
@@ -0,0 +1,328 @@
+// Finds the names JavaScript code declares, without fully parsing it. Handles
+// `const`/`let`/`var` (including destructuring), `function`, `class`, `import`
+// and arrow function parameters, which covers what frontmatter and expressions use.
+
+use crate::codes;
+use crate::diagnostic::Diagnostic;
+use crate::expr::{Lexer, Token, TokenKind};
+use crate::span::Span;
+use crate::token::TokenStream;
+use crate::token_type::TokenType;
+
+/// A declared name and where it is, `span` counts bytes from the start of the code
+#[derive(Debug, Clone)]
+pub struct Declaration {
+    pub name: String,
+    pub kind: DeclarationKind,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeclarationKind {
+    Variable,  // const, let or var
+    Function,  // function foo() {}
+    Class,     // class Foo {}
+    Import,    // import Foo from "..."
+    Parameter, // (foo) => ..., only visible inside the arrow function
+}
+
+/// An `import` statement's names and the module they come from
+#[derive(Debug, Clone)]
+pub struct Import {
+    pub names: Vec<String>,
+    pub source: String, // `./Card.regg` in `import Card from "./Card.regg"`
+    pub span: Span,     // of the source with its quotes, in bytes from the start of the code
+}
+
+/// Names declared by `code`, in the order they appear
+pub fn declared_names(code: &str) -> Vec<String> {
+    return declarations(code)
+        .into_iter()
+        .map(|declaration| declaration.name)
+        .collect();
+}
+
+/// Names declared by `code` and where they're declared, in the order they appear
+pub fn declarations(code: &str) -> Vec<Declaration> {
+    let tokens = Lexer::new(code).tokenize();
+    let mut declarations = Vec::new();
+
+    for (i, kind) in declared(&tokens) {
+        declarations.push(Declaration {
+            name: tokens[i].text.clone(),
+            kind,
+            span: tokens[i].span,
+        });
+    }
+
+    return declarations;
+}
+
+// The indices of the tokens that are declared names, and what declares them
+fn declared(tokens: &[Token]) -> Vec<(usize, DeclarationKind)> {
+    let mut names = Vec::new();
+    let after =
+        |i: usize, kind, indices: Vec<usize>| indices.into_iter().map(move |j| (i + 1 + j, kind));
+
+    for (i, token) in tokens.iter().enumerate() {
+        let rest = &tokens[i + 1..];
+        match token.kind {
+            TokenKind::Const | TokenKind::Let | TokenKind::Var => match rest.first() {
+                Some(open) if open.text == "{" || open.kind == TokenKind::LeftBracket => {
+                    names.extend(after(i, DeclarationKind::Variable, destructured(rest)))
+                }
+                Some(name) if is_name(name) => names.push((i + 1, DeclarationKind::Variable)),
+                _ => {}
+            },
+            TokenKind::Function if rest.first().is_some_and(is_name) => {
+                names.push((i + 1, DeclarationKind::Function))
+            }
+            TokenKind::Class if rest.first().is_some_and(is_name) => {
+                names.push((i + 1, DeclarationKind::Class))
+            }
+            TokenKind::Import => names.extend(after(i, DeclarationKind::Import, imported(rest))),
+            TokenKind::Arrow => names.extend(
+                arrow_parameters(&tokens[..i])
+                    .into_iter()
+                    .map(|j| (j, DeclarationKind::Parameter)),
+            ),
+            _ => {}
+        }
+    }
+
+    return names;
+}
+
+/// Where `code` uses or declares `name`, without properties like `user.name` or the keys
+/// of object literals like `{ name: 1 }`
+pub fn references(code: &str, name: &str) -> Vec<Span> {
+    let tokens = Lexer::new(code).tokenize();
+    let mut spans = Vec::new();
+
+    for (i, token) in tokens.iter().enumerate() {
+        if token.text != name {
+            continue;
+        }
+        let previous = i.checked_sub(1).map(|i| &tokens[i]);
+        let next = tokens.get(i + 1).map(|token| token.text.as_str());
+        if previous.is_some_and(|previous| {
+            matches!(previous.kind, TokenKind::Dot | TokenKind::QuestionDot)
+        }) {
+            continue;
+        }
+        let key_start = previous
+            .is_some_and(|previous| previous.text == "{" || previous.kind == TokenKind::Comma);
+        if key_start && next == Some(":") {
+            continue;
+        }
+        spans.push(token.span);
+    }
+
+    return spans;
+}
+
+/// Whether `name` can be declared, like `title` or `$store`
+pub fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    return chars
+        .next()
+        .is_some_and(|c| is_name_char(c) && !c.is_ascii_digit())
+        && chars.all(is_name_char);
+}
+
+/// The `import` statements in `code`, in the order they appear
+pub fn imports(code: &str) -> Vec<Import> {
+    let tokens = Lexer::new(code).tokenize();
+    let mut imports = Vec::new();
+
+    for (i, token) in tokens.iter().enumerate() {
+        if token.kind != TokenKind::Import {
+            continue;
+        }
+
+        let rest = &tokens[i + 1..];
+        let (names, source) = match rest.first() {
+            Some(open) if open.kind == TokenKind::LeftParen => continue, // `import("./module.js")`
+            // `import "./styles.css"`
+            Some(first) if first.kind == TokenKind::String => (Vec::new(), Some(first)),
+            _ => {
+                let from = rest.iter().position(|token| token.text == "from");
+                let source = from.and_then(|from| rest.get(from + 1));
+                let names = imported(rest).into_iter().map(|i| rest[i].text.clone());
+                (names.collect(), source)
+            }
+        };
+
+        let Some(source) = source else {
+            continue;
+        };
+        if let Some(text) = string(source) {
+            imports.push(Import {
+                names,
+                source: text.to_string(),
+                span: source.span,
+            });
+        }
+    }
+
+    return imports;
+}
+
+/// A warning for each name the frontmatter imports that neither the frontmatter nor the
+/// template uses
+pub fn unused_imports(tokens: &TokenStream) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+
+    for block in tokens.iter() {
+        if !matches!(block.kind, TokenType::CodeBlock) {
+            continue;
+        }
+        let code = tokens.literal_str(block).unwrap_or_default();
+        let code_start = block.span.start + "---".len();
+
+        for declaration in declarations(&code) {
+            if declaration.kind != DeclarationKind::Import {
+                continue;
+            }
+            let name = declaration.name.as_str();
+            // the import itself is one of them
+            if references(&code, name).len() > 1 || used_in_template(tokens, name) {
+                continue;
+            }
+
+            let span = Span::new(
+                code_start + declaration.span.start,
+                code_start + declaration.span.end,
+            );
+            let line = 1 + tokens.source()[..span.start].matches('\n').count();
+            let message = format!("`{}` is imported but never used", name);
+            let diagnostic = Diagnostic::warning(line, &message)
+                .with_code(codes::UNUSED_IMPORT)
+                .with_span(span)
+                .with_help("remove it from the import");
+            diagnostics.push(diagnostic);
+        }
+    }
+
+    return diagnostics;
+}
+
+// `<Card>`, `<Icons.Star>` or `name` in the code of an expression or attribute
+fn used_in_template(tokens: &TokenStream, name: &str) -> bool {
+    return tokens.iter().any(|token| match token.kind {
+        TokenType::OpeningTagStart => {
+            let tag = tokens.literal_str(token).unwrap_or_default();
+            tag == name || tag.split('.').next() == Some(name)
+        }
+        TokenType::Expression
+        | TokenType::SpreadAttribute
+        | TokenType::ShorthandAttribute
+        | TokenType::DynamicTagStart => {
+            let code = tokens.literal_str(token).unwrap_or_default();
+            !references(&code, name).is_empty()
+        }
+        _ => false,
+    });
+}
+
+// `{ a, b: c, ...d }` or `[a, , b]` at the start of `tokens`, declares `a`, `c` and `d`
+fn destructured(tokens: &[Token]) -> Vec<usize> {
+    let mut names = Vec::new();
+    let mut depth = 0;
+
+    for (i, token) in tokens.iter().enumerate() {
+        match token.text.as_str() {
+            "{" | "[" => depth += 1,
+            "}" | "]" => {
+                depth -= 1;
+                if depth == 0 {
+                    break;
+                }
+            }
+            _ if is_name(token) => {
+                // `b` in `{ b: c }` is a property, `c` in `{ c = 1 }` has a default
+                let next = tokens.get(i + 1).map(|token| token.text.as_str());
+                let previous = tokens[..i].last().map(|token| token.text.as_str());
+                if next != Some(":") && previous != Some("=") {
+                    names.push(i);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    return names;
+}
+
+// `Foo, { bar, baz as qux } from "..."` or `* as foo from "..."`, declares `Foo`, `bar`, `qux` and `foo`
+fn imported(tokens: &[Token]) -> Vec<usize> {
+    let mut names = Vec::new();
+
+    for (i, token) in tokens.iter().enumerate() {
+        match token.text.as_str() {
+            "from" | ";" => break,
+            // `baz` in `baz as qux` isn't declared, only `qux` is
+            name if is_name(token)
+                && name != "as"
+                && tokens.get(i + 1).map(|token| token.text.as_str()) != Some("as") =>
+            {
+                names.push(i);
+            }
+            _ => {}
+        }
+    }
+
+    return names;
+}
+
+// The parameters of an arrow function whose `=>` follows `tokens`
+fn arrow_parameters(tokens: &[Token]) -> Vec<usize> {
+    match tokens.last() {
+        Some(close) if close.kind == TokenKind::RightParen => {
+            let mut depth = 0;
+            for (i, token) in tokens.iter().enumerate().rev() {
+                match token.kind {
+                    TokenKind::RightParen => depth += 1,
+                    TokenKind::LeftParen => {
+                        depth -= 1;
+                        if depth == 0 {
+                            let parameters = &tokens[i + 1..tokens.len() - 1];
+                            return parameters
+                                .iter()
+                                .enumerate()
+                                .filter(|(j, name)| {
+                                    is_name(name)
+                                        && parameters.get(j + 1).map(|token| token.text.as_str())
+                                            != Some(":")
+                                        && (*j == 0 || parameters[j - 1].text != "=")
+                                })
+                                .map(|(j, _)| i + 1 + j)
+                                .collect();
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            return Vec::new();
+        }
+        Some(name) if is_name(name) => return vec![tokens.len() - 1],
+        _ => return Vec::new(),
+    }
+}
+
+// The content of a string token, `None` for other tokens or an unterminated string
+fn string(token: &Token) -> Option<&str> {
+    if token.kind != TokenKind::String {
+        return None;
+    }
+    let quote = token.text.chars().next()?;
+    return token.text[1..].strip_suffix(quote);
+}
+
+fn is_name(token: &Token) -> bool {
+    return token.kind == TokenKind::Identifier;
+}
+
+fn is_name_char(c: char) -> bool {
+    return c.is_alphanumeric() || c == '_' || c == '$';
+}
@@ -0,0 +1,219 @@
+use std::{
+    env, fmt,
+    io::{self, IsTerminal},
+    str::FromStr,
+};
+
+use serde_json::{json, Value};
+
+use crate::span::Span;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,   // the template is invalid, `Regg` exits with an error
+    Warning, // the template works but is likely not what was meant
+}
+
+/// How the CLI prints diagnostics, `human` by default
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ErrorFormat {
+    #[default]
+    Human,
+    Json, // one JSON object per line, for CI systems and editors
+}
+
+/// Whether the CLI prints diagnostics in color, `auto` by default
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ColorChoice {
+    #[default]
+    Auto, // when stderr is a terminal and `NO_COLOR` isn't set
+    Always,
+    Never,
+}
+
+/// An error or warning found in a template
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub code: Option<&'static str>, // like `E0002`, `regg explain` describes it
+    pub line: usize,
+    pub message: String,
+    pub span: Option<Span>,
+    pub labels: Vec<Label>, // other places in the source the diagnostic is about
+    pub help: Option<String>,
+}
+
+/// A secondary location of a diagnostic, e.g. where an unclosed tag was opened
+#[derive(Debug, Clone)]
+pub struct Label {
+    pub line: usize,
+    pub span: Span,
+    pub message: String,
+}
+
+impl Diagnostic {
+    pub fn error(line: usize, message: &str) -> Self {
+        Self::new(Severity::Error, line, message)
+    }
+
+    pub fn warning(line: usize, message: &str) -> Self {
+        Self::new(Severity::Warning, line, message)
+    }
+
+    fn new(severity: Severity, line: usize, message: &str) -> Self {
+        Self {
+            severity,
+            code: None,
+            line,
+            message: message.to_string(),
+            span: None,
+            labels: Vec::new(),
+            help: None,
+        }
+    }
+
+    pub fn with_code(mut self, code: &'static str) -> Self {
+        self.code = Some(code);
+        self
+    }
+
+    pub fn with_span(mut self, span: Span) -> Self {
+        self.span = Some(span);
+        self
+    }
+
+    pub fn with_label(mut self, line: usize, span: Span, message: &str) -> Self {
+        self.labels.push(Label {
+            line,
+            span,
+            message: message.to_string(),
+        });
+        self
+    }
+
+    pub fn with_help(mut self, help: &str) -> Self {
+        self.help = Some(help.to_string());
+        self
+    }
+
+    /// Moves the diagnostic and its labels `bytes` and `lines` further, after an edit before them
+    pub fn shift(&mut self, bytes: isize, lines: isize) {
+        let shift = |span: Span| {
+            let start = (span.start as isize + bytes) as usize;
+            return Span::new(start, (span.end as isize + bytes) as usize);
+        };
+        self.line = (self.line as isize + lines) as usize;
+        self.span = self.span.map(shift);
+        for label in &mut self.labels {
+            label.line = (label.line as isize + lines) as usize;
+            label.span = shift(label.span);
+        }
+    }
+
+    /// The diagnostic as a JSON object, for tools that read diagnostics
+    pub fn to_json(&self) -> Value {
+        let labels: Vec<Value> = self
+            .labels
+            .iter()
+            .map(|label| {
+                json!({
+                    "line": label.line,
+                    "span": span_json(label.span),
+                    "message": label.message,
+                })
+            })
+            .collect();
+
+        return json!({
+            "severity": self.severity.to_string(),
+            "line": self.line,
+            "span": self.span.map(span_json),
+            "message": self.message,
+            "labels": labels,
+            "code": self.code,
+            "suggestion": self.help,
+        });
+    }
+}
+
+impl FromStr for ErrorFormat {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name.to_ascii_lowercase().as_str() {
+            "human" => Ok(ErrorFormat::Human),
+            "json" => Ok(ErrorFormat::Json),
+            _ => Err(format!(
+                "unknown error format `{name}`, expected `human` or `json`"
+            )),
+        }
+    }
+}
+
+// `error[E0008]: Mismatched closing tag ... (line 3)`, on one line without the source
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.code {
+            Some(code) => write!(f, "{}[{}]", self.severity, code)?,
+            None => write!(f, "{}", self.severity)?,
+        }
+        write!(f, ": {} (line {})", self.message, self.line)
+    }
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+        }
+    }
+}
+
+impl fmt::Display for ErrorFormat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ErrorFormat::Human => write!(f, "human"),
+            ErrorFormat::Json => write!(f, "json"),
+        }
+    }
+}
+
+fn span_json(span: Span) -> Value {
+    return json!({ "start": span.start, "end": span.end });
+}
+
+impl ColorChoice {
+    pub fn enabled(&self) -> bool {
+        match self {
+            ColorChoice::Auto => io::stderr().is_terminal() && env::var_os("NO_COLOR").is_none(),
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+        }
+    }
+}
+
+impl FromStr for ColorChoice {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name.to_ascii_lowercase().as_str() {
+            "auto" => Ok(ColorChoice::Auto),
+            "always" => Ok(ColorChoice::Always),
+            "never" => Ok(ColorChoice::Never),
+            _ => Err(format!(
+                "unknown color choice `{name}`, expected `auto`, `always` or `never`"
+            )),
+        }
+    }
+}
+
+impl fmt::Display for ColorChoice {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ColorChoice::Auto => write!(f, "auto"),
+            ColorChoice::Always => write!(f, "always"),
+            ColorChoice::Never => write!(f, "never"),
+        }
+    }
+}
@@ -0,0 +1,95 @@
+use std::{collections::HashMap, path::Path, sync::Arc};
+
+use crate::{print_line, Regg};
+
+/// Processes one file in `Regg::run_file`, given its path and raw bytes, which it owns
+pub type Handler = fn(&mut Regg, &str, Vec<u8>);
+
+/// Picks the pipeline a file goes through based on its extension.
+///
+/// Extensions are matched case-insensitively, files with an unknown extension are
+/// treated as raw assets.
+#[derive(Clone)]
+pub struct Dispatcher {
+    handlers: HashMap<String, Handler>,
+    fallback: Handler,
+}
+
+impl Dispatcher {
+    pub fn new() -> Self {
+        let mut dispatcher = Self {
+            handlers: HashMap::new(),
+            fallback: asset,
+        };
+
+        dispatcher.register("regg", template);
+        dispatcher.register("html", template); // HTML is a template without frontmatter
+        dispatcher.register("htm", template);
+        #[cfg(feature = "markdown")]
+        {
+            dispatcher.register("md", crate::markdown::markdown);
+            dispatcher.register("markdown", crate::markdown::markdown);
+        }
+        #[cfg(not(feature = "markdown"))]
+        {
+            dispatcher.register("md", asset); // built with `--features markdown` only
+            dispatcher.register("markdown", asset);
+        }
+
+        return dispatcher;
+    }
+
+    /// Routes files ending in `.{extension}` to `handler`, replacing any earlier handler
+    pub fn register(&mut self, extension: &str, handler: Handler) {
+        let extension = extension.trim_start_matches('.').to_ascii_lowercase();
+        self.handlers.insert(extension, handler);
+    }
+
+    /// Whether a handler is registered for the path's extension, rather than the fallback
+    pub fn handles(&self, path: &Path) -> bool {
+        return path.extension().is_some_and(|extension| {
+            let extension = extension.to_string_lossy().to_ascii_lowercase();
+            self.handlers.contains_key(&extension)
+        });
+    }
+
+    /// Whether the file is run as a template, like the ones `regg fmt` formats
+    pub fn is_template(&self, path: &Path) -> bool {
+        return std::ptr::fn_addr_eq(self.handler_for(path), template as Handler);
+    }
+
+    pub fn handler_for(&self, path: &Path) -> Handler {
+        let extension = path
+            .extension()
+            .map(|extension| extension.to_string_lossy().to_ascii_lowercase());
+
+        return match extension {
+            Some(extension) => *self.handlers.get(&extension).unwrap_or(&self.fallback),
+            None => self.fallback,
+        };
+    }
+}
+
+impl Default for Dispatcher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Template source: decoded and run through the scanner
+pub fn template(regg: &mut Regg, _path: &str, bytes: Vec<u8>) {
+    let content = regg.decode_owned(bytes);
+    regg.run_shared(Arc::new(content));
+}
+
+/// Raw asset: left as-is
+pub fn asset(regg: &mut Regg, path: &str, bytes: Vec<u8>) {
+    if !regg.prints_tokens() {
+        return; // nothing to check in an asset
+    }
+    print_line(format_args!(
+        "Asset {{ path: {:?}, bytes: {} }}",
+        path,
+        bytes.len()
+    ));
+}
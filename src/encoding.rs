@@ -0,0 +1,97 @@
+use std::{borrow::Cow, fmt, str::FromStr};
+
+use crate::span::Span;
+
+/// Character encoding `Regg::run_file` decodes source files with
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Encoding {
+    #[default]
+    Utf8,
+    Latin1, // ISO-8859-1, every byte is the code point of the same value
+}
+
+/// A byte sequence that is not valid in the source's encoding
+#[derive(Debug)]
+pub struct InvalidSequence {
+    pub offset: usize, // byte offset in the undecoded source
+    pub line: usize,
+    pub bytes: Vec<u8>,
+    pub span: Span, // the `U+FFFD` it was replaced with in the decoded source
+}
+
+impl Encoding {
+    /// Decodes `bytes`, replacing invalid sequences with `U+FFFD` and returning where they were.
+    /// Valid UTF-8 is borrowed, not copied.
+    pub fn decode<'a>(&self, bytes: &'a [u8]) -> (Cow<'a, str>, Vec<InvalidSequence>) {
+        match self {
+            Encoding::Latin1 => (
+                Cow::Owned(bytes.iter().map(|&byte| byte as char).collect()),
+                Vec::new(),
+            ),
+            Encoding::Utf8 => {
+                if let Ok(text) = std::str::from_utf8(bytes) {
+                    return (Cow::Borrowed(text), Vec::new());
+                }
+
+                let mut decoded = String::with_capacity(bytes.len());
+                let mut invalid = Vec::new();
+                let mut offset = 0;
+                let mut line = 1;
+
+                for chunk in bytes.utf8_chunks() {
+                    decoded.push_str(chunk.valid());
+                    offset += chunk.valid().len();
+                    line += chunk.valid().matches('\n').count();
+
+                    if !chunk.invalid().is_empty() {
+                        let start = decoded.len();
+                        decoded.push(char::REPLACEMENT_CHARACTER);
+                        invalid.push(InvalidSequence {
+                            offset,
+                            line,
+                            bytes: chunk.invalid().to_vec(),
+                            span: Span::new(start, decoded.len()),
+                        });
+                        offset += chunk.invalid().len();
+                    }
+                }
+
+                (Cow::Owned(decoded), invalid)
+            }
+        }
+    }
+}
+
+impl Encoding {
+    /// Encodes text decoded with this encoding back into bytes, for `regg fmt`
+    pub fn encode(&self, text: &str) -> Vec<u8> {
+        match self {
+            // `decode` only produces code points up to U+00FF
+            Encoding::Latin1 => text.chars().map(|c| c as u32 as u8).collect(),
+            Encoding::Utf8 => text.as_bytes().to_vec(),
+        }
+    }
+}
+
+impl FromStr for Encoding {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name.to_ascii_lowercase().as_str() {
+            "utf-8" | "utf8" => Ok(Encoding::Utf8),
+            "latin-1" | "latin1" | "iso-8859-1" => Ok(Encoding::Latin1),
+            _ => Err(format!(
+                "unknown encoding `{name}`, expected `utf-8` or `latin-1`"
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Encoding {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Encoding::Utf8 => write!(f, "utf-8"),
+            Encoding::Latin1 => write!(f, "latin-1"),
+        }
+    }
+}
@@ -0,0 +1,11 @@
+// Exit codes of the `regg` binary, the non-zero ones follow sysexits.h
+// https://man.freebsd.org/cgi/man.cgi?query=sysexits
+
+pub const OK: i32 = 0;
+pub const TOO_MANY_WARNINGS: i32 = 1; // more warnings than `--max-warnings` allows
+pub const UNFORMATTED: i32 = 1; // `regg fmt --check` found files that would be reformatted
+pub const USAGE_ERROR: i32 = 64; // EX_USAGE: `regg explain` was given an unknown code
+pub const SOURCE_ERROR: i32 = 65; // EX_DATAERR: the template has errors
+pub const IO_ERROR: i32 = 66; // EX_NOINPUT: the file can't be read
+pub const INTERNAL_ERROR: i32 = 70; // EX_SOFTWARE: Regg itself crashed
+pub const CONFIG_ERROR: i32 = 78; // EX_CONFIG: `regg.toml` is invalid
@@ -0,0 +1,281 @@
+// Tokens of the JavaScript inside `{ ... }` and in the frontmatter, for the parser and anything
+// that has to look into code. The lexer never fails: unknown characters and unterminated strings are tokens
+// too, so the tokens always cover the whole expression except whitespace and comments.
+
+use crate::span::Span;
+use crate::token::Literal;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenKind {
+    Identifier, // `title`, `$store`, `flag`
+    Number,     // `42`, `1.5e3`, `0xff`
+    Boolean,    // `true` or `false`
+    Null,       // `null`
+    String,     // `"a"`, `'a'` or `` `a` ``, with the quotes
+
+    // Keywords that declare names, other keywords are identifiers
+    Import,   // `import`
+    Export,   // `export`
+    Const,    // `const`
+    Let,      // `let`
+    Var,      // `var`
+    Function, // `function`
+    Class,    // `class`
+    Arrow,    // `=>`
+
+    // Operators, by precedence from loosest
+    QuestionQuestion, // `??`
+    OrOr,             // `||`
+    AndAnd,           // `&&`
+    EqualEqual,       // `==`
+    BangEqual,        // `!=`
+    Less,             // `<`
+    Greater,          // `>`
+    LessEqual,        // `<=`
+    GreaterEqual,     // `>=`
+    Plus,             // `+`
+    Minus,            // `-`
+    Star,             // `*`
+    Slash,            // `/`
+    Percent,          // `%`
+    Bang,             // `!`
+    QuestionDot,      // `?.`
+    Dot,              // `.`
+    Operator,         // any other, `===`, `...`, `?`, `:`
+
+    // Punctuation
+    Comma,        // `,`
+    LeftParen,    // `(`
+    RightParen,   // `)`
+    LeftBracket,  // `[`
+    RightBracket, // `]`
+    Punctuation,  // any other, `{`, `}`, `;`
+
+    Unknown, // `#`, `@` and other characters JavaScript doesn't have
+}
+
+#[derive(Debug, Clone)]
+pub struct Token {
+    pub kind: TokenKind,
+    pub text: String,
+    pub span: Span, // from the start of the expression, in bytes
+    pub literal: Option<Literal<'static>>, // the value of numbers, booleans and `null`
+}
+
+// Longest first, so `===` isn't `==` then `=`
+const OPERATORS: &[&str] = &[
+    ">>>=", "...", "===", "!==", "**=", "<<=", ">>=", ">>>", "&&=", "||=", "??=", "=>", "==", "!=",
+    "<=", ">=", "&&", "||", "??", "?.", "++", "--", "+=", "-=", "*=", "/=", "%=", "&=", "|=", "^=",
+    "**", "<<", ">>", "+", "-", "*", "/", "%", "<", ">", "=", "!", "~", "&", "|", "^", "?", ":",
+    ".",
+];
+
+pub struct Lexer<'a> {
+    source: &'a str,
+    current: usize,
+}
+
+impl<'a> Lexer<'a> {
+    pub fn new(source: &'a str) -> Self {
+        Self { source, current: 0 }
+    }
+
+    /// Every token of the expression, in order
+    pub fn tokenize(self) -> Vec<Token> {
+        return self.collect();
+    }
+
+    fn skip_whitespace_and_comments(&mut self) {
+        loop {
+            let rest = self.rest();
+            let trimmed = rest.trim_start();
+            self.current += rest.len() - trimmed.len();
+
+            if trimmed.starts_with("//") {
+                self.current += trimmed.find('\n').unwrap_or(trimmed.len());
+            } else if let Some(comment) = trimmed.strip_prefix("/*") {
+                self.current += match comment.find("*/") {
+                    Some(end) => "/*".len() + end + "*/".len(),
+                    None => trimmed.len(),
+                };
+            } else {
+                return;
+            }
+        }
+    }
+
+    fn rest(&self) -> &'a str {
+        return &self.source[self.current..];
+    }
+}
+
+impl Iterator for Lexer<'_> {
+    type Item = Token;
+
+    fn next(&mut self) -> Option<Token> {
+        self.skip_whitespace_and_comments();
+        let rest = self.rest();
+        let c = rest.chars().next()?;
+
+        let (length, kind) = token_length(rest, c);
+        let text = &rest[..length];
+        let (kind, literal) = match (kind, text) {
+            (TokenKind::Number, _) => (kind, Some(Literal::Number(number(text)))),
+            (TokenKind::Identifier, "true") => (TokenKind::Boolean, Some(Literal::Bool(true))),
+            (TokenKind::Identifier, "false") => (TokenKind::Boolean, Some(Literal::Bool(false))),
+            (TokenKind::Identifier, "null") => (TokenKind::Null, Some(Literal::Null)),
+            (TokenKind::Identifier, _) => (keyword(text).unwrap_or(kind), None),
+            _ => (kind, None),
+        };
+
+        let start = self.current;
+        self.current += length;
+        return Some(Token {
+            kind,
+            text: text.to_string(),
+            span: Span::new(start, self.current),
+            literal,
+        });
+    }
+}
+
+// The length of the token at the start of `rest` and what it is
+fn token_length(rest: &str, c: char) -> (usize, TokenKind) {
+    if is_identifier_start(c) {
+        let end = rest.find(|c| !is_identifier_char(c)).unwrap_or(rest.len());
+        return (end, TokenKind::Identifier);
+    }
+
+    if c.is_ascii_digit() || (c == '.' && rest[1..].starts_with(|c: char| c.is_ascii_digit())) {
+        return (number_length(rest), TokenKind::Number);
+    }
+
+    if matches!(c, '"' | '\'' | '`') {
+        return (string_length(rest, c), TokenKind::String);
+    }
+
+    if matches!(c, '(' | ')' | '[' | ']' | '{' | '}' | ',' | ';') {
+        let kind = match c {
+            ',' => TokenKind::Comma,
+            '(' => TokenKind::LeftParen,
+            ')' => TokenKind::RightParen,
+            '[' => TokenKind::LeftBracket,
+            ']' => TokenKind::RightBracket,
+            _ => TokenKind::Punctuation,
+        };
+        return (1, kind);
+    }
+
+    // `a?.5:1` is a conditional, not optional chaining
+    let operator = OPERATORS.iter().find(|operator| {
+        rest.starts_with(*operator)
+            && !(**operator == "?." && rest[2..].starts_with(|c: char| c.is_ascii_digit()))
+    });
+    return match operator {
+        Some(operator) => (operator.len(), operator_kind(operator)),
+        None => (c.len_utf8(), TokenKind::Unknown),
+    };
+}
+
+// `42`, `1.5`, `.5e-3` or `0xff`, and `1_000`
+fn number_length(text: &str) -> usize {
+    let bytes = text.as_bytes();
+    let digits = |mut i: usize, radix: u32| {
+        while i < bytes.len() && ((bytes[i] as char).is_digit(radix) || bytes[i] == b'_') {
+            i += 1;
+        }
+        return i;
+    };
+
+    if bytes.len() > 2 && bytes[0] == b'0' && matches!(bytes[1] | 0x20, b'x' | b'o' | b'b') {
+        let radix = match bytes[1] | 0x20 {
+            b'x' => 16,
+            b'o' => 8,
+            _ => 2,
+        };
+        return digits(2, radix);
+    }
+
+    let mut i = digits(0, 10);
+    if bytes.get(i) == Some(&b'.') {
+        i = digits(i + 1, 10);
+    }
+    if matches!(bytes.get(i), Some(b'e' | b'E')) {
+        let sign = usize::from(matches!(bytes.get(i + 1), Some(b'+' | b'-')));
+        if bytes.get(i + 1 + sign).is_some_and(u8::is_ascii_digit) {
+            i = digits(i + 1 + sign, 10);
+        }
+    }
+    return i;
+}
+
+fn keyword(text: &str) -> Option<TokenKind> {
+    return match text {
+        "import" => Some(TokenKind::Import),
+        "export" => Some(TokenKind::Export),
+        "const" => Some(TokenKind::Const),
+        "let" => Some(TokenKind::Let),
+        "var" => Some(TokenKind::Var),
+        "function" => Some(TokenKind::Function),
+        "class" => Some(TokenKind::Class),
+        _ => None,
+    };
+}
+
+fn operator_kind(operator: &str) -> TokenKind {
+    return match operator {
+        "??" => TokenKind::QuestionQuestion,
+        "||" => TokenKind::OrOr,
+        "&&" => TokenKind::AndAnd,
+        "==" => TokenKind::EqualEqual,
+        "!=" => TokenKind::BangEqual,
+        "<" => TokenKind::Less,
+        ">" => TokenKind::Greater,
+        "<=" => TokenKind::LessEqual,
+        ">=" => TokenKind::GreaterEqual,
+        "+" => TokenKind::Plus,
+        "-" => TokenKind::Minus,
+        "*" => TokenKind::Star,
+        "/" => TokenKind::Slash,
+        "%" => TokenKind::Percent,
+        "!" => TokenKind::Bang,
+        "?." => TokenKind::QuestionDot,
+        "." => TokenKind::Dot,
+        "=>" => TokenKind::Arrow,
+        _ => TokenKind::Operator,
+    };
+}
+
+// The value of a number `number_length` found
+fn number(text: &str) -> f64 {
+    let text = text.replace('_', "");
+    let radix = match text.get(..2).map(str::to_ascii_lowercase).as_deref() {
+        Some("0x") => 16,
+        Some("0o") => 8,
+        Some("0b") => 2,
+        _ => return text.parse().unwrap_or(f64::NAN),
+    };
+    return u64::from_str_radix(&text[2..], radix).map_or(f64::NAN, |number| number as f64);
+}
+
+// Up to and with the closing quote, or the whole text if there's none
+fn string_length(text: &str, quote: char) -> usize {
+    let mut escaped = false;
+    for (i, c) in text.char_indices().skip(1) {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            _ if c == quote => return i + c.len_utf8(),
+            _ => {}
+        }
+    }
+    return text.len();
+}
+
+fn is_identifier_start(c: char) -> bool {
+    return c.is_alphabetic() || c == '_' || c == '$';
+}
+
+fn is_identifier_char(c: char) -> bool {
+    return c.is_alphanumeric() || c == '_' || c == '$';
+}
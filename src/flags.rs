@@ -0,0 +1,98 @@
+// Build-time feature flags for `{#if flag("beta")} ... {/if}` blocks, read from the
+// `[flags]` table of `regg.toml` and the `REGG_FLAGS` environment variable.
+
+use std::{collections::HashMap, env, fs, io, path::Path};
+
+pub const CONFIG_FILE: &str = "regg.toml";
+pub const ENV_VAR: &str = "REGG_FLAGS";
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Flags {
+    values: HashMap<String, bool>,
+}
+
+impl Flags {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Reads the flags from `regg.toml` in `dir`, if there is one, then from `REGG_FLAGS`.
+    ///
+    /// `REGG_FLAGS=beta,-new-nav` turns `beta` on and `new-nav` off, overriding `regg.toml`.
+    pub fn load(dir: &Path) -> Result<Self, String> {
+        let mut flags = Self::new();
+
+        let path = dir.join(CONFIG_FILE);
+        match fs::read(&path) {
+            Ok(bytes) => {
+                let config = String::from_utf8(bytes).map_err(|error| {
+                    let valid = &error.as_bytes()[..error.utf8_error().valid_up_to()];
+                    let line = 1 + valid.iter().filter(|&&byte| byte == b'\n').count();
+                    format!(
+                        "{}:{}: invalid UTF-8 at byte {}, TOML files are always UTF-8",
+                        path.display(),
+                        line,
+                        valid.len()
+                    )
+                })?;
+                flags
+                    .read_config(&config)
+                    .map_err(|error| format!("{}: {}", path.display(), error))?
+            }
+            Err(error) if error.kind() == io::ErrorKind::NotFound => {}
+            Err(error) => return Err(format!("{}: {}", path.display(), error)),
+        }
+
+        if let Ok(value) = env::var(ENV_VAR) {
+            flags.read_list(&value);
+        }
+
+        return Ok(flags);
+    }
+
+    pub fn set(&mut self, name: &str, enabled: bool) {
+        self.values.insert(name.to_string(), enabled);
+    }
+
+    /// Whether the flag is on, `None` if it isn't defined
+    pub fn get(&self, name: &str) -> Option<bool> {
+        return self.values.get(name).copied();
+    }
+
+    // `[flags]` with `name = true` or `name = false` entries, other tables are ignored
+    fn read_config(&mut self, config: &str) -> Result<(), String> {
+        let config = config
+            .parse::<toml::Table>()
+            .map_err(|error| error.to_string())?;
+
+        let Some(flags) = config.get("flags") else {
+            return Ok(());
+        };
+        let Some(flags) = flags.as_table() else {
+            return Err("`flags` has to be a table".to_string());
+        };
+
+        for (name, value) in flags {
+            match value.as_bool() {
+                Some(enabled) => self.set(name, enabled),
+                None => return Err(format!("flag `{}` has to be true or false", name)),
+            }
+        }
+
+        return Ok(());
+    }
+
+    // `beta,-new-nav`, a leading `-` turns the flag off
+    fn read_list(&mut self, list: &str) {
+        for name in list
+            .split(',')
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+        {
+            match name.strip_prefix('-') {
+                Some(name) => self.set(name, false),
+                None => self.set(name, true),
+            }
+        }
+    }
+}
@@ -0,0 +1,324 @@
+// `regg fmt`: re-indents templates by nesting depth, normalizes the spacing in tags and
+// `{ }` and puts each attribute of an opening tag that doesn't fit on a line on its own
+// line. Code blocks, multi-line expressions and the content of raw text elements like
+// `<pre>` are kept as they are, only whitespace ever changes.
+
+use crate::diagnostic::{Diagnostic, Severity};
+use crate::html::is_void_element;
+use crate::parser::Parser;
+use crate::scanner::{Scanner, BOM};
+use crate::token::{Literal, Token};
+use crate::token_type::TokenType;
+
+const INDENT: &str = "  ";
+const MAX_WIDTH: usize = 100;
+
+// Elements whose content is whitespace-sensitive or not markup
+const RAW_TEXT_ELEMENTS: [&str; 4] = ["pre", "textarea", "script", "style"];
+
+/// Formats a template, templates with errors aren't formatted and their errors are returned
+pub fn format(source: &str) -> Result<String, Vec<Diagnostic>> {
+    let mut scanner = Scanner::new(source);
+    let tokens = scanner.scan_tokens();
+
+    let mut parser = Parser::new(tokens);
+    parser.parse();
+    let mut errors: Vec<Diagnostic> = parser.diagnostics().clone();
+
+    let mut formatter = Formatter {
+        source,
+        tokens,
+        current: 0,
+        depth: 0,
+        end: 0,
+        output: String::new(),
+    };
+    formatter.format();
+    // the byte order mark is trivia to the scanner, keep it
+    let output = match source.starts_with(BOM) {
+        true => format!("{}{}", BOM, formatter.output),
+        false => formatter.output,
+    };
+
+    errors.splice(0..0, scanner.diagnostics().iter().cloned());
+    errors.retain(|diagnostic| matches!(diagnostic.severity, Severity::Error));
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+
+    assert!(
+        without_whitespace(&output) == without_whitespace(source),
+        "formatting changed more than whitespace"
+    );
+    return Ok(output);
+}
+
+struct Formatter<'a> {
+    source: &'a str,
+    tokens: &'a [Token],
+    current: usize,
+    depth: usize,
+    end: usize, // where the source of the last written token ends, without trailing whitespace
+    output: String,
+}
+
+impl<'a> Formatter<'a> {
+    fn format(&mut self) {
+        while self.current < self.tokens.len() {
+            let token = &self.tokens[self.current];
+
+            match token.kind {
+                TokenType::EOF => break,
+                TokenType::OpeningTagStart | TokenType::DynamicTagStart => {
+                    self.separate(token);
+                    self.tag();
+                    continue;
+                }
+                TokenType::ClosingTag | TokenType::FragmentClose | TokenType::HTMLExprEnd => {
+                    self.depth = self.depth.saturating_sub(1);
+                    self.separate(token);
+                    self.write(token);
+                }
+                TokenType::FragmentOpen | TokenType::HTMLExprStart => {
+                    self.separate(token);
+                    self.write(token);
+                    self.depth += 1;
+                }
+                TokenType::Expression => match block(token, self.source) {
+                    "/if" | ":else" => {
+                        self.depth = self.depth.saturating_sub(1);
+                        self.separate(token);
+                        self.write(token);
+                        if block(token, self.source) == ":else" {
+                            self.depth += 1;
+                        }
+                    }
+                    code => {
+                        self.separate(token);
+                        self.write(token);
+                        if code.starts_with("#if") {
+                            self.depth += 1;
+                        }
+                    }
+                },
+                _ => {
+                    self.separate(token);
+                    self.write(token);
+                }
+            }
+
+            self.current += 1;
+        }
+
+        self.trim_line_end();
+        if !self.output.is_empty() {
+            self.output.push('\n');
+        }
+    }
+
+    // An opening tag, on one line if it fits, otherwise with one attribute per line
+    fn tag(&mut self) {
+        let start = &self.tokens[self.current];
+        let opening = match start.kind {
+            TokenType::DynamicTagStart => format!("<{{{}}}", start.literal_string(self.source)),
+            _ => format!("<{}", start.literal_string(self.source)),
+        };
+        self.end = start.span.end;
+        self.current += 1;
+
+        let mut attributes: Vec<String> = Vec::new();
+        let mut end = None;
+        while let Some(token) = self.tokens.get(self.current) {
+            match token.kind {
+                TokenType::OpeningTagEnd | TokenType::SelfClosingTagEnd => {
+                    end = Some(token);
+                    break;
+                }
+                TokenType::TextToken
+                | TokenType::Expression
+                | TokenType::SpreadAttribute
+                | TokenType::ShorthandAttribute
+                | TokenType::BooleanAttribute
+                | TokenType::ClientDirective => {
+                    let text = match token.kind {
+                        TokenType::TextToken
+                        | TokenType::BooleanAttribute
+                        | TokenType::ClientDirective => self.content(token).to_string(),
+                        _ => braces(self.content(token)),
+                    };
+                    // `foo={bar}` is a TextToken `foo=` and an Expression
+                    match attributes.last_mut() {
+                        Some(previous) if previous.ends_with('=') => previous.push_str(&text),
+                        _ => attributes.push(text),
+                    }
+                    self.end = token.span.end;
+                    self.current += 1;
+                }
+                _ => break, // unterminated tag, reported by the parser
+            }
+        }
+
+        let close = match end {
+            Some(token) if matches!(token.kind, TokenType::SelfClosingTagEnd) => "/>",
+            Some(_) => ">",
+            None => "",
+        };
+        let self_closing = close == "/>";
+
+        let mut one_line = opening.clone();
+        for attribute in &attributes {
+            one_line.push(' ');
+            one_line.push_str(attribute);
+        }
+        if self_closing {
+            one_line.push(' ');
+        }
+        one_line.push_str(close);
+
+        let column = self.output.len() - self.output.rfind('\n').map_or(0, |i| i + 1);
+        if attributes.is_empty()
+            || column + one_line.chars().count() <= MAX_WIDTH && !one_line.contains('\n')
+        {
+            self.output.push_str(&one_line);
+        } else {
+            self.output.push_str(&opening);
+            for attribute in &attributes {
+                self.output.push('\n');
+                self.indent(self.depth + 1);
+                self.output.push_str(attribute);
+            }
+            self.output.push('\n');
+            self.indent(self.depth);
+            self.output.push_str(close);
+        }
+
+        let Some(end) = end else {
+            return;
+        };
+        self.end = end.span.end;
+        self.current += 1;
+
+        let name = start.literal_string(self.source);
+        let dynamic = matches!(start.kind, TokenType::DynamicTagStart);
+        if self_closing || (!dynamic && is_void_element(&name)) {
+            return;
+        }
+        self.depth += 1;
+
+        if RAW_TEXT_ELEMENTS
+            .iter()
+            .any(|raw| raw.eq_ignore_ascii_case(&name))
+        {
+            // copy the content as it is, up to the closing tag
+            let closing = self.tokens[self.current..].iter().position(|token| {
+                matches!(token.kind, TokenType::ClosingTag)
+                    && token
+                        .literal_string(self.source)
+                        .eq_ignore_ascii_case(&name)
+            });
+            if let Some(offset) = closing {
+                let closing = &self.tokens[self.current + offset];
+                self.output
+                    .push_str(&self.source[self.end..closing.span.start]);
+                self.end = closing.span.start;
+                self.current += offset;
+            }
+        }
+    }
+
+    // Writes the whitespace between the last token and `token`: a line break (keeping at
+    // most one blank line) and the indentation, a single space, or nothing
+    fn separate(&mut self, token: &Token) {
+        let gap = self
+            .source
+            .get(self.end..token.span.start)
+            .unwrap_or_default();
+        if self.output.is_empty() {
+            return; // leading whitespace
+        }
+
+        let newlines = gap.matches('\n').count();
+        if newlines > 0 {
+            self.trim_line_end();
+            self.output.push('\n');
+            if newlines > 1 {
+                self.output.push('\n');
+            }
+            self.indent(self.depth);
+        } else if !gap.is_empty() {
+            self.output.push(' ');
+        }
+    }
+
+    fn write(&mut self, token: &Token) {
+        let content = self.content(token);
+        self.end = self.start(token) + content.len();
+
+        match token.kind {
+            TokenType::TextToken => {
+                // one line of text per source line, re-indented and with single spaces
+                let mut blank = false;
+                for (i, line) in content.lines().enumerate() {
+                    let line = line.split_whitespace().collect::<Vec<_>>().join(" ");
+                    if i == 0 {
+                        self.output.push_str(&line);
+                    } else if line.is_empty() {
+                        blank = true;
+                    } else {
+                        self.output.push('\n');
+                        if blank {
+                            self.output.push('\n');
+                            blank = false;
+                        }
+                        self.indent(self.depth);
+                        self.output.push_str(&line);
+                    }
+                }
+            }
+            TokenType::Expression => self.output.push_str(&braces(content)),
+            _ => self.output.push_str(content),
+        }
+    }
+
+    // The source of a token that wasn't written yet, without trailing whitespace
+    fn content(&self, token: &Token) -> &'a str {
+        return self.source[self.start(token)..token.span.end].trim_end();
+    }
+
+    // Tokens after an HTMLExprEnd can start inside it
+    fn start(&self, token: &Token) -> usize {
+        return self.end.max(token.span.start).min(token.span.end);
+    }
+
+    fn indent(&mut self, depth: usize) {
+        self.output.push_str(&INDENT.repeat(depth));
+    }
+
+    fn trim_line_end(&mut self) {
+        let len = self.output.trim_end_matches([' ', '\t']).len();
+        self.output.truncate(len);
+    }
+}
+
+// `{  foo }` becomes `{foo}`, multi-line expressions are kept as they are
+fn braces(content: &str) -> String {
+    match content
+        .strip_prefix('{')
+        .and_then(|rest| rest.strip_suffix('}'))
+    {
+        Some(inner) if !inner.contains('\n') => format!("{{{}}}", inner.trim()),
+        _ => content.to_string(),
+    }
+}
+
+// The code of an Expression token, where `{#if ...}` blocks are recognized
+fn block<'a>(token: &Token, source: &'a str) -> &'a str {
+    return match token.literal(source) {
+        Some(Literal::Expr(code)) => code.trim(),
+        _ => "",
+    };
+}
+
+fn without_whitespace(text: &str) -> String {
+    return text.chars().filter(|c| !c.is_whitespace()).collect();
+}
@@ -0,0 +1,278 @@
+// The import graph of templates: which templates a template imports in its frontmatter,
+// following relative imports from file to file. It's built with a worklist instead of
+// recursion and every file is read once, so import cycles can't loop forever or overflow
+// the stack, they're found with `cycle` and reported.
+
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use std::str::FromStr;
+
+use serde_json::{json, Value};
+
+use crate::declarations::imports;
+use crate::scanner::Scanner;
+use crate::span::Span;
+use crate::token_type::TokenType;
+
+/// How `regg graph` prints the graph, `dot` by default
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum GraphFormat {
+    #[default]
+    Dot, // for Graphviz, `regg graph src | dot -Tsvg > graph.svg`
+    Json,
+}
+
+/// Templates and their imports, the entries come first
+#[derive(Debug, Default)]
+pub struct Graph {
+    pub files: Vec<PathBuf>,
+    pub imports: Vec<Vec<Edge>>, // the imports of every file, in source order
+}
+
+/// An import of another template
+#[derive(Debug, Clone)]
+pub struct Edge {
+    pub to: usize,  // the index of the imported file
+    pub span: Span, // of the source string in the importing file
+    pub line: usize,
+}
+
+impl Graph {
+    /// The graph of `entries` and of every template they import, directly or not.
+    /// `is_template` tells which of the imported files are templates, others aren't followed.
+    pub fn build(entries: &[PathBuf], is_template: impl Fn(&Path) -> bool) -> Self {
+        let mut graph = Graph::default();
+        let mut indices: HashMap<PathBuf, usize> = HashMap::new();
+        let mut queue: VecDeque<usize> = VecDeque::new();
+
+        for entry in entries {
+            queue.push_back(graph.add(&mut indices, entry));
+        }
+
+        while let Some(file) = queue.pop_front() {
+            let path = graph.files[file].clone();
+            let Ok(bytes) = fs::read(&path) else {
+                continue; // reported by whoever reads it for real
+            };
+            let source = String::from_utf8_lossy(&bytes);
+            let dir = path.parent().unwrap_or(Path::new(""));
+
+            for (import, span, line) in template_imports(&source) {
+                let Some(imported) = resolve(dir, &import) else {
+                    continue;
+                };
+                if !imported.is_file() || !is_template(&imported) {
+                    continue;
+                }
+
+                let known = indices.len();
+                let to = graph.add(&mut indices, &imported);
+                if to == known {
+                    queue.push_back(to);
+                }
+                graph.imports[file].push(Edge { to, span, line });
+            }
+        }
+
+        return graph;
+    }
+
+    /// The shortest chain of imports from `file` back to itself, starting and ending with
+    /// `file`, `None` if it isn't in a cycle
+    pub fn cycle(&self, file: usize) -> Option<Vec<usize>> {
+        // breadth first from `file`, remembering where every file was reached from
+        let mut previous: Vec<Option<usize>> = vec![None; self.files.len()];
+        let mut queue = VecDeque::from([file]);
+
+        while let Some(current) = queue.pop_front() {
+            for edge in &self.imports[current] {
+                if edge.to == file {
+                    // back from `current` to `file`, the only file without a previous one
+                    let mut chain = vec![current];
+                    let mut at = current;
+                    while let Some(before) = previous[at] {
+                        chain.push(before);
+                        at = before;
+                    }
+                    chain.reverse();
+                    chain.push(file);
+                    return Some(chain);
+                }
+                if edge.to != file && previous[edge.to].is_none() {
+                    previous[edge.to] = Some(current);
+                    queue.push_back(edge.to);
+                }
+            }
+        }
+
+        return None;
+    }
+
+    /// How many templates `file` pulls in, directly or through other templates
+    pub fn dependencies(&self, file: usize) -> usize {
+        let mut seen = vec![false; self.files.len()];
+        let mut stack = vec![file];
+        seen[file] = true;
+
+        let mut count = 0;
+        while let Some(current) = stack.pop() {
+            for edge in &self.imports[current] {
+                if !seen[edge.to] {
+                    seen[edge.to] = true;
+                    count += 1;
+                    stack.push(edge.to);
+                }
+            }
+        }
+
+        return count;
+    }
+
+    /// The graph in the DOT language of Graphviz, a node for every template and an edge
+    /// for every import
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph regg {\n    node [shape=box];\n");
+        for (file, path) in self.files.iter().enumerate() {
+            let label = quote(&path.to_string_lossy());
+            dot.push_str(&format!("    {} [label={}];\n", file, label));
+        }
+        for (file, edges) in self.imports.iter().enumerate() {
+            for (i, edge) in edges.iter().enumerate() {
+                // a template importing another one twice is still one dependency
+                if edges[..i].iter().any(|other| other.to == edge.to) {
+                    continue;
+                }
+                dot.push_str(&format!("    {} -> {};\n", file, edge.to));
+            }
+        }
+        dot.push_str("}\n");
+        return dot;
+    }
+
+    /// The graph as JSON, the templates with how many others they pull in, and the imports
+    /// between them
+    pub fn to_json(&self) -> Value {
+        let files: Vec<Value> = self
+            .files
+            .iter()
+            .enumerate()
+            .map(|(file, path)| {
+                json!({
+                    "path": path.to_string_lossy(),
+                    "imports": self.imports[file].len(),
+                    "dependencies": self.dependencies(file),
+                })
+            })
+            .collect();
+        let imports: Vec<Value> = self
+            .imports
+            .iter()
+            .enumerate()
+            .flat_map(|(file, edges)| {
+                edges.iter().map(move |edge| {
+                    json!({
+                        "from": self.files[file].to_string_lossy(),
+                        "to": self.files[edge.to].to_string_lossy(),
+                        "line": edge.line,
+                    })
+                })
+            })
+            .collect();
+
+        return json!({ "files": files, "imports": imports });
+    }
+
+    /// The import in `from` of `to`
+    pub fn edge(&self, from: usize, to: usize) -> Option<&Edge> {
+        return self.imports[from].iter().find(|edge| edge.to == to);
+    }
+
+    // The index of `path`, added if it's new. Files are told apart by their canonical path,
+    // `./a/../b.regg` is `./b.regg`.
+    fn add(&mut self, indices: &mut HashMap<PathBuf, usize>, path: &Path) -> usize {
+        let key = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+        if let Some(&index) = indices.get(&key) {
+            return index;
+        }
+
+        let index = self.files.len();
+        indices.insert(key, index);
+        self.files.push(path.to_path_buf());
+        self.imports.push(Vec::new());
+        return index;
+    }
+}
+
+/// The file a relative import like `./Card.regg` points to from `dir`, `None` for packages
+/// and aliases only the bundler knows
+pub fn resolve(dir: &Path, source: &str) -> Option<PathBuf> {
+    if !source.starts_with('.') {
+        return None;
+    }
+    // without the `.` of `./Card.regg` and the `..` of `nested/../Card.regg`, so they're
+    // both `Card.regg`
+    let mut path = PathBuf::new();
+    for component in dir.join(source).components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir
+                if matches!(path.components().next_back(), Some(Component::Normal(_))) =>
+            {
+                path.pop();
+            }
+            _ => path.push(component),
+        }
+    }
+    return Some(path);
+}
+
+// A DOT string, `"src/Card.regg"`
+fn quote(text: &str) -> String {
+    return format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""));
+}
+
+// The sources the frontmatter of a template imports, with their spans in the template and
+// their lines
+fn template_imports(source: &str) -> Vec<(String, Span, usize)> {
+    let mut scanner = Scanner::new(source);
+    let mut found = Vec::new();
+
+    for token in scanner.scan_tokens().iter() {
+        if !matches!(token.kind, TokenType::CodeBlock) {
+            continue;
+        }
+        let code_start = token.span.start + "---".len();
+        for import in imports(&token.literal_str(source).unwrap_or_default()) {
+            let span = Span::new(code_start + import.span.start, code_start + import.span.end);
+            let line = 1 + source[..span.start].matches('\n').count();
+            found.push((import.source, span, line));
+        }
+    }
+
+    return found;
+}
+
+impl FromStr for GraphFormat {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name.to_ascii_lowercase().as_str() {
+            "dot" => Ok(GraphFormat::Dot),
+            "json" => Ok(GraphFormat::Json),
+            _ => Err(format!(
+                "unknown graph format `{name}`, expected `dot` or `json`"
+            )),
+        }
+    }
+}
+
+impl fmt::Display for GraphFormat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GraphFormat::Dot => write!(f, "dot"),
+            GraphFormat::Json => write!(f, "json"),
+        }
+    }
+}
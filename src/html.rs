@@ -0,0 +1,326 @@
+// https://html.spec.whatwg.org/multipage/indices.html#elements-3
+pub const ELEMENTS: [&str; 112] = [
+    "a",
+    "abbr",
+    "address",
+    "area",
+    "article",
+    "aside",
+    "audio",
+    "b",
+    "base",
+    "bdi",
+    "bdo",
+    "blockquote",
+    "body",
+    "br",
+    "button",
+    "canvas",
+    "caption",
+    "cite",
+    "code",
+    "col",
+    "colgroup",
+    "data",
+    "datalist",
+    "dd",
+    "del",
+    "details",
+    "dfn",
+    "dialog",
+    "div",
+    "dl",
+    "dt",
+    "em",
+    "embed",
+    "fieldset",
+    "figcaption",
+    "figure",
+    "footer",
+    "form",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "head",
+    "header",
+    "hgroup",
+    "hr",
+    "html",
+    "i",
+    "iframe",
+    "img",
+    "input",
+    "ins",
+    "kbd",
+    "label",
+    "legend",
+    "li",
+    "link",
+    "main",
+    "map",
+    "mark",
+    "menu",
+    "meta",
+    "meter",
+    "nav",
+    "noscript",
+    "object",
+    "ol",
+    "optgroup",
+    "option",
+    "output",
+    "p",
+    "picture",
+    "pre",
+    "progress",
+    "q",
+    "rp",
+    "rt",
+    "ruby",
+    "s",
+    "samp",
+    "script",
+    "search",
+    "section",
+    "select",
+    "slot",
+    "small",
+    "source",
+    "span",
+    "strong",
+    "style",
+    "sub",
+    "summary",
+    "sup",
+    "table",
+    "tbody",
+    "td",
+    "template",
+    "textarea",
+    "tfoot",
+    "th",
+    "thead",
+    "time",
+    "title",
+    "tr",
+    "track",
+    "u",
+    "ul",
+    "var",
+    "video",
+    "wbr",
+];
+
+// https://html.spec.whatwg.org/multipage/syntax.html#void-elements
+pub const VOID_ELEMENTS: [&str; 13] = [
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "source", "track",
+    "wbr",
+];
+
+/// Whether `name` is an element that never has children or a closing tag, like `<br>`
+pub fn is_void_element(name: &str) -> bool {
+    return VOID_ELEMENTS.contains(&name.to_ascii_lowercase().as_str());
+}
+
+/// A short description of an HTML element, for hovers in editors
+pub fn description(name: &str) -> Option<&'static str> {
+    let description = match name.to_ascii_lowercase().as_str() {
+        "a" => "A hyperlink to another page, a file, a location in the same page or anything else a URL can address.",
+        "abbr" => "An abbreviation or acronym, the expansion can go in the `title` attribute.",
+        "address" => "Contact information for the nearest `<article>` or `<body>`.",
+        "area" => "A clickable area of an image map.",
+        "article" => "A self-contained composition, like a blog post or a comment, that could be distributed on its own.",
+        "aside" => "Content only indirectly related to the main content, like a sidebar.",
+        "audio" => "Sound content, with the sources in `src` or `<source>` children.",
+        "b" => "Text to draw attention to, without extra importance.",
+        "base" => "The base URL for the relative URLs in the document.",
+        "bdi" => "Text isolated from the text direction of its surroundings.",
+        "bdo" => "Text written in the direction given by `dir`, overriding the bidirectional algorithm.",
+        "blockquote" => "A quotation from another source, the source URL can go in `cite`.",
+        "body" => "The content of the document.",
+        "br" => "A line break.",
+        "button" => "A clickable button, to submit a form or run a script.",
+        "canvas" => "A drawing surface for scripts, with the canvas or WebGL APIs.",
+        "caption" => "The title of a `<table>`.",
+        "cite" => "The title of a creative work, like a book or a film.",
+        "code" => "A fragment of computer code.",
+        "col" => "A column of a `<colgroup>`.",
+        "colgroup" => "A group of columns of a `<table>`.",
+        "data" => "Content with a machine-readable value in `value`.",
+        "datalist" => "The `<option>`s suggested for an `<input>` with a `list` attribute.",
+        "dd" => "The description of the preceding term in a `<dl>`.",
+        "del" => "Text that was removed from the document.",
+        "details" => "A disclosure widget, open to show its content, with a `<summary>` as its label.",
+        "dfn" => "The term being defined in the surrounding sentence.",
+        "dialog" => "A dialog box or other interactive component, like a modal.",
+        "div" => "A generic container with no meaning of its own.",
+        "dl" => "A description list of terms (`<dt>`) and descriptions (`<dd>`).",
+        "dt" => "A term in a `<dl>`.",
+        "em" => "Text with stress emphasis.",
+        "embed" => "External content, like a plugin, at this point of the document.",
+        "fieldset" => "A group of form controls, with a `<legend>` as its caption.",
+        "figcaption" => "The caption of a `<figure>`.",
+        "figure" => "Self-contained content like an image or a diagram, with an optional `<figcaption>`.",
+        "footer" => "The footer of the nearest section or of the page.",
+        "form" => "A form with controls to submit data.",
+        "h1" => "A level 1 heading, the most important.",
+        "h2" => "A level 2 heading.",
+        "h3" => "A level 3 heading.",
+        "h4" => "A level 4 heading.",
+        "h5" => "A level 5 heading.",
+        "h6" => "A level 6 heading, the least important.",
+        "head" => "Metadata about the document, like its title, scripts and style sheets.",
+        "header" => "Introductory content of the nearest section or of the page, like a logo and navigation.",
+        "hgroup" => "A heading with related content, like a subtitle.",
+        "hr" => "A thematic break between paragraphs.",
+        "html" => "The root of the document.",
+        "i" => "Text in an alternate voice, like a technical term or a phrase in another language.",
+        "iframe" => "Another page embedded into this one.",
+        "img" => "An image, `alt` describes it for people who can't see it.",
+        "input" => "A form control, what kind depends on `type`.",
+        "ins" => "Text that was added to the document.",
+        "kbd" => "Text representing user input, like keyboard keys.",
+        "label" => "The caption of a form control.",
+        "legend" => "The caption of a `<fieldset>`.",
+        "li" => "An item of a list.",
+        "link" => "A relationship to an external resource, most often a style sheet.",
+        "main" => "The main content of the page, there should be only one visible at a time.",
+        "map" => "An image map, with `<area>`s as its clickable areas.",
+        "mark" => "Text highlighted for reference, like search results.",
+        "menu" => "A list of commands, like a toolbar.",
+        "meta" => "Metadata that other elements like `<title>` can't represent.",
+        "meter" => "A value within a known range, like disk usage.",
+        "nav" => "A section of navigation links.",
+        "noscript" => "Content used when scripting is disabled.",
+        "object" => "An external resource, like an image, a page or a plugin.",
+        "ol" => "An ordered list.",
+        "optgroup" => "A group of `<option>`s in a `<select>`.",
+        "option" => "An option of a `<select>`, an `<optgroup>` or a `<datalist>`.",
+        "output" => "The result of a calculation or a user action.",
+        "p" => "A paragraph.",
+        "picture" => "An `<img>` with alternative `<source>`s for different displays.",
+        "pre" => "Preformatted text, shown as it's written in the source.",
+        "progress" => "The progress of a task, like a download.",
+        "q" => "A short inline quotation.",
+        "rp" => "Parentheses shown by browsers that don't support ruby annotations.",
+        "rt" => "The text of a ruby annotation.",
+        "ruby" => "A ruby annotation, for the pronunciation of East Asian characters.",
+        "s" => "Text that is no longer accurate or relevant, shown struck through.",
+        "samp" => "Sample output of a computer program.",
+        "script" => "A script, inline or loaded from `src`.",
+        "search" => "A part of the page for searching or filtering.",
+        "section" => "A generic section of the document, usually with a heading.",
+        "select" => "A control with a menu of `<option>`s.",
+        "slot" => "A placeholder in a web component that's filled with the component's children.",
+        "small" => "Side comments and small print, like copyright notices.",
+        "source" => "A media resource of a `<picture>`, `<audio>` or `<video>`.",
+        "span" => "A generic inline container with no meaning of its own.",
+        "strong" => "Text with strong importance.",
+        "style" => "A style sheet for the document.",
+        "sub" => "Subscript text.",
+        "summary" => "The label of a `<details>`, clicking it opens and closes the details.",
+        "sup" => "Superscript text.",
+        "table" => "Tabular data, in rows and columns.",
+        "tbody" => "The body rows of a `<table>`.",
+        "td" => "A data cell of a `<table>`.",
+        "template" => "Markup that isn't rendered, to be cloned by scripts.",
+        "textarea" => "A multi-line plain text editing control.",
+        "tfoot" => "The footer rows of a `<table>`.",
+        "th" => "A header cell of a `<table>`.",
+        "thead" => "The header rows of a `<table>`.",
+        "time" => "A time or a date, with a machine-readable value in `datetime`.",
+        "title" => "The title of the document, shown in the browser's tab.",
+        "tr" => "A row of a `<table>`.",
+        "track" => "Timed text, like subtitles, for an `<audio>` or `<video>`.",
+        "u" => "Text with a non-textual annotation, shown underlined.",
+        "ul" => "An unordered list.",
+        "var" => "A variable in a mathematical expression or a programming context.",
+        "video" => "A video, with the sources in `src` or `<source>` children.",
+        "wbr" => "A position where the browser may break a line.",
+        _ => return None,
+    };
+    return Some(description);
+}
+
+// The longest character reference the scanner looks for, in bytes, with the `&` and `;`
+pub const MAX_CHARACTER_REFERENCE_LENGTH: usize = 32;
+
+// The named character references most templates use, the full list has over 2000
+// https://html.spec.whatwg.org/multipage/named-characters.html
+const NAMED_CHARACTER_REFERENCES: [(&str, &str); 46] = [
+    ("amp", "&"),
+    ("lt", "<"),
+    ("gt", ">"),
+    ("quot", "\""),
+    ("apos", "'"),
+    ("nbsp", "\u{a0}"),
+    ("ensp", "\u{2002}"),
+    ("emsp", "\u{2003}"),
+    ("thinsp", "\u{2009}"),
+    ("zwnj", "\u{200c}"),
+    ("zwj", "\u{200d}"),
+    ("shy", "\u{ad}"),
+    ("copy", "©"),
+    ("reg", "®"),
+    ("trade", "™"),
+    ("hellip", "…"),
+    ("mdash", "—"),
+    ("ndash", "–"),
+    ("lsquo", "‘"),
+    ("rsquo", "’"),
+    ("ldquo", "“"),
+    ("rdquo", "”"),
+    ("laquo", "«"),
+    ("raquo", "»"),
+    ("middot", "·"),
+    ("bull", "•"),
+    ("times", "×"),
+    ("divide", "÷"),
+    ("minus", "−"),
+    ("plusmn", "±"),
+    ("deg", "°"),
+    ("micro", "µ"),
+    ("euro", "€"),
+    ("pound", "£"),
+    ("yen", "¥"),
+    ("cent", "¢"),
+    ("sect", "§"),
+    ("para", "¶"),
+    ("larr", "←"),
+    ("rarr", "→"),
+    ("uarr", "↑"),
+    ("darr", "↓"),
+    ("harr", "↔"),
+    ("hearts", "♥"),
+    ("check", "✓"),
+    ("star", "☆"),
+];
+
+/// The character a reference like `&amp;`, `&#39;` or `&#x27;` stands for, `None` if it isn't
+/// one. Numbers that aren't a character decode to U+FFFD, like browsers do.
+pub fn decode_character_reference(reference: &str) -> Option<String> {
+    let name = reference.strip_prefix('&')?.strip_suffix(';')?;
+    let Some(number) = name.strip_prefix('#') else {
+        return NAMED_CHARACTER_REFERENCES
+            .iter()
+            .find(|(named, _)| *named == name)
+            .map(|(_, value)| value.to_string());
+    };
+
+    let (digits, radix) = match number.strip_prefix(['x', 'X']) {
+        Some(hex) => (hex, 16),
+        None => (number, 10),
+    };
+    if digits.is_empty() || !digits.chars().all(|c| c.is_digit(radix)) {
+        return None;
+    }
+    let character = u32::from_str_radix(digits, radix)
+        .ok()
+        .filter(|&code| code != 0)
+        .and_then(char::from_u32)
+        .unwrap_or(char::REPLACEMENT_CHARACTER);
+    return Some(character.to_string());
+}
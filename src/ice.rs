@@ -0,0 +1,151 @@
+// Internal compiler error (ICE) reporting: remembers which source and span Regg is
+// working on, so a panic can be reported with the template location that caused it.
+
+use std::{
+    backtrace::Backtrace,
+    cell::{Cell, RefCell},
+    fs,
+    panic::{self, PanicHookInfo},
+    path::PathBuf,
+    sync::Arc,
+};
+
+use crate::span::Span;
+
+const ISSUES_URL: &str = "https://github.com/hoppercomplex/regg/issues";
+
+/// A file being processed and its source
+pub type Source = (String, Arc<String>);
+
+thread_local! {
+    // The file the current thread is processing, `check` processes several at once
+    static SOURCE: RefCell<Option<Source>> = const { RefCell::new(None) };
+    // The span the current thread is processing
+    static SPAN: Cell<Option<Span>> = const { Cell::new(None) };
+}
+
+/// Records the source Regg is about to process, `path` is `<repl>` for REPL input
+pub fn enter_source(path: &str, source: Arc<String>) {
+    share_source(Some((path.to_string(), source)));
+}
+
+/// The source the current thread is processing, for the threads it starts to `share_source`
+pub fn current_source() -> Option<Source> {
+    return SOURCE.with(|current| current.borrow().clone());
+}
+
+/// Records that the current thread works on `source` too, like the scanner threads
+pub fn share_source(source: Option<Source>) {
+    SOURCE.with(|current| *current.borrow_mut() = source);
+    set_span(Span::default());
+}
+
+/// Records the span of the source the current thread is processing
+pub fn set_span(span: Span) {
+    SPAN.with(|current| current.set(Some(span)));
+}
+
+/// Replaces the default panic message with an ICE report.
+///
+/// With `dump` set, a bug report bundle with the version, the panic, the
+/// backtrace and the lines of input around the span is written to that file.
+pub fn install_hook(dump: Option<PathBuf>) {
+    panic::set_hook(Box::new(move |info| {
+        let report = report(info);
+        eprintln!("{}", report.summary);
+
+        match &dump {
+            Some(path) => match fs::write(path, report.bundle()) {
+                Ok(()) => eprintln!(
+                    "note: wrote a bug report to {}, please attach it to the issue",
+                    path.display()
+                ),
+                Err(error) => eprintln!("note: couldn't write {}: {}", path.display(), error),
+            },
+            None => eprintln!("note: run again with `--ice-dump` to write a bug report"),
+        }
+    }));
+}
+
+struct Report {
+    summary: String,
+    input: Option<String>,
+    backtrace: String,
+}
+
+impl Report {
+    fn bundle(&self) -> String {
+        let mut bundle = format!("regg {}\n\n{}\n", env!("CARGO_PKG_VERSION"), self.summary);
+        if let Some(input) = &self.input {
+            bundle += &format!("\n--- input ---\n{}\n", input);
+        }
+        bundle += &format!("\n--- backtrace ---\n{}\n", self.backtrace);
+        return bundle;
+    }
+}
+
+fn report(info: &PanicHookInfo) -> Report {
+    let message = match info.payload().downcast_ref::<&str>() {
+        Some(message) => message.to_string(),
+        None => match info.payload().downcast_ref::<String>() {
+            Some(message) => message.clone(),
+            None => "Box<dyn Any>".to_string(),
+        },
+    };
+
+    let mut summary = format!("error: internal compiler error: {}", message);
+    if let Some(location) = info.location() {
+        summary += &format!("\n  at {}:{}", location.file(), location.line());
+    }
+
+    let span = SPAN.with(|span| span.get());
+    let source = current_source();
+
+    let mut input = None;
+    if let (Some(span), Some((path, source))) = (span, source) {
+        let start = floor_char_boundary(&source, span.start);
+        let line = source[..start].matches('\n').count() + 1;
+        let column = source[..start]
+            .rsplit('\n')
+            .next()
+            .unwrap_or("")
+            .chars()
+            .count()
+            + 1;
+        summary += &format!("\n  while processing {}:{}:{}", path, line, column);
+        input = Some(input_slice(&source, line));
+    }
+
+    summary += &format!(
+        "\nnote: this is a bug in Regg, please report it at {}",
+        ISSUES_URL
+    );
+
+    return Report {
+        summary,
+        input,
+        backtrace: Backtrace::force_capture().to_string(),
+    };
+}
+
+// The lines around `line`, enough to reproduce most crashes without sharing the whole file
+fn input_slice(source: &str, line: usize) -> String {
+    const CONTEXT: usize = 5;
+
+    return source
+        .lines()
+        .enumerate()
+        .skip(line.saturating_sub(CONTEXT + 1))
+        .take(CONTEXT * 2 + 1)
+        .map(|(index, text)| format!("{:>4} | {}", index + 1, text))
+        .collect::<Vec<_>>()
+        .join("\n");
+}
+
+fn floor_char_boundary(source: &str, offset: usize) -> usize {
+    let mut offset = offset.min(source.len());
+    while !source.is_char_boundary(offset) {
+        offset -= 1;
+    }
+    return offset;
+}
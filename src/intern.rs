@@ -0,0 +1,127 @@
+// Interned strings for the names in the tree. Tag names like `div` and attribute names like
+// `hidden` come up thousands of times in a site, the parser makes every one of them a clone
+// of the same `Atom` instead of a new allocation. Tokens don't need it, their text stays in
+// the source.
+
+use std::{
+    borrow::Borrow,
+    collections::HashSet,
+    fmt,
+    hash::{Hash, Hasher},
+    ops::Deref,
+    sync::Arc,
+};
+
+/// Lexemes up to this long are interned, longer ones (text, code) rarely repeat
+pub const MAX_INTERNED_LENGTH: usize = 32;
+
+/// A shared, immutable string. Clones are cheap and atoms from the same `Interner` compare
+/// by pointer.
+#[derive(Clone, Default)]
+pub struct Atom(Arc<str>);
+
+impl Atom {
+    pub fn as_str(&self) -> &str {
+        return &self.0;
+    }
+}
+
+/// Hands out one `Atom` per distinct short string
+#[derive(Debug, Default)]
+pub struct Interner {
+    atoms: HashSet<Atom>,
+}
+
+impl Interner {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The atom for `text`, the same one every time for short strings
+    pub fn intern(&mut self, text: &str) -> Atom {
+        if text.len() > MAX_INTERNED_LENGTH {
+            return Atom::from(text);
+        }
+        if let Some(atom) = self.atoms.get(text) {
+            return atom.clone();
+        }
+
+        let atom = Atom::from(text);
+        self.atoms.insert(atom.clone());
+        return atom;
+    }
+
+    /// How many distinct strings were interned
+    pub fn len(&self) -> usize {
+        return self.atoms.len();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.atoms.is_empty();
+    }
+}
+
+impl Deref for Atom {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        return &self.0;
+    }
+}
+
+impl Borrow<str> for Atom {
+    fn borrow(&self) -> &str {
+        return &self.0;
+    }
+}
+
+impl PartialEq for Atom {
+    fn eq(&self, other: &Self) -> bool {
+        return Arc::ptr_eq(&self.0, &other.0) || self.0 == other.0;
+    }
+}
+
+impl Eq for Atom {}
+
+impl PartialEq<str> for Atom {
+    fn eq(&self, other: &str) -> bool {
+        return &*self.0 == other;
+    }
+}
+
+impl PartialEq<&str> for Atom {
+    fn eq(&self, other: &&str) -> bool {
+        return &*self.0 == *other;
+    }
+}
+
+impl Hash for Atom {
+    // like `str`, so `HashSet<Atom>` can be looked up with a `&str`
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.hash(state);
+    }
+}
+
+impl From<&str> for Atom {
+    fn from(text: &str) -> Self {
+        Self(Arc::from(text))
+    }
+}
+
+impl From<String> for Atom {
+    fn from(text: String) -> Self {
+        Self(Arc::from(text))
+    }
+}
+
+impl fmt::Debug for Atom {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(&*self.0, f)
+    }
+}
+
+impl fmt::Display for Atom {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(&*self.0, f)
+    }
+}
@@ -1,76 +1,828 @@
+#![allow(clippy::needless_return)]
+
 use std::{
-    fs,
+    borrow::Cow,
+    collections::HashMap,
+    fmt, fs,
     io::{self, Write},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex, PoisonError,
+    },
+    thread,
 };
 
-use crate::scanner::Scanner;
+use crate::builder::{ErrorSink, ReggBuilder};
+use crate::declarations::{imports, unused_imports};
+use crate::diagnostic::{Diagnostic, ErrorFormat, Severity};
+use crate::dispatch::{Dispatcher, Handler};
+use crate::encoding::Encoding;
+use crate::flags::Flags;
+use crate::graph::{resolve, Graph, GraphFormat};
+use crate::html::is_void_element;
+use crate::line_index::LineIndex;
+use crate::parser::Parser;
+use crate::props::{check_props, props, Props};
+use crate::scanner::{Scanner, ScannerOptions, PARALLEL_THRESHOLD};
+use crate::token::TokenStream;
+use crate::token_type::TokenType;
+use serde_json::json;
 
+pub mod ast;
+pub mod builder;
+pub mod codes;
+pub mod cst;
+pub mod declarations;
+pub mod diagnostic;
+pub mod dispatch;
+pub mod encoding;
+pub mod exit_code;
+pub mod expr;
+pub mod flags;
+pub mod format;
+pub mod graph;
+pub mod html;
+pub mod ice;
+pub mod intern;
+pub mod line_index;
+// the language server works with file paths, which the browser doesn't have
+#[cfg(not(target_arch = "wasm32"))]
+pub mod lsp;
+#[cfg(feature = "markdown")]
+pub mod markdown;
+pub mod parser;
+pub mod props;
 pub mod scanner;
+pub mod snippet;
+pub mod span;
 pub mod token;
 pub mod token_type;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+pub mod watch;
+
+// The source of a checked file, to quote in its diagnostics, and the diagnostics
+type Checked = (Option<(String, LineIndex)>, Vec<Diagnostic>);
 
 pub struct Regg {
-    had_error: bool,
+    error_count: usize,
+    warning_count: usize,
+    max_warnings: Option<usize>,
+    max_errors: Option<usize>,
+    deny_warnings: bool,
+    encoding: Encoding,
+    dispatcher: Dispatcher,
+    flags: Flags,
+    print_tokens: bool,
+    error_format: ErrorFormat,
+    color: bool,
+    source: Option<(String, LineIndex)>, // the source being run, quoted in diagnostics
+    source_name: String,                 // the file being run, for internal error reports
+    jobs: Option<usize>,                 // files `check` runs at once, `None` for one per core
+    scanner_options: ScannerOptions,
+    collected: Option<Vec<Diagnostic>>, // diagnostics kept for later instead of printed
+    error_sink: Option<Mutex<ErrorSink>>, // where diagnostics go instead of stderr
 }
 
 impl Regg {
     pub fn new() -> Self {
-        Self { had_error: false }
+        Self {
+            error_count: 0,
+            warning_count: 0,
+            max_warnings: None,
+            max_errors: None,
+            deny_warnings: false,
+            encoding: Encoding::default(),
+            dispatcher: Dispatcher::new(),
+            flags: Flags::new(),
+            print_tokens: true,
+            error_format: ErrorFormat::default(),
+            color: false,
+            source: None,
+            source_name: "<input>".to_string(),
+            jobs: None,
+            scanner_options: ScannerOptions::new(),
+            collected: None,
+            error_sink: None,
+        }
+    }
+
+    /// A `Regg` with other settings than the defaults of `new`
+    pub fn builder() -> ReggBuilder {
+        return ReggBuilder::default();
+    }
+
+    /// Sets the encoding `run_file` decodes files with, UTF-8 by default
+    pub fn set_encoding(&mut self, encoding: Encoding) {
+        self.encoding = encoding;
+    }
+
+    /// Makes `run_file` fail when a file has more than `max_warnings` warnings, `None` allows any number
+    pub fn set_max_warnings(&mut self, max_warnings: Option<usize>) {
+        self.max_warnings = max_warnings;
+    }
+
+    /// Stops reporting diagnostics, and checking more files, after `max_errors` errors.
+    /// `None` reports all of them.
+    pub fn set_max_errors(&mut self, max_errors: Option<usize>) {
+        self.max_errors = max_errors;
+    }
+
+    /// Reports warnings as errors, like `-D warnings`
+    pub fn set_deny_warnings(&mut self, deny_warnings: bool) {
+        self.deny_warnings = deny_warnings;
+    }
+
+    /// Sets the flags `{#if flag("...")}` blocks are resolved with
+    pub fn set_flags(&mut self, flags: Flags) {
+        self.flags = flags;
+    }
+
+    /// Whether `run` prints the tokens to stdout. Without them, like in `regg check`,
+    /// diagnostics name the file they're in.
+    pub fn set_print_tokens(&mut self, print_tokens: bool) {
+        self.print_tokens = print_tokens;
+    }
+
+    pub fn prints_tokens(&self) -> bool {
+        return self.print_tokens;
+    }
+
+    /// Sets how diagnostics are printed to stderr, as text or as one JSON object per line
+    pub fn set_error_format(&mut self, error_format: ErrorFormat) {
+        self.error_format = error_format;
+    }
+
+    pub fn error_format(&self) -> ErrorFormat {
+        return self.error_format;
     }
 
+    /// Whether diagnostics are printed in color, off by default
+    pub fn set_color(&mut self, color: bool) {
+        self.color = color;
+    }
+
+    /// Sets how many files `check` processes at once, `None` for as many as there are cores
+    pub fn set_jobs(&mut self, jobs: Option<usize>) {
+        self.jobs = jobs;
+    }
+
+    /// Sets the limits sources are scanned with, see `ScannerOptions`
+    pub fn set_scanner_options(&mut self, options: ScannerOptions) {
+        self.scanner_options = options;
+    }
+
+    /// Routes files with `extension` (without the leading `.`) to `handler` in `run_file`
+    pub fn register_extension(&mut self, extension: &str, handler: Handler) {
+        self.dispatcher.register(extension, handler);
+    }
+
+    /// Runs a file and exits the process with one of the `exit_code`s if it failed
     pub fn run_file(&mut self, path: &str) {
-        // TODO: Handle Errors Better
-        let content = fs::read_to_string(path).expect("Error reading file");
+        if !self.process_file(path) {
+            std::process::exit(exit_code::IO_ERROR)
+        }
+
+        let code = self.finish();
+        if code != exit_code::OK {
+            std::process::exit(code)
+        }
+    }
+
+    /// Checks files, and the files Regg handles in directories, then returns one of the
+    /// `exit_code`s. `--max-warnings` applies to the warnings of all the files together.
+    /// Files are checked on `--jobs` threads, their diagnostics are printed in path order.
+    pub fn check(&mut self, paths: &[PathBuf]) -> i32 {
+        self.error_count = 0;
+        self.warning_count = 0;
+
+        let mut files = self.files(paths);
+        files.sort();
+        files.dedup();
+
+        let jobs = self
+            .jobs
+            .unwrap_or_else(|| thread::available_parallelism().map_or(1, |n| n.get()))
+            .min(files.len());
+        let mut unreadable = false;
+        if jobs <= 1 {
+            for file in &files {
+                if self.reached_max_errors() {
+                    break;
+                }
+                if !self.process_file(&file.to_string_lossy()) {
+                    unreadable = true;
+                }
+            }
+        } else {
+            for (file, checked) in files.iter().zip(self.check_parallel(&files, jobs)) {
+                if self.reached_max_errors() {
+                    break;
+                }
+                let path = file.to_string_lossy();
+                match checked {
+                    Ok((source, diagnostics)) => {
+                        self.source_name = path.to_string();
+                        self.source = source;
+                        diagnostics
+                            .iter()
+                            .for_each(|diagnostic| self.diagnostic(diagnostic));
+                    }
+                    Err(error) => {
+                        self.unreadable(&path, &error);
+                        unreadable = true;
+                    }
+                }
+            }
+        }
 
-        self.run(&content);
+        let code = self.finish();
+        if unreadable {
+            return exit_code::IO_ERROR;
+        }
+        return code;
+    }
+
+    // Runs `files` on `jobs` threads, every thread takes the next file when it's done with
+    // one. Returns the source and the diagnostics of every file, in the order of `files`.
+    fn check_parallel(&self, files: &[PathBuf], jobs: usize) -> Vec<io::Result<Checked>> {
+        let next = AtomicUsize::new(0);
+
+        let mut checked: Vec<(usize, io::Result<Checked>)> = thread::scope(|scope| {
+            let handles: Vec<_> = (0..jobs)
+                .map(|_| {
+                    scope.spawn(|| {
+                        let mut worker = self.worker();
+                        let mut checked = Vec::new();
+                        loop {
+                            let index = next.fetch_add(1, Ordering::Relaxed);
+                            let Some(file) = files.get(index) else {
+                                break;
+                            };
+                            checked.push((index, worker.collect_file(file)));
+                        }
+                        checked
+                    })
+                })
+                .collect();
+
+            handles
+                .into_iter()
+                .flat_map(|handle| handle.join().expect("Check thread panicked"))
+                .collect()
+        });
+
+        checked.sort_by_key(|(index, _)| *index);
+        return checked.into_iter().map(|(_, checked)| checked).collect();
+    }
+
+    // A `Regg` with the same settings that collects diagnostics instead of printing them
+    fn worker(&self) -> Regg {
+        let mut worker = Regg::new();
+        worker.encoding = self.encoding;
+        worker.dispatcher = self.dispatcher.clone();
+        worker.flags = self.flags.clone();
+        worker.print_tokens = self.print_tokens;
+        worker.scanner_options = self.scanner_options;
+        worker.collected = Some(Vec::new());
+        return worker;
+    }
 
-        if self.had_error {
-            std::process::exit(65)
+    // Runs a file through its handler, keeping its diagnostics
+    fn collect_file(&mut self, file: &Path) -> io::Result<Checked> {
+        let bytes = fs::read(file)?;
+
+        let path = file.to_string_lossy();
+        self.source_name = path.to_string();
+        self.source = None;
+        let handler = self.dispatcher.handler_for(file);
+        handler(self, &path, bytes);
+
+        let diagnostics = self.collected.replace(Vec::new()).unwrap_or_default();
+        return Ok((self.source.take(), diagnostics));
+    }
+
+    /// Formats the templates among `paths` like `check` finds them, or with `check_only`
+    /// just lists the ones that would change. Returns one of the `exit_code`s.
+    pub fn format_files(&mut self, paths: &[PathBuf], check_only: bool) -> i32 {
+        self.error_count = 0;
+        self.warning_count = 0;
+
+        let mut failed = false;
+        let mut unformatted = false;
+        let mut files = self.files(paths);
+        files.retain(|file| self.dispatcher.is_template(file));
+        for file in &files {
+            if self.reached_max_errors() {
+                break;
+            }
+            let path = file.to_string_lossy().to_string();
+            let bytes = match fs::read(file) {
+                Ok(bytes) => bytes,
+                Err(error) => {
+                    self.unreadable(&path, &error);
+                    failed = true;
+                    continue;
+                }
+            };
+
+            self.source_name = path.clone();
+            let source = self.decode(&bytes);
+            let formatted = match format::format(&source) {
+                Ok(formatted) => formatted,
+                Err(errors) => {
+                    self.quote_source(&source);
+                    errors.iter().for_each(|error| self.diagnostic(error));
+                    continue;
+                }
+            };
+
+            if formatted == source {
+                continue;
+            }
+
+            if check_only {
+                eprintln!("{} would be reformatted", path);
+                unformatted = true;
+            } else if let Err(error) = fs::write(file, self.encoding.encode(&formatted)) {
+                eprintln!("Error writing {}: {}", path, error);
+                failed = true;
+            } else {
+                eprintln!("Formatted {}", path);
+            }
         }
+
+        self.summary();
+        if failed {
+            return exit_code::IO_ERROR;
+        }
+        if self.error_count > 0 {
+            return exit_code::SOURCE_ERROR;
+        }
+        if unformatted {
+            return exit_code::UNFORMATTED;
+        }
+        return exit_code::OK;
+    }
+
+    /// Prints the graph of the templates among `paths`, found like `check` finds them, and
+    /// of the templates they import. Returns one of the `exit_code`s.
+    pub fn graph(&self, paths: &[PathBuf], format: GraphFormat) -> i32 {
+        let mut files = self.files(paths);
+        files.retain(|file| self.dispatcher.is_template(file));
+        for file in &files {
+            if let Err(error) = fs::metadata(file) {
+                self.unreadable(&file.to_string_lossy(), &error);
+                return exit_code::IO_ERROR;
+            }
+        }
+
+        let dispatcher = &self.dispatcher;
+        let graph = Graph::build(&files, |path| {
+            dispatcher.handles(path) && dispatcher.is_template(path)
+        });
+        match format {
+            GraphFormat::Dot => print_line(graph.to_dot().trim_end()),
+            GraphFormat::Json => print_line(graph.to_json()),
+        }
+        return exit_code::OK;
+    }
+
+    /// Whether `check` picks up the file when it's in a directory
+    pub fn handles(&self, path: &Path) -> bool {
+        return self.dispatcher.handles(path);
+    }
+
+    // Runs a file through its handler, false if it can't be read
+    fn process_file(&mut self, path: &str) -> bool {
+        let bytes = match fs::read(path) {
+            Ok(bytes) => bytes,
+            Err(error) => {
+                self.unreadable(path, &error);
+                return false;
+            }
+        };
+
+        self.source_name = path.to_string();
+        self.source = None;
+        let handler = self.dispatcher.handler_for(Path::new(path));
+        handler(self, path, bytes);
+        return true;
+    }
+
+    // A file that can't be read, as a diagnostic without a line in the JSON format
+    fn unreadable(&self, path: &str, error: &io::Error) {
+        let message = format!("Error reading {}: {}", path, error);
+        match self.error_format {
+            ErrorFormat::Human => eprintln!("{}", message),
+            ErrorFormat::Json => {
+                let mut diagnostic = Diagnostic::error(0, &message)
+                    .with_code(codes::UNREADABLE_FILE)
+                    .to_json();
+                diagnostic["file"] = json!(path);
+                diagnostic["line"] = json!(null);
+                eprintln!("{}", diagnostic);
+            }
+        }
+    }
+
+    // The exit code for the errors and warnings reported so far
+    fn finish(&self) -> i32 {
+        self.summary();
+        if self.error_count > 0 {
+            return exit_code::SOURCE_ERROR;
+        }
+
+        if let Some(max_warnings) = self.max_warnings {
+            if self.warning_count > max_warnings {
+                eprintln!(
+                    "Found {} warnings, more than the maximum of {}",
+                    self.warning_count, max_warnings
+                );
+                return exit_code::TOO_MANY_WARNINGS;
+            }
+        }
+
+        return exit_code::OK;
+    }
+
+    // `Found 2 errors and 1 warning` at the end of a run, in the human format
+    fn summary(&self) {
+        if self.error_format == ErrorFormat::Json {
+            return;
+        }
+
+        let counts: Vec<String> = [(self.error_count, "error"), (self.warning_count, "warning")]
+            .into_iter()
+            .filter(|(count, _)| *count > 0)
+            .map(|(count, noun)| match count {
+                1 => format!("1 {}", noun),
+                _ => format!("{} {}s", count, noun),
+            })
+            .collect();
+        if counts.is_empty() {
+            return;
+        }
+
+        let mut summary = format!("Found {}", counts.join(" and "));
+        if self.reached_max_errors() {
+            summary += ", stopped there because of `--max-errors`";
+        }
+        eprintln!("{}", summary);
+    }
+
+    fn reached_max_errors(&self) -> bool {
+        return self
+            .max_errors
+            .is_some_and(|max_errors| self.error_count >= max_errors);
+    }
+
+    // The files among `paths` and the files Regg handles in the directories among them
+    fn files(&self, paths: &[PathBuf]) -> Vec<PathBuf> {
+        let mut files = Vec::new();
+        for path in paths {
+            if path.is_dir() {
+                self.walk(path, &mut files);
+            } else {
+                files.push(path.clone());
+            }
+        }
+        return files;
+    }
+
+    // Collects the files Regg handles in `dir` and its subdirectories, skipping hidden ones
+    fn walk(&self, dir: &Path, files: &mut Vec<PathBuf>) {
+        let Ok(entries) = fs::read_dir(dir) else {
+            return;
+        };
+
+        let mut paths: Vec<PathBuf> = entries.flatten().map(|entry| entry.path()).collect();
+        paths.sort();
+
+        for path in paths {
+            if path
+                .file_name()
+                .is_some_and(|name| name.to_string_lossy().starts_with('.'))
+            {
+                continue;
+            }
+
+            if path.is_dir() {
+                self.walk(&path, files);
+            } else if self.handles(&path) {
+                files.push(path);
+            }
+        }
+    }
+
+    /// Keeps `source` to quote in the diagnostics that follow. It's only copied when there
+    /// are diagnostics, large files are often fine.
+    pub fn quote_source(&mut self, source: &str) {
+        self.source = Some((source.to_string(), LineIndex::new(source)));
+    }
+
+    /// Decodes a file's bytes with the configured encoding, reporting invalid byte sequences
+    pub fn decode<'a>(&mut self, bytes: &'a [u8]) -> Cow<'a, str> {
+        let (content, invalid_sequences) = self.encoding.decode(bytes);
+        if !invalid_sequences.is_empty() {
+            self.quote_source(&content);
+        }
+        for sequence in invalid_sequences {
+            let message = format!(
+                "Invalid {} byte sequence {:02X?} at byte {} was replaced with `\u{FFFD}`, \
+                 is the file saved in another encoding? (see `--encoding`)",
+                self.encoding, sequence.bytes, sequence.offset
+            );
+            let diagnostic = Diagnostic::error(sequence.line, &message)
+                .with_code(codes::INVALID_ENCODING)
+                .with_span(sequence.span);
+            self.diagnostic(&diagnostic);
+        }
+
+        return content;
+    }
+
+    /// Decodes `bytes` like `decode`, valid UTF-8 becomes the text without being copied
+    pub fn decode_owned(&mut self, bytes: Vec<u8>) -> String {
+        if let Cow::Owned(content) = self.decode(&bytes) {
+            return content;
+        }
+        return String::from_utf8(bytes).expect("decoded without replacing anything");
     }
 
     pub fn run_prompt(&mut self) {
-        print!("Welcome to REPL of REGG, press CTRL+C to exit.\n");
+        eprintln!("Welcome to REPL of REGG, press CTRL+C to exit.");
+        self.source_name = "<repl>".to_string();
+        let mut input = String::new();
         loop {
-            print!("> ");
-            let mut input = String::new();
-            io::stdout().flush().unwrap();
+            // Keep reading lines while a tag, expression or code block is left open,
+            // an empty line runs the input as it is
+            eprint!("{}", if input.is_empty() { "> " } else { ". " });
+            let mut line = String::new();
+            let _ = io::stderr().flush(); // the prompt is only lost if stderr is closed
 
-            match io::stdin().read_line(&mut input) {
+            match io::stdin().read_line(&mut line) {
+                Ok(0) => break, // CTRL+D
                 Ok(_n) => {
-                    self.run(&input);
-                    self.had_error = false;
+                    input.push_str(&line);
+                    if line.trim().is_empty() || !is_incomplete(&input) {
+                        self.run(&input);
+                        self.error_count = 0;
+                        self.warning_count = 0;
+                        input.clear();
+                    }
                 }
-                Err(error) => println!("error: {error}"),
+                Err(error) => eprintln!("error: {error}"),
             }
         }
     }
 
     pub fn run<'a>(&mut self, source: &'a str) -> &'a str {
-        let mut scanner = Scanner::new(source.to_string());
-        let tokens = scanner.scan_tokens();
+        self.run_shared(Arc::new(source.to_string()));
+        return source;
+    }
 
-        tokens.iter().for_each(|token| println!("{:?}", token));
+    /// Runs `source` like `run`, the tokens and a panic report share it instead of copying it
+    pub fn run_shared(&mut self, shared: Arc<String>) {
+        ice::enter_source(&self.source_name, Arc::clone(&shared));
+        let source = shared.as_str();
 
-        return source;
+        let mut scanner = Scanner::shared(&shared).with_options(self.scanner_options);
+        let tokens = if source.len() >= PARALLEL_THRESHOLD {
+            let threads = thread::available_parallelism().map_or(1, |n| n.get());
+            scanner.scan_tokens_parallel(threads)
+        } else {
+            scanner.scan_tokens()
+        };
+
+        if self.print_tokens {
+            tokens
+                .iter()
+                .for_each(|token| print_line(token.display(tokens.source())));
+        }
+
+        let mut parser = Parser::new(tokens).with_flags(self.flags.clone());
+        parser.parse();
+        let mut parser_diagnostics = parser.diagnostics().clone();
+        parser_diagnostics.extend(unused_imports(tokens));
+
+        if let Some(dir) = Path::new(&self.source_name).parent() {
+            let components = self.component_props(dir, tokens);
+            parser_diagnostics.extend(check_props(tokens, &components));
+            parser_diagnostics.extend(self.circular_import());
+        }
+
+        let diagnostics = scanner.diagnostics().len() + parser_diagnostics.len();
+        if diagnostics > 0 {
+            self.quote_source(source);
+        }
+        for diagnostic in scanner.diagnostics().iter().chain(&parser_diagnostics) {
+            self.diagnostic(diagnostic);
+        }
+    }
+
+    // The props of the components the frontmatter imports from templates, by the name
+    // they're imported as
+    fn component_props(&self, dir: &Path, tokens: &TokenStream) -> HashMap<String, Props> {
+        let mut components = HashMap::new();
+        let code_blocks = tokens
+            .iter()
+            .filter(|token| matches!(token.kind, TokenType::CodeBlock));
+
+        for import in
+            code_blocks.flat_map(|token| imports(&tokens.literal_str(token).unwrap_or_default()))
+        {
+            let Some(name) = import.names.first() else {
+                continue;
+            };
+            let Some(path) = resolve(dir, &import.source) else {
+                continue; // a package, or an alias only the bundler knows
+            };
+            if !self.dispatcher.handles(&path) || !self.dispatcher.is_template(&path) {
+                continue;
+            }
+            let Ok(bytes) = fs::read(&path) else {
+                continue;
+            };
+
+            let source = String::from_utf8_lossy(&bytes);
+            let mut scanner = Scanner::new(&source);
+            let found = scanner
+                .scan_tokens()
+                .iter()
+                .filter(|token| matches!(token.kind, TokenType::CodeBlock))
+                .find_map(|token| props(&token.literal_str(&source)?));
+            if let Some(found) = found {
+                components.insert(name.clone(), found);
+            }
+        }
+
+        return components;
+    }
+
+    // An import chain that leads back to the template being run, with the import that
+    // starts it
+    fn circular_import(&self) -> Option<Diagnostic> {
+        let path = Path::new(&self.source_name);
+        if !path.is_file() || !self.dispatcher.is_template(path) {
+            return None;
+        }
+
+        let dispatcher = &self.dispatcher;
+        let graph = Graph::build(&[path.to_path_buf()], |path| {
+            dispatcher.handles(path) && dispatcher.is_template(path)
+        });
+        let chain = graph.cycle(0)?;
+        let first = graph.edge(chain[0], chain[1])?;
+
+        let names: Vec<String> = chain
+            .iter()
+            .map(|&file| format!("`{}`", graph.files[file].display()))
+            .collect();
+        let message = format!(
+            "Circular import: {} imports {}",
+            names[0],
+            names[1..].join(", which imports ")
+        );
+        let diagnostic = Diagnostic::error(first.line, &message)
+            .with_code(codes::CIRCULAR_IMPORT)
+            .with_span(first.span)
+            .with_help("move what they share into another component, or pass it in a slot");
+        return Some(diagnostic);
     }
 
     pub fn error(&mut self, line: usize, message: &str) {
-        self.report(line, "", message);
+        self.diagnostic(&Diagnostic::error(line, message));
+    }
+
+    pub fn warning(&mut self, line: usize, message: &str) {
+        self.diagnostic(&Diagnostic::warning(line, message));
+    }
+
+    pub fn diagnostic(&mut self, diagnostic: &Diagnostic) {
+        if let Some(collected) = &mut self.collected {
+            collected.push(diagnostic.clone());
+            return;
+        }
+        if self.reached_max_errors() {
+            return;
+        }
+
+        let denied;
+        let diagnostic = match diagnostic.severity {
+            Severity::Warning if self.deny_warnings => {
+                denied = Diagnostic {
+                    severity: Severity::Error,
+                    ..diagnostic.clone()
+                };
+                &denied
+            }
+            _ => diagnostic,
+        };
+        match diagnostic.severity {
+            Severity::Error => self.error_count += 1,
+            Severity::Warning => self.warning_count += 1,
+        }
+
+        if let Some(sink) = &mut self.error_sink {
+            let sink = sink.get_mut().unwrap_or_else(PoisonError::into_inner);
+            sink(&self.source_name, diagnostic);
+            return;
+        }
+
+        match self.error_format {
+            ErrorFormat::Human => eprint!(
+                "{}",
+                snippet::render(
+                    diagnostic,
+                    &self.source_name,
+                    self.source
+                        .as_ref()
+                        .map(|(source, index)| (source.as_str(), index)),
+                    self.color
+                )
+            ),
+            ErrorFormat::Json => {
+                let mut json = diagnostic.to_json();
+                json["file"] = json!(self.source_name);
+                eprintln!("{}", json);
+            }
+        }
+    }
+}
+
+impl Default for Regg {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Prints `line` to stdout like `println!`. Once stdout is closed, like by
+/// `regg page.regg | head -5`, there's no one left to print for and Regg exits quietly
+/// instead of panicking.
+pub fn print_line(line: impl fmt::Display) {
+    let result = writeln!(io::stdout().lock(), "{}", line);
+    match result {
+        Ok(()) => {}
+        Err(error) if error.kind() == io::ErrorKind::BrokenPipe => {
+            std::process::exit(exit_code::OK)
+        }
+        Err(error) => panic!("failed printing to stdout: {}", error),
     }
+}
 
-    fn report(&mut self, line: usize, place: &str, message: &str) {
-        // TODO: improve error handling
-        // https://github.com/zkat/miette
-        // http://www.unicode.org/charts/PDF/U2500.pdf
-        //    ╭
-        // 15 │ <h1> {, heading.toUpperCase() } </h1>
-        //    .       ┬
-        //    .       │
-        //    .       └─ unexpected character `,`
+// Whether a REPL input still has an open code block, expression or element
+fn is_incomplete(input: &str) -> bool {
+    if input.matches("---").count() % 2 == 1
+        || input.matches('{').count() > input.matches('}').count()
+        || input.matches("(`").count() > input.matches("`)").count()
+    {
+        return true;
+    }
 
-        println!("[line {}] Error{}: {}", line, place, message);
+    let mut scanner = Scanner::new(input);
+    let tokens = scanner.scan_tokens();
+    // the elements left open, like the parser a closing tag closes the last one with its
+    // name and the ones inside it, and one that closes nothing is ignored
+    let mut open: Vec<String> = Vec::new();
+    let mut tag_name = String::new();
 
-        self.had_error = true;
+    for token in tokens.iter() {
+        let literal = tokens.literal_str(token).unwrap_or_default();
+        match token.kind {
+            TokenType::OpeningTagStart => tag_name = literal.into_owned(),
+            TokenType::DynamicTagStart => tag_name = format!("{{{}}}", literal),
+            TokenType::OpeningTagEnd if !tag_name.is_empty() && !is_void_element(&tag_name) => {
+                open.push(std::mem::take(&mut tag_name))
+            }
+            TokenType::FragmentOpen => open.push(String::new()),
+            TokenType::ClosingTag | TokenType::FragmentClose => {
+                let name = match token.kind {
+                    TokenType::ClosingTag => &*literal,
+                    _ => "",
+                };
+                if let Some(index) = open.iter().rposition(|tag| tag.eq_ignore_ascii_case(name)) {
+                    open.truncate(index);
+                }
+            }
+            _ => {}
+        }
+
+        if !matches!(
+            token.kind,
+            TokenType::OpeningTagStart
+                | TokenType::DynamicTagStart
+                | TokenType::TextToken
+                | TokenType::Expression
+                | TokenType::SpreadAttribute
+                | TokenType::ShorthandAttribute
+                | TokenType::BooleanAttribute
+                | TokenType::ClientDirective
+        ) {
+            tag_name.clear();
+        }
     }
+
+    return !open.is_empty();
 }
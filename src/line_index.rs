@@ -0,0 +1,163 @@
+// Converts between the byte offsets of spans and the lines and columns editors and
+// terminals show. Built once per source, every conversion is a binary search.
+//
+// Columns can be counted in bytes (UTF-8), in UTF-16 code units like LSP clients do by
+// default, or in characters (UTF-32). They only differ after a character outside of ASCII,
+// so only those are indexed: between two of them, every byte is one column in any encoding.
+
+/// What the columns of a `LineColumn` count
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ColumnEncoding {
+    Utf8,
+    #[default]
+    Utf16,
+    Utf32, // characters
+}
+
+/// A position in the source, both counted from 0
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct LineColumn {
+    pub line: usize,
+    pub column: usize,
+}
+
+#[derive(Debug, Clone)]
+pub struct LineIndex {
+    len: usize,
+    line_starts: Vec<usize>,         // byte offset of the start of every line
+    wide_chars: Vec<(usize, usize)>, // byte offset and UTF-8 length of every non-ASCII character
+    // For the first `n` wide characters, how many more bytes than UTF-16 code units and
+    // than characters they take
+    utf16_savings: Vec<usize>,
+    utf32_savings: Vec<usize>,
+}
+
+impl LineIndex {
+    pub fn new(text: &str) -> Self {
+        let line_starts = std::iter::once(0)
+            .chain(text.match_indices('\n').map(|(index, _)| index + 1))
+            .collect();
+
+        let wide_chars: Vec<(usize, usize)> = text
+            .char_indices()
+            .filter(|(_, c)| !c.is_ascii())
+            .map(|(offset, c)| (offset, c.len_utf8()))
+            .collect();
+
+        let mut utf16_savings = vec![0];
+        let mut utf32_savings = vec![0];
+        for (offset, length) in &wide_chars {
+            let c = text[*offset..].chars().next().unwrap_or_default();
+            utf16_savings.push(utf16_savings[utf16_savings.len() - 1] + length - c.len_utf16());
+            utf32_savings.push(utf32_savings[utf32_savings.len() - 1] + length - 1);
+        }
+
+        return Self {
+            len: text.len(),
+            line_starts,
+            wide_chars,
+            utf16_savings,
+            utf32_savings,
+        };
+    }
+
+    pub fn line_count(&self) -> usize {
+        return self.line_starts.len();
+    }
+
+    /// The byte offset a line starts at
+    pub fn line_start(&self, line: usize) -> Option<usize> {
+        return self.line_starts.get(line).copied();
+    }
+
+    /// The byte offset a line ends at, before its `\n`
+    pub fn line_end(&self, line: usize) -> Option<usize> {
+        self.line_starts.get(line)?;
+        return Some(
+            self.line_starts
+                .get(line + 1)
+                .map_or(self.len, |next| next - 1),
+        );
+    }
+
+    /// The line and column of a byte offset. An offset past the end is the end, an offset
+    /// inside a character is the start of that character.
+    pub fn line_column(&self, offset: usize, encoding: ColumnEncoding) -> LineColumn {
+        let offset = self.floor_char_boundary(offset);
+        let line = self.line_starts.partition_point(|&start| start <= offset) - 1;
+        let start = self.line_starts[line];
+        let column = (offset - start) - self.savings(start, offset, encoding);
+        return LineColumn { line, column };
+    }
+
+    /// The byte offset of a line and column. A column past the end of the line is the end
+    /// of the line, a line past the end of the source is the end of the source.
+    pub fn offset(&self, position: LineColumn, encoding: ColumnEncoding) -> usize {
+        let (Some(start), Some(end)) =
+            (self.line_start(position.line), self.line_end(position.line))
+        else {
+            return self.len;
+        };
+
+        // the last wide character that starts before the column, its column and width
+        let first = self
+            .wide_chars
+            .partition_point(|&(offset, _)| offset < start);
+        let last = self.wide_chars.partition_point(|&(offset, _)| offset < end);
+        let column_of = |index: usize| {
+            let offset = self.wide_chars[index].0;
+            return (offset - start) - self.savings_between(first, index, encoding);
+        };
+        let (mut low, mut high) = (first, last);
+        while low < high {
+            let middle = (low + high) / 2;
+            match column_of(middle) < position.column {
+                true => low = middle + 1,
+                false => high = middle,
+            }
+        }
+
+        let (base, base_column) = match (low > first).then(|| low - 1) {
+            Some(wide) => {
+                let (offset, length) = self.wide_chars[wide];
+                let column = column_of(wide);
+                let width = length - self.savings_between(wide, wide + 1, encoding);
+                if position.column < column + width {
+                    return offset; // inside the character, like between two UTF-16 surrogates
+                }
+                (offset + length, column + width)
+            }
+            None => (start, 0),
+        };
+        return (base + position.column - base_column).min(end);
+    }
+
+    // How many more bytes than columns the wide characters between two offsets take
+    fn savings(&self, start: usize, end: usize, encoding: ColumnEncoding) -> usize {
+        let first = self
+            .wide_chars
+            .partition_point(|&(offset, _)| offset < start);
+        let last = self.wide_chars.partition_point(|&(offset, _)| offset < end);
+        return self.savings_between(first, last, encoding);
+    }
+
+    // How many more bytes than columns the wide characters `first..last` take
+    fn savings_between(&self, first: usize, last: usize, encoding: ColumnEncoding) -> usize {
+        match encoding {
+            ColumnEncoding::Utf8 => return 0,
+            ColumnEncoding::Utf16 => return self.utf16_savings[last] - self.utf16_savings[first],
+            ColumnEncoding::Utf32 => return self.utf32_savings[last] - self.utf32_savings[first],
+        }
+    }
+
+    fn floor_char_boundary(&self, offset: usize) -> usize {
+        let offset = offset.min(self.len);
+        let index = self
+            .wide_chars
+            .partition_point(|&(start, _)| start <= offset);
+        match index.checked_sub(1).map(|index| self.wide_chars[index]) {
+            Some((start, length)) if offset < start + length => return start,
+            _ => return offset,
+        }
+    }
+}
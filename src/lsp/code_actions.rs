@@ -0,0 +1,237 @@
+// Code actions, as edits of the source found with the syntax tree: close an element that's
+// never closed, turn an empty element into a self-closing one, and wrap the selection in a
+// new element.
+
+use std::collections::HashMap;
+
+use lsp_types::{
+    CodeAction, CodeActionKind, CodeActionOrCommand, Range, TextEdit, Url, WorkspaceEdit,
+};
+
+use super::document::Document;
+use crate::cst::{self, NodeKind, SyntaxElement, SyntaxNode, SyntaxToken, TokenKind};
+use crate::html::is_void_element;
+use crate::span::Span;
+use crate::token_type::TokenType;
+
+// The element a selection is wrapped in, editors let the user rename it right after
+const WRAPPER: &str = "div";
+
+pub fn code_actions(uri: &Url, document: &Document, range: Range) -> Vec<CodeActionOrCommand> {
+    let tree = cst::parse(&document.text);
+    let start = document.offset(range.start);
+    let end = document.offset(range.end);
+    let mut actions = Vec::new();
+
+    if let Some(element) = element_at(&tree, start) {
+        let name = tag_name(element);
+        if is_unclosed(element) {
+            // after the last child, on a line of its own if the element spans lines
+            let end = element
+                .tokens()
+                .into_iter()
+                .rev()
+                .find(|token| !token.is_trivia())
+                .map_or(element.span.end, |token| token.span.end);
+            let closing = format!("</{}>", name);
+            let text = match document.text[end..element.span.end].contains('\n') {
+                true => format!("\n{}{}", indentation(document, element.span.start), closing),
+                false => closing.clone(),
+            };
+            actions.push(action(
+                uri,
+                document,
+                &format!("Add `{}`", closing),
+                CodeActionKind::QUICKFIX,
+                vec![(Span::new(end, end), text)],
+            ));
+        }
+        if let Some(tag_end) = empty_element_end(element) {
+            // `<div class="a"></div>` becomes `<div class="a" />`
+            let before = &document.text[..tag_end];
+            let text = match before.ends_with(char::is_whitespace) {
+                true => "/>",
+                false => " />",
+            };
+            actions.push(action(
+                uri,
+                document,
+                &format!("Convert `<{}>` to a self-closing tag", name),
+                CodeActionKind::REFACTOR_REWRITE,
+                vec![(Span::new(tag_end, element.span.end), text.to_string())],
+            ));
+        }
+    }
+
+    if start < end && can_wrap(&tree, start, end) {
+        actions.push(action(
+            uri,
+            document,
+            &format!("Wrap in `<{}>`", WRAPPER),
+            CodeActionKind::REFACTOR_REWRITE,
+            vec![
+                (Span::new(start, start), format!("<{}>", WRAPPER)),
+                (Span::new(end, end), format!("</{}>", WRAPPER)),
+            ],
+        ));
+    }
+
+    return actions;
+}
+
+// The innermost element whose start tag contains `offset`
+fn element_at(node: &SyntaxNode, offset: usize) -> Option<&SyntaxNode> {
+    for child in node.child_nodes() {
+        if child.span.start > offset || offset > child.span.end {
+            continue;
+        }
+        if let Some(element) = element_at(child, offset) {
+            return Some(element);
+        }
+        let start_tag = child.child_nodes().next();
+        if child.kind == NodeKind::Element
+            && start_tag.is_some_and(|tag| tag.span.start <= offset && offset <= tag.span.end)
+        {
+            return Some(child);
+        }
+    }
+    return None;
+}
+
+// `div` for `<div>`, `{tag}` for `<{ tag }>`
+fn tag_name(element: &SyntaxNode) -> String {
+    let start = element
+        .tokens()
+        .into_iter()
+        .find(|token| !token.is_trivia())
+        .map(|token| token.text.trim_start_matches('<').trim())
+        .unwrap_or_default();
+
+    match start
+        .strip_prefix('{')
+        .and_then(|name| name.strip_suffix('}'))
+    {
+        Some(expression) => return format!("{{{}}}", expression.trim()),
+        None => return start.to_string(),
+    }
+}
+
+// An element with a complete start tag, that isn't void or self-closing, and that has no
+// closing tag of its own
+fn is_unclosed(element: &SyntaxNode) -> bool {
+    let Some(end) = start_tag_end(element) else {
+        return false;
+    };
+    let name = tag_name(element);
+    if matches!(end.kind, TokenKind::Token(TokenType::SelfClosingTagEnd))
+        || (!name.starts_with('{') && is_void_element(&name))
+    {
+        return false;
+    }
+
+    match element.children.last() {
+        Some(SyntaxElement::Token(token))
+            if matches!(token.kind, TokenKind::Token(TokenType::ClosingTag)) =>
+        {
+            // a stray closing tag at the end belongs to the element it's in
+            let closing = token.text.trim_start_matches("</").trim_end_matches('>');
+            return !without_whitespace(closing).eq_ignore_ascii_case(&without_whitespace(&name));
+        }
+        _ => return true,
+    }
+}
+
+// Where the `>` of an element without children starts, if it has a closing tag
+fn empty_element_end(element: &SyntaxNode) -> Option<usize> {
+    let end = start_tag_end(element)?;
+    if !matches!(end.kind, TokenKind::Token(TokenType::OpeningTagEnd)) {
+        return None;
+    }
+
+    let mut rest = element.children.iter().skip(1);
+    let closed = rest.all(|child| match child {
+        SyntaxElement::Token(token) => {
+            token.is_trivia() || matches!(token.kind, TokenKind::Token(TokenType::ClosingTag))
+        }
+        SyntaxElement::Node(_) => false,
+    });
+    return closed
+        .then_some(end.span.start)
+        .filter(|_| !is_unclosed(element));
+}
+
+// The `>` or `/>` that ends the start tag of an element
+fn start_tag_end(element: &SyntaxNode) -> Option<&SyntaxToken> {
+    let start_tag = element.child_nodes().next()?;
+    return start_tag.tokens().into_iter().rev().find(|token| {
+        matches!(
+            token.kind,
+            TokenKind::Token(TokenType::OpeningTagEnd | TokenType::SelfClosingTagEnd)
+        )
+    });
+}
+
+// A selection can be wrapped if it starts and ends between the children of the same node,
+// without cutting through a tag or an expression, only text can be split
+fn can_wrap(node: &SyntaxNode, start: usize, end: usize) -> bool {
+    for child in &node.children {
+        let span = match child {
+            SyntaxElement::Node(node) => node.span,
+            SyntaxElement::Token(token) => token.span,
+        };
+        let cuts = |offset: usize| span.start < offset && offset < span.end;
+        if !cuts(start) && !cuts(end) {
+            continue;
+        }
+
+        match child {
+            // the whole selection is inside this child
+            SyntaxElement::Node(inner) if span.start <= start && end <= span.end => {
+                return inner.kind != NodeKind::StartTag && can_wrap(inner, start, end);
+            }
+            SyntaxElement::Token(token) if is_text(token) => continue,
+            _ => return false,
+        }
+    }
+    return true;
+}
+
+// The whitespace at the start of the line `offset` is on
+fn indentation(document: &Document, offset: usize) -> &str {
+    let line_start = document.text[..offset]
+        .rfind('\n')
+        .map_or(0, |index| index + 1);
+    let line = &document.text[line_start..];
+    return &line[..line.len() - line.trim_start_matches([' ', '\t']).len()];
+}
+
+fn without_whitespace(text: &str) -> String {
+    return text.chars().filter(|c| !c.is_whitespace()).collect();
+}
+
+fn is_text(token: &SyntaxToken) -> bool {
+    return token.is_trivia() || matches!(token.kind, TokenKind::Token(TokenType::TextToken));
+}
+
+fn action(
+    uri: &Url,
+    document: &Document,
+    title: &str,
+    kind: CodeActionKind,
+    edits: Vec<(Span, String)>,
+) -> CodeActionOrCommand {
+    let edits = edits
+        .into_iter()
+        .map(|(span, text)| TextEdit::new(document.range(span), text))
+        .collect();
+
+    return CodeActionOrCommand::CodeAction(CodeAction {
+        title: title.to_string(),
+        kind: Some(kind),
+        edit: Some(WorkspaceEdit {
+            changes: Some(HashMap::from([(uri.clone(), edits)])),
+            ..Default::default()
+        }),
+        ..Default::default()
+    });
+}
@@ -0,0 +1,112 @@
+use lsp_types::{
+    CompletionItem, CompletionItemKind, CompletionTextEdit, Position, Range, TextEdit,
+};
+
+use super::document::Document;
+use crate::html::{is_void_element, ELEMENTS};
+use crate::scanner::Scanner;
+use crate::token_type::TokenType;
+
+/// Tag names and imported components after `<`, the innermost open element after `</`
+pub fn completions(document: &Document, position: Position) -> Vec<CompletionItem> {
+    let offset = document.offset(position);
+    let before = &document.text[..offset];
+    let name_start = before.trim_end_matches(is_tag_name_char).len();
+
+    let typed = document.range_of(name_start, offset);
+    if let Some(prefix) = before[..name_start].strip_suffix("</") {
+        let Some(name) = open_elements(prefix).pop() else {
+            return Vec::new();
+        };
+
+        // replace the `</` too, so the item can be filtered by the `</` the user typed
+        let closing = format!("</{}>", name);
+        return vec![CompletionItem {
+            label: closing.clone(),
+            kind: Some(CompletionItemKind::SNIPPET),
+            text_edit: Some(edit(document.range_of(prefix.len(), offset), &closing)),
+            ..Default::default()
+        }];
+    }
+
+    if !before[..name_start].ends_with('<') {
+        return Vec::new();
+    }
+
+    let mut items: Vec<CompletionItem> = components(document)
+        .into_iter()
+        .map(|(name, source)| CompletionItem {
+            label: name.clone(),
+            kind: Some(CompletionItemKind::CLASS),
+            detail: Some(source),
+            text_edit: Some(edit(typed, &name)),
+            ..Default::default()
+        })
+        .collect();
+
+    items.extend(ELEMENTS.iter().map(|name| CompletionItem {
+        label: name.to_string(),
+        kind: Some(CompletionItemKind::PROPERTY),
+        text_edit: Some(edit(typed, name)),
+        ..Default::default()
+    }));
+
+    return items;
+}
+
+/// The names of the elements still open at the end of `source`, outermost first
+pub fn open_elements(source: &str) -> Vec<String> {
+    let mut scanner = Scanner::new(source);
+    let mut open: Vec<String> = Vec::new();
+    let mut tag = None;
+
+    for token in scanner.scan_tokens() {
+        match token.kind {
+            TokenType::OpeningTagStart => tag = Some(token.literal_string(source)),
+            TokenType::DynamicTagStart => {
+                tag = Some(format!("{{{}}}", token.literal_string(source)))
+            }
+            TokenType::OpeningTagEnd => {
+                if let Some(name) = tag.take().filter(|name| !is_void_element(name)) {
+                    open.push(name);
+                }
+            }
+            TokenType::SelfClosingTagEnd => tag = None,
+            TokenType::ClosingTag => {
+                let name = token.literal_string(source);
+                if let Some(index) = open
+                    .iter()
+                    .rposition(|open| open.eq_ignore_ascii_case(&name))
+                {
+                    open.truncate(index);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    return open;
+}
+
+/// The capitalized names imported by the frontmatter, like `Card`, and where they come from
+pub fn components(document: &Document) -> Vec<(String, String)> {
+    let mut components = Vec::new();
+
+    for import in document.imports() {
+        for name in import.names {
+            if name.starts_with(|c: char| c.is_uppercase()) {
+                components.push((name, import.source.clone()));
+            }
+        }
+    }
+
+    return components;
+}
+
+fn edit(range: Range, text: &str) -> CompletionTextEdit {
+    return CompletionTextEdit::Edit(TextEdit::new(range, text.to_string()));
+}
+
+fn is_tag_name_char(c: char) -> bool {
+    return c.is_alphanumeric() || matches!(c, '-' | '_' | '.' | ':');
+}
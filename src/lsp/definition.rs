@@ -0,0 +1,60 @@
+// Go to definition: the tag of an imported component goes to the file it's imported from,
+// a name in an expression to where the frontmatter declares it.
+
+use std::path::PathBuf;
+
+use lsp_types::{Location, Position, Range, Url};
+
+use super::document::Document;
+use crate::token_type::TokenType;
+
+pub fn definition(uri: &Url, document: &Document, position: Position) -> Option<Location> {
+    let offset = document.offset(position);
+    let token = document.tokens().token_at(offset)?;
+
+    match token.kind {
+        TokenType::OpeningTagStart | TokenType::ClosingTag => {
+            // `<Card.Title>` is in the file `Card` is imported from
+            let name = token.literal_str(&document.text)?;
+            let component = name.split('.').next()?;
+            let path = component_path(uri, document, component)?;
+            return Some(Location::new(
+                Url::from_file_path(path).ok()?,
+                Range::default(),
+            ));
+        }
+        TokenType::Expression
+        | TokenType::ShorthandAttribute
+        | TokenType::SpreadAttribute
+        | TokenType::DynamicTagStart => {
+            let word = document.word_at(offset)?;
+            if document.text[..word.start].ends_with('.') {
+                return None; // a property, like `name` in `{user.name}`
+            }
+
+            let name = &document.text[word.start..word.end];
+            let declaration = document
+                .declarations()
+                .into_iter()
+                .find(|declaration| declaration.name == name)?;
+            return Some(Location::new(uri.clone(), document.range(declaration.span)));
+        }
+        _ => return None,
+    }
+}
+
+/// The file a component is imported from, if it's a relative path to a file that exists
+pub fn component_path(uri: &Url, document: &Document, component: &str) -> Option<PathBuf> {
+    let import = document
+        .imports()
+        .into_iter()
+        .find(|import| import.names.iter().any(|name| name == component))?;
+    if !import.source.starts_with('.') {
+        return None; // a package, or an alias only the bundler knows
+    }
+
+    let dir = uri.to_file_path().ok()?.parent()?.to_path_buf();
+    // `components` drops the `.` of `./Card.regg`
+    let path: PathBuf = dir.join(&import.source).components().collect();
+    return Some(path).filter(|path| path.is_file());
+}
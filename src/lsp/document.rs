@@ -0,0 +1,196 @@
+use lsp_types::{Position, Range, TextDocumentContentChangeEvent};
+
+use crate::ast::Node;
+use crate::declarations::{declarations, imports, Declaration, DeclarationKind, Import};
+use crate::diagnostic::Diagnostic;
+use crate::flags::Flags;
+use crate::line_index::{ColumnEncoding, LineColumn, LineIndex};
+use crate::parser::{Parsed, Parser};
+use crate::scanner::{Scanner, TextEdit};
+use crate::span::Span;
+use crate::token::TokenStream;
+use crate::token_type::TokenType;
+
+/// An open document, kept in sync with the editor.
+///
+/// LSP positions count UTF-16 code units, spans count bytes; `offset` and `position`
+/// convert between the two. The tokens and the tree are kept too, and only scanned and
+/// parsed again around an edit.
+pub struct Document {
+    pub text: String,
+    pub version: i32,
+    line_index: LineIndex,
+    tokens: TokenStream,
+    scanner_diagnostics: Vec<Diagnostic>,
+    parsed: Parsed,
+    flags: Flags,
+}
+
+impl Document {
+    /// `{#if}` blocks are resolved with `flags`
+    pub fn new(text: String, version: i32, flags: &Flags) -> Self {
+        let line_index = LineIndex::new(&text);
+        let mut scanner = Scanner::new(&text);
+        scanner.scan_tokens();
+        let scanner_diagnostics = scanner.diagnostics().clone();
+        let tokens = scanner.into_tokens();
+        let parsed = Parser::new(&tokens)
+            .with_flags(flags.clone())
+            .parse_incremental();
+        return Self {
+            text,
+            version,
+            line_index,
+            tokens,
+            scanner_diagnostics,
+            parsed,
+            flags: flags.clone(),
+        };
+    }
+
+    /// Applies an edit from `textDocument/didChange`, the whole text if it has no range
+    pub fn apply(&mut self, change: TextDocumentContentChangeEvent) {
+        let Some(range) = change.range else {
+            *self = Self::new(change.text, self.version, &self.flags);
+            return;
+        };
+
+        let start = self.offset(range.start);
+        let end = self.offset(range.end).max(start);
+        let length = self.text.len();
+        self.text.replace_range(start..end, &change.text);
+        self.line_index = LineIndex::new(&self.text);
+
+        let edit = TextEdit {
+            span: Span::new(start, end),
+            text: change.text,
+        };
+        let mut scanner = Scanner::new(&self.text);
+        self.tokens = scanner.relex(std::mem::take(&mut self.tokens), &edit);
+        let Some(damage) = scanner.damage() else {
+            return;
+        };
+
+        // The scanner reports errors where it finds them, but apart from the limits, which
+        // an editor's documents don't reach, they're all for something left unterminated
+        // and found at the end of the source. So they only change if it scanned the end
+        // again, otherwise they only moved.
+        match damage.old.end < length {
+            true => {
+                let bytes = damage.new.end as isize - damage.old.end as isize;
+                for diagnostic in &mut self.scanner_diagnostics {
+                    diagnostic.shift(bytes, damage.lines);
+                }
+            }
+            false => self.scanner_diagnostics = scanner.diagnostics().clone(),
+        }
+
+        let previous = std::mem::take(&mut self.parsed);
+        self.parsed = Parser::new(&self.tokens)
+            .with_flags(self.flags.clone())
+            .reparse(previous, damage);
+    }
+
+    pub fn tokens(&self) -> &TokenStream {
+        return &self.tokens;
+    }
+
+    pub fn nodes(&self) -> &[Node] {
+        return &self.parsed.nodes;
+    }
+
+    /// The scanner's and parser's diagnostics, in source order
+    pub fn diagnostics(&self) -> Vec<Diagnostic> {
+        let mut diagnostics = self.scanner_diagnostics.clone();
+        diagnostics.extend(self.parsed.diagnostics.iter().cloned());
+        return diagnostics;
+    }
+
+    /// The names the frontmatter declares with their spans in the document, without the
+    /// parameters of its functions, which can't be used by the template
+    pub fn declarations(&self) -> Vec<Declaration> {
+        let mut found = Vec::new();
+        for (start, code) in self.code_blocks() {
+            for mut declaration in declarations(&code) {
+                if declaration.kind == DeclarationKind::Parameter {
+                    continue;
+                }
+                declaration.span =
+                    Span::new(start + declaration.span.start, start + declaration.span.end);
+                found.push(declaration);
+            }
+        }
+        return found;
+    }
+
+    /// The `import` statements of the frontmatter
+    pub fn imports(&self) -> Vec<Import> {
+        return self
+            .code_blocks()
+            .iter()
+            .flat_map(|(_, code)| imports(code))
+            .collect();
+    }
+
+    /// The identifier at or right before `offset`, like `user` in `{user.name}`
+    pub fn word_at(&self, offset: usize) -> Option<Span> {
+        let is_word = |c: char| c.is_alphanumeric() || c == '_' || c == '$';
+        let start = self.text[..offset].trim_end_matches(is_word).len();
+        let end = self.text[offset..]
+            .find(|c: char| !is_word(c))
+            .map_or(self.text.len(), |length| offset + length);
+
+        let word = &self.text[start..end];
+        if word.is_empty() || word.starts_with(|c: char| c.is_ascii_digit()) {
+            return None;
+        }
+        return Some(Span::new(start, end));
+    }
+
+    /// The byte offset of an LSP position, clamped to the end of its line
+    pub fn offset(&self, position: Position) -> usize {
+        let position = LineColumn {
+            line: position.line as usize,
+            column: position.character as usize,
+        };
+        return self.line_index.offset(position, ColumnEncoding::Utf16);
+    }
+
+    pub fn position(&self, offset: usize) -> Position {
+        let position = self.line_index.line_column(offset, ColumnEncoding::Utf16);
+        return Position::new(position.line as u32, position.column as u32);
+    }
+
+    pub fn range(&self, span: Span) -> Range {
+        return Range::new(self.position(span.start), self.position(span.end));
+    }
+
+    /// The range between two byte offsets
+    pub fn range_of(&self, start: usize, end: usize) -> Range {
+        return Range::new(self.position(start), self.position(end));
+    }
+
+    /// The whole of a line, numbered from 1 like in diagnostics
+    pub fn line_range(&self, line: usize) -> Range {
+        let index = line.saturating_sub(1).min(self.line_index.line_count() - 1);
+        let start = self.line_index.line_start(index).unwrap_or_default();
+        let end = self.line_index.line_end(index).unwrap_or_default();
+        return self.range(Span::new(start, end));
+    }
+
+    // The code of every code block and the offset it starts at, after the opening `---`
+    fn code_blocks(&self) -> Vec<(usize, String)> {
+        return self
+            .tokens
+            .iter()
+            .filter(|token| matches!(token.kind, TokenType::CodeBlock))
+            .map(|token| {
+                let code = token
+                    .literal_str(&self.text)
+                    .unwrap_or_default()
+                    .into_owned();
+                (token.span.start + "---".len(), code)
+            })
+            .collect();
+    }
+}
@@ -0,0 +1,69 @@
+// Folding ranges from the syntax tree: the frontmatter, and elements, fragments, markup
+// expressions and expressions that span several lines. The line with the closing tag or
+// brace stays visible when folded.
+
+use lsp_types::{FoldingRange, FoldingRangeKind};
+
+use super::document::Document;
+use crate::cst::{self, NodeKind, SyntaxElement, SyntaxNode, TokenKind};
+use crate::token_type::TokenType;
+
+pub fn folding_ranges(document: &Document) -> Vec<FoldingRange> {
+    let tree = cst::parse(&document.text);
+    let mut ranges = Vec::new();
+    fold(document, &tree, &mut ranges);
+    return ranges;
+}
+
+fn fold(document: &Document, node: &SyntaxNode, ranges: &mut Vec<FoldingRange>) {
+    if matches!(
+        node.kind,
+        NodeKind::Element | NodeKind::Fragment | NodeKind::HTMLExpr
+    ) {
+        // up to the start of the last token, the closing tag if there is one
+        let tokens = node.tokens();
+        if let Some(last) = tokens.iter().rev().find(|token| !token.is_trivia()) {
+            push(document, ranges, node.span.start, last.span.start, None);
+        }
+    }
+
+    for child in &node.children {
+        match child {
+            SyntaxElement::Node(child) => fold(document, child, ranges),
+            SyntaxElement::Token(token) => match token.kind {
+                TokenKind::Token(TokenType::CodeBlock) => {
+                    let kind = Some(FoldingRangeKind::Region);
+                    let end = token.span.end.saturating_sub("---".len());
+                    push(document, ranges, token.span.start, end, kind);
+                }
+                TokenKind::Token(TokenType::Expression) => {
+                    let end = token.span.end.saturating_sub("}".len());
+                    push(document, ranges, token.span.start, end, None);
+                }
+                _ => {}
+            },
+        }
+    }
+}
+
+// Folds the lines after the one `start` is on, up to the line before the one `end` is on
+fn push(
+    document: &Document,
+    ranges: &mut Vec<FoldingRange>,
+    start: usize,
+    end: usize,
+    kind: Option<FoldingRangeKind>,
+) {
+    let start_line = document.position(start).line;
+    let end_line = document.position(end).line.saturating_sub(1);
+    if end_line <= start_line {
+        return;
+    }
+
+    ranges.push(FoldingRange {
+        start_line,
+        end_line,
+        kind,
+        ..Default::default()
+    });
+}
@@ -0,0 +1,127 @@
+// Hovers: what an HTML element is, where a component comes from, what a Regg directive
+// does, and how the frontmatter declares a name used in an expression.
+
+use lsp_types::{Hover, HoverContents, MarkupContent, MarkupKind, Position, Url};
+
+use super::definition::component_path;
+use super::document::Document;
+use crate::html::description;
+use crate::span::Span;
+use crate::token::Token;
+use crate::token_type::TokenType;
+
+pub fn hover(uri: &Url, document: &Document, position: Position) -> Option<Hover> {
+    let offset = document.offset(position);
+    let mut in_tag = false;
+
+    for token in document.tokens() {
+        if !matches!(token.kind, TokenType::EOF)
+            && token.span.start <= offset
+            && offset <= token.span.end
+        {
+            let (value, span) = match token.kind {
+                TokenType::OpeningTagStart | TokenType::ClosingTag => tag(uri, document, token)?,
+                TokenType::TextToken | TokenType::BooleanAttribute | TokenType::ClientDirective
+                    if in_tag =>
+                {
+                    attribute(token, &document.text)?
+                }
+                TokenType::Expression
+                | TokenType::ShorthandAttribute
+                | TokenType::SpreadAttribute
+                | TokenType::DynamicTagStart => name(document, offset)?,
+                _ => return None,
+            };
+            return Some(Hover {
+                contents: HoverContents::Markup(MarkupContent {
+                    kind: MarkupKind::Markdown,
+                    value,
+                }),
+                range: Some(document.range(span)),
+            });
+        }
+
+        match token.kind {
+            TokenType::OpeningTagStart | TokenType::DynamicTagStart => in_tag = true,
+            TokenType::OpeningTagEnd | TokenType::SelfClosingTagEnd => in_tag = false,
+            _ => {}
+        }
+    }
+
+    return None;
+}
+
+// An HTML element's description, or the file a component is imported from
+fn tag(uri: &Url, document: &Document, token: &Token) -> Option<(String, Span)> {
+    let name = token.literal_str(&document.text)?;
+    let start = token.span.start + token.lexeme(&document.text).find(&*name)?;
+    let span = Span::new(start, start + name.len());
+
+    let component = name.split('.').next()?;
+    let import = document
+        .imports()
+        .into_iter()
+        .find(|import| import.names.iter().any(|imported| imported == component));
+    if let Some(import) = import {
+        let mut value = format!("**`<{}>`** from `{}`", name, import.source);
+        if let Some(path) = component_path(uri, document, component) {
+            value += &format!("\n\n{}", path.display());
+        }
+        return Some((value, span));
+    }
+
+    let value = format!("**`<{}>`**\n\n{}", name, description(&name)?);
+    return Some((value, span));
+}
+
+// What a Regg directive like `data:set` does
+fn attribute(token: &Token, source: &str) -> Option<(String, Span)> {
+    let name = token.lexeme(source).split('=').next()?.trim_end();
+    let description = match name {
+        "data:set" => "Adds a `data-*` attribute for every property of the object.",
+        "set:html" => "Sets the children of the element as raw HTML, without escaping.",
+        "transition:name" => "Identifies the element on every page for view transitions.",
+        "transition:persist" => "Keeps the element and its state between pages.",
+        "client:load" => "Hydrates the component as soon as the page loads.",
+        "client:idle" => {
+            "Hydrates the component once the page is done loading and the browser is idle."
+        }
+        "client:visible" => "Hydrates the component once it's scrolled into view.",
+        "client:media" => "Hydrates the component once the media query in the value matches.",
+        "client:only" => {
+            "Renders the component only in the browser, with the framework in the value."
+        }
+        _ => return None,
+    };
+
+    let value = format!("**`{}`**\n\n{}", name, description);
+    return Some((
+        value,
+        Span::new(token.span.start, token.span.start + name.len()),
+    ));
+}
+
+// The line of the frontmatter that declares the name at `offset`
+fn name(document: &Document, offset: usize) -> Option<(String, Span)> {
+    let word = document.word_at(offset)?;
+    if document.text[..word.start].ends_with('.') {
+        return None; // a property, like `name` in `{user.name}`
+    }
+
+    let name = &document.text[word.start..word.end];
+    let declaration = document
+        .declarations()
+        .into_iter()
+        .find(|declaration| declaration.name == name)?;
+
+    let line_start = document.text[..declaration.span.start]
+        .rfind('\n')
+        .map_or(0, |index| index + 1);
+    let line_end = document.text[declaration.span.end..]
+        .find('\n')
+        .map_or(document.text.len(), |index| declaration.span.end + index);
+    let line = document.text[line_start..line_end].trim();
+
+    let value = format!("```js\n{}\n```", line);
+    return Some((value, word));
+}
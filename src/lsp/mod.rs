@@ -0,0 +1,357 @@
+// `regg lsp`: a Language Server Protocol server over stdio. Open documents are kept in
+// sync incrementally and the scanner's and parser's diagnostics are published on every change.
+
+mod code_actions;
+mod completion;
+mod definition;
+mod document;
+mod folding;
+mod hover;
+mod rename;
+mod semantic_tokens;
+mod symbols;
+
+use std::{collections::HashMap, error::Error, path::Path};
+
+use lsp_server::{Connection, Message, Notification, Request, RequestId, Response};
+use lsp_types::{
+    notification::{
+        DidChangeTextDocument, DidCloseTextDocument, DidOpenTextDocument, LogMessage,
+        Notification as _, PublishDiagnostics,
+    },
+    request::{
+        CodeActionRequest, Completion, DocumentSymbolRequest, FoldingRangeRequest, GotoDefinition,
+        HoverRequest, Rename, Request as _, SemanticTokensFullRequest,
+    },
+    CodeActionProviderCapability, CompletionOptions, CompletionResponse,
+    DiagnosticRelatedInformation, DiagnosticSeverity, DocumentSymbolResponse,
+    FoldingRangeProviderCapability, GotoDefinitionResponse, HoverProviderCapability, Location,
+    LogMessageParams, MessageType, NumberOrString, OneOf, PublishDiagnosticsParams, SemanticTokens,
+    SemanticTokensFullOptions, SemanticTokensOptions, SemanticTokensResult,
+    SemanticTokensServerCapabilities, ServerCapabilities, TextDocumentSyncCapability,
+    TextDocumentSyncKind, Url,
+};
+
+use crate::diagnostic::{Diagnostic, Severity};
+use crate::flags::Flags;
+use document::Document;
+
+type Result<T> = std::result::Result<T, Box<dyn Error + Sync + Send>>;
+
+/// Serves editors over stdin and stdout until they shut the server down
+pub fn run() -> Result<()> {
+    let (connection, io_threads) = Connection::stdio();
+    serve(&connection)?;
+
+    drop(connection); // lets the writer thread finish
+    io_threads.join()?;
+    return Ok(());
+}
+
+/// Serves the editor on the other end of `connection`, from `initialize` to `shutdown`
+pub fn serve(connection: &Connection) -> Result<()> {
+    let capabilities = ServerCapabilities {
+        text_document_sync: Some(TextDocumentSyncCapability::Kind(
+            TextDocumentSyncKind::INCREMENTAL,
+        )),
+        completion_provider: Some(CompletionOptions {
+            trigger_characters: Some(vec!["<".to_string(), "/".to_string()]),
+            ..Default::default()
+        }),
+        code_action_provider: Some(CodeActionProviderCapability::Simple(true)),
+        definition_provider: Some(OneOf::Left(true)),
+        folding_range_provider: Some(FoldingRangeProviderCapability::Simple(true)),
+        hover_provider: Some(HoverProviderCapability::Simple(true)),
+        document_symbol_provider: Some(OneOf::Left(true)),
+        rename_provider: Some(OneOf::Left(true)),
+        semantic_tokens_provider: Some(SemanticTokensServerCapabilities::SemanticTokensOptions(
+            SemanticTokensOptions {
+                legend: semantic_tokens::legend(),
+                full: Some(SemanticTokensFullOptions::Bool(true)),
+                ..Default::default()
+            },
+        )),
+        ..Default::default()
+    };
+    connection.initialize(serde_json::to_value(capabilities)?)?;
+
+    let mut server = Server {
+        connection,
+        documents: HashMap::new(),
+        // an invalid regg.toml is reported by `regg check`, here it only means no flags
+        flags: Flags::load(Path::new(".")).unwrap_or_default(),
+    };
+    return server.serve();
+}
+
+struct Server<'a> {
+    connection: &'a Connection,
+    documents: HashMap<Url, Document>,
+    flags: Flags,
+}
+
+impl Server<'_> {
+    fn serve(&mut self) -> Result<()> {
+        for message in &self.connection.receiver {
+            match message {
+                Message::Request(request) => {
+                    if self.connection.handle_shutdown(&request)? {
+                        return Ok(());
+                    }
+                    self.request(request)?;
+                }
+                Message::Notification(notification) => self.notification(notification)?,
+                Message::Response(_) => {}
+            }
+        }
+        return Ok(());
+    }
+
+    fn request(&mut self, request: Request) -> Result<()> {
+        // params the request can't have are the editor's mistake, the server goes on
+        let id = request.id.clone();
+        let response = self.response(request).unwrap_or_else(|error| {
+            Response::new_err(
+                id,
+                lsp_server::ErrorCode::InvalidParams as i32,
+                error.to_string(),
+            )
+        });
+        self.connection.sender.send(response.into())?;
+        return Ok(());
+    }
+
+    fn response(&mut self, request: Request) -> serde_json::Result<Response> {
+        let response = match request.method.as_str() {
+            Completion::METHOD => {
+                let (id, params) = request_params::<Completion>(request)?;
+                let position = params.text_document_position;
+                let items = self
+                    .documents
+                    .get(&position.text_document.uri)
+                    .map(|document| completion::completions(document, position.position))
+                    .unwrap_or_default();
+                Response::new_ok(id, CompletionResponse::Array(items))
+            }
+            CodeActionRequest::METHOD => {
+                let (id, params) = request_params::<CodeActionRequest>(request)?;
+                let uri = params.text_document.uri;
+                let actions = self
+                    .documents
+                    .get(&uri)
+                    .map(|document| code_actions::code_actions(&uri, document, params.range))
+                    .unwrap_or_default();
+                Response::new_ok(id, actions)
+            }
+            FoldingRangeRequest::METHOD => {
+                let (id, params) = request_params::<FoldingRangeRequest>(request)?;
+                let ranges = self
+                    .documents
+                    .get(&params.text_document.uri)
+                    .map(folding::folding_ranges)
+                    .unwrap_or_default();
+                Response::new_ok(id, ranges)
+            }
+            GotoDefinition::METHOD => {
+                let (id, params) = request_params::<GotoDefinition>(request)?;
+                let position = params.text_document_position_params;
+                let uri = position.text_document.uri;
+                let location = self
+                    .documents
+                    .get(&uri)
+                    .and_then(|document| definition::definition(&uri, document, position.position));
+                Response::new_ok(id, location.map(GotoDefinitionResponse::Scalar))
+            }
+            HoverRequest::METHOD => {
+                let (id, params) = request_params::<HoverRequest>(request)?;
+                let position = params.text_document_position_params;
+                let uri = position.text_document.uri;
+                let hover = self
+                    .documents
+                    .get(&uri)
+                    .and_then(|document| hover::hover(&uri, document, position.position));
+                Response::new_ok(id, hover)
+            }
+            DocumentSymbolRequest::METHOD => {
+                let (id, params) = request_params::<DocumentSymbolRequest>(request)?;
+                let symbols = self
+                    .documents
+                    .get(&params.text_document.uri)
+                    .map(symbols::document_symbols)
+                    .unwrap_or_default();
+                Response::new_ok(id, DocumentSymbolResponse::Nested(symbols))
+            }
+            Rename::METHOD => {
+                let (id, params) = request_params::<Rename>(request)?;
+                let position = params.text_document_position;
+                let uri = position.text_document.uri;
+                let edit = self.documents.get(&uri).map(|document| {
+                    rename::rename(&uri, document, position.position, &params.new_name)
+                });
+                match edit {
+                    Some(Err(message)) => {
+                        Response::new_err(id, lsp_server::ErrorCode::RequestFailed as i32, message)
+                    }
+                    Some(Ok(edit)) => Response::new_ok(id, edit),
+                    None => Response::new_ok(id, serde_json::Value::Null),
+                }
+            }
+            SemanticTokensFullRequest::METHOD => {
+                let (id, params) = request_params::<SemanticTokensFullRequest>(request)?;
+                let data = self
+                    .documents
+                    .get(&params.text_document.uri)
+                    .map(semantic_tokens::semantic_tokens)
+                    .unwrap_or_default();
+                Response::new_ok(
+                    id,
+                    SemanticTokensResult::Tokens(SemanticTokens {
+                        result_id: None,
+                        data,
+                    }),
+                )
+            }
+            _ => Response::new_err(
+                request.id,
+                lsp_server::ErrorCode::MethodNotFound as i32,
+                format!("`{}` isn't supported", request.method),
+            ),
+        };
+        return Ok(response);
+    }
+
+    fn notification(&mut self, notification: Notification) -> Result<()> {
+        match notification.method.as_str() {
+            DidOpenTextDocument::METHOD => {
+                let Some(params) = self.params::<DidOpenTextDocument>(notification)? else {
+                    return Ok(());
+                };
+                let document = params.text_document;
+                self.documents.insert(
+                    document.uri.clone(),
+                    Document::new(document.text, document.version, &self.flags),
+                );
+                self.publish_diagnostics(&document.uri)?;
+            }
+            DidChangeTextDocument::METHOD => {
+                let Some(params) = self.params::<DidChangeTextDocument>(notification)? else {
+                    return Ok(());
+                };
+                let uri = params.text_document.uri;
+                if let Some(document) = self.documents.get_mut(&uri) {
+                    for change in params.content_changes {
+                        document.apply(change);
+                    }
+                    document.version = params.text_document.version;
+                    self.publish_diagnostics(&uri)?;
+                }
+            }
+            DidCloseTextDocument::METHOD => {
+                let Some(params) = self.params::<DidCloseTextDocument>(notification)? else {
+                    return Ok(());
+                };
+                let uri = params.text_document.uri;
+                self.documents.remove(&uri);
+                // clear the squiggles of the closed file
+                self.send_notification::<PublishDiagnostics>(PublishDiagnosticsParams::new(
+                    uri,
+                    Vec::new(),
+                    None,
+                ))?;
+            }
+            _ => {}
+        }
+        return Ok(());
+    }
+
+    fn publish_diagnostics(&self, uri: &Url) -> Result<()> {
+        let Some(document) = self.documents.get(uri) else {
+            return Ok(());
+        };
+
+        let diagnostics = document
+            .diagnostics()
+            .iter()
+            .map(|diagnostic| to_lsp_diagnostic(uri, document, diagnostic))
+            .collect();
+        return self.send_notification::<PublishDiagnostics>(PublishDiagnosticsParams::new(
+            uri.clone(),
+            diagnostics,
+            Some(document.version),
+        ));
+    }
+
+    // The params of `notification`, `None` if it can't have them. There's nothing to reply
+    // to, the editor is told in its log instead.
+    fn params<N: lsp_types::notification::Notification>(
+        &self,
+        notification: Notification,
+    ) -> Result<Option<N::Params>> {
+        match serde_json::from_value(notification.params) {
+            Ok(params) => return Ok(Some(params)),
+            Err(error) => {
+                self.send_notification::<LogMessage>(LogMessageParams {
+                    typ: MessageType::ERROR,
+                    message: format!("Invalid params for `{}`: {}", N::METHOD, error),
+                })?;
+                return Ok(None);
+            }
+        }
+    }
+
+    fn send_notification<N: lsp_types::notification::Notification>(
+        &self,
+        params: N::Params,
+    ) -> Result<()> {
+        let notification = Notification::new(N::METHOD.to_string(), params);
+        self.connection.sender.send(notification.into())?;
+        return Ok(());
+    }
+}
+
+fn request_params<R: lsp_types::request::Request>(
+    request: Request,
+) -> serde_json::Result<(RequestId, R::Params)> {
+    return Ok((request.id, serde_json::from_value(request.params)?));
+}
+
+fn to_lsp_diagnostic(
+    uri: &Url,
+    document: &Document,
+    diagnostic: &Diagnostic,
+) -> lsp_types::Diagnostic {
+    let range = match diagnostic.span {
+        Some(span) => document.range(span),
+        None => document.line_range(diagnostic.line),
+    };
+    let severity = match diagnostic.severity {
+        Severity::Error => DiagnosticSeverity::ERROR,
+        Severity::Warning => DiagnosticSeverity::WARNING,
+    };
+
+    let mut message = diagnostic.message.clone();
+    if let Some(help) = &diagnostic.help {
+        message += &format!("\nhelp: {}", help);
+    }
+
+    let related_information = diagnostic
+        .labels
+        .iter()
+        .map(|label| DiagnosticRelatedInformation {
+            location: Location::new(uri.clone(), document.range(label.span)),
+            message: label.message.clone(),
+        })
+        .collect::<Vec<_>>();
+
+    return lsp_types::Diagnostic {
+        range,
+        severity: Some(severity),
+        code: diagnostic
+            .code
+            .map(|code| NumberOrString::String(code.to_string())),
+        source: Some("regg".to_string()),
+        message,
+        related_information: Some(related_information).filter(|labels| !labels.is_empty()),
+        ..Default::default()
+    };
+}
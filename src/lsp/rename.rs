@@ -0,0 +1,120 @@
+// Renaming a name the frontmatter declares: its declaration and every use in the code
+// blocks and expressions of the document, and for components the tags too.
+
+use std::collections::HashMap;
+
+use lsp_types::{Position, TextEdit, Url, WorkspaceEdit};
+
+use super::document::Document;
+use crate::declarations::{is_identifier, references};
+use crate::span::Span;
+use crate::token_type::TokenType;
+
+pub fn rename(
+    uri: &Url,
+    document: &Document,
+    position: Position,
+    new_name: &str,
+) -> Result<WorkspaceEdit, String> {
+    let offset = document.offset(position);
+    let tokens = document.tokens();
+
+    let nothing = || "There's nothing to rename here".to_string();
+    let token = tokens.token_at(offset).ok_or_else(nothing)?;
+    let literal = token.literal_str(&document.text).unwrap_or_default();
+    let name = match token.kind {
+        // `Card` in `<Card.Title>`
+        TokenType::OpeningTagStart | TokenType::ClosingTag if !literal.starts_with('{') => {
+            literal.split('.').next().unwrap_or_default().to_string()
+        }
+        TokenType::CodeBlock
+        | TokenType::Expression
+        | TokenType::ShorthandAttribute
+        | TokenType::SpreadAttribute
+        | TokenType::DynamicTagStart
+        | TokenType::ClosingTag => {
+            let word = document.word_at(offset).ok_or_else(nothing)?;
+            document.text[word.start..word.end].to_string()
+        }
+        _ => return Err(nothing()),
+    };
+
+    let declarations = document.declarations();
+    if !declarations
+        .iter()
+        .any(|declaration| declaration.name == name)
+    {
+        return Err(format!("`{}` isn't declared in the frontmatter", name));
+    }
+    if !is_identifier(new_name) {
+        return Err(format!("`{}` isn't a valid name", new_name));
+    }
+    if declarations
+        .iter()
+        .any(|declaration| declaration.name == new_name)
+    {
+        return Err(format!(
+            "`{}` is already declared in the frontmatter",
+            new_name
+        ));
+    }
+    let is_component = name.starts_with(|c: char| c.is_uppercase());
+    if is_component && !new_name.starts_with(|c: char| c.is_uppercase()) {
+        // `<card>` would be an HTML element
+        return Err(format!(
+            "`{}` is used as a component, its name has to start with an uppercase letter",
+            name
+        ));
+    }
+
+    let mut edits: Vec<(Span, String)> = Vec::new();
+    for token in tokens {
+        let text = &document.text[token.span.start..token.span.end];
+        // code, where the name can be used, `</{tag}>` closes a dynamic tag
+        let is_code = match token.kind {
+            TokenType::CodeBlock
+            | TokenType::Expression
+            | TokenType::SpreadAttribute
+            | TokenType::DynamicTagStart => true,
+            TokenType::ClosingTag => text.contains('{'),
+            _ => false,
+        };
+        if is_code {
+            for span in references(text, &name) {
+                let span = Span::new(token.span.start + span.start, token.span.start + span.end);
+                edits.push((span, new_name.to_string()));
+            }
+            continue;
+        }
+
+        match token.kind {
+            TokenType::ShorthandAttribute
+                if token.literal_str(&document.text).as_deref() == Some(name.as_str()) =>
+            {
+                // `{title}` is short for `title={title}`, the attribute keeps its name
+                edits.push((token.span, format!("{}={{{}}}", name, new_name)));
+            }
+            TokenType::OpeningTagStart | TokenType::ClosingTag if is_component => {
+                let literal = token.literal_str(&document.text).unwrap_or_default();
+                if literal == name || literal.starts_with(&format!("{}.", name)) {
+                    let start = token.span.start + text.find(&*literal).unwrap_or_default();
+                    edits.push((Span::new(start, start + name.len()), new_name.to_string()));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    // tokens after a markup expression can start inside it
+    edits.sort_by_key(|(span, _)| span.start);
+    edits.dedup_by_key(|(span, _)| span.start);
+
+    let edits = edits
+        .into_iter()
+        .map(|(span, text)| TextEdit::new(document.range(span), text))
+        .collect();
+    return Ok(WorkspaceEdit {
+        changes: Some(HashMap::from([(uri.clone(), edits)])),
+        ..Default::default()
+    });
+}
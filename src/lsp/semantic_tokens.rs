@@ -0,0 +1,308 @@
+// Semantic tokens: the scanner's tokens split into tag names, attributes, expression code and
+// punctuation, so editors can highlight templates without a grammar of their own. The code
+// in the frontmatter and in expressions only gets a rough JavaScript highlighting of
+// keywords, identifiers, strings, numbers and comments.
+
+use lsp_types::{Position, SemanticToken, SemanticTokenType, SemanticTokensLegend};
+
+use super::document::Document;
+use crate::token::Token;
+use crate::token_type::TokenType;
+
+// The token types, their index in the legend is what tokens refer to
+const TOKEN_TYPES: [SemanticTokenType; 9] = [
+    SemanticTokenType::TYPE,     // tag names
+    SemanticTokenType::CLASS,    // component names, like `Card` or `Card.Title`
+    SemanticTokenType::PROPERTY, // attribute names
+    SemanticTokenType::STRING,
+    SemanticTokenType::VARIABLE,
+    SemanticTokenType::OPERATOR, // `<`, `>`, `{`, `}`, `---`, ...
+    SemanticTokenType::KEYWORD,  // JavaScript keywords and `#if`, `:else`, `/if`, `@html`
+    SemanticTokenType::NUMBER,
+    SemanticTokenType::COMMENT,
+];
+const TAG: u32 = 0;
+const COMPONENT: u32 = 1;
+const ATTRIBUTE: u32 = 2;
+const STRING: u32 = 3;
+const VARIABLE: u32 = 4;
+const OPERATOR: u32 = 5;
+const KEYWORD: u32 = 6;
+const NUMBER: u32 = 7;
+const COMMENT: u32 = 8;
+
+const JS_KEYWORDS: [&str; 39] = [
+    "as",
+    "async",
+    "await",
+    "break",
+    "case",
+    "catch",
+    "class",
+    "const",
+    "continue",
+    "default",
+    "delete",
+    "do",
+    "else",
+    "export",
+    "extends",
+    "false",
+    "finally",
+    "for",
+    "from",
+    "function",
+    "if",
+    "import",
+    "in",
+    "instanceof",
+    "let",
+    "new",
+    "null",
+    "of",
+    "return",
+    "switch",
+    "this",
+    "throw",
+    "true",
+    "try",
+    "typeof",
+    "undefined",
+    "var",
+    "while",
+    "yield",
+];
+
+pub fn legend() -> SemanticTokensLegend {
+    return SemanticTokensLegend {
+        token_types: TOKEN_TYPES.to_vec(),
+        token_modifiers: Vec::new(),
+    };
+}
+
+/// The semantic tokens of the whole document, in the relative encoding LSP uses
+pub fn semantic_tokens(document: &Document) -> Vec<SemanticToken> {
+    let mut highlighter = Highlighter {
+        source: &document.text,
+        spans: Vec::new(),
+        end: 0,
+        in_tag: false,
+    };
+    for token in document.tokens() {
+        highlighter.token(token);
+    }
+    return encode(document, &highlighter.spans);
+}
+
+struct Highlighter<'a> {
+    source: &'a str,
+    spans: Vec<(usize, usize, u32)>, // start, end and token type, in source order
+    end: usize,                      // where the last span ends, spans never overlap
+    in_tag: bool,
+}
+
+impl Highlighter<'_> {
+    fn token(&mut self, token: &Token) {
+        let (start, end) = (token.span.start, token.span.end);
+        let text = self.source.get(start..end).unwrap_or_default();
+
+        match token.kind {
+            TokenType::CodeBlock => {
+                let fence = match text.len() >= 6 && text.ends_with("---") {
+                    true => 3,
+                    false => 0, // unterminated
+                };
+                self.push(start, start + 3, OPERATOR);
+                self.javascript(start + 3, end - fence);
+                self.push(end - fence, end, OPERATOR);
+            }
+            TokenType::OpeningTagStart => {
+                self.in_tag = true;
+                self.push(start, start + 1, OPERATOR);
+                self.push(start + 1, end, tag_type(&text[1..]));
+            }
+            TokenType::DynamicTagStart => {
+                self.in_tag = true;
+                self.push(start, start + 1, OPERATOR);
+                self.expression(start + 1, end);
+            }
+            TokenType::OpeningTagEnd | TokenType::SelfClosingTagEnd => {
+                self.in_tag = false;
+                self.push(start, end, OPERATOR);
+            }
+            TokenType::ClosingTag => {
+                // `</name>`, `</{name}>` for dynamic tags
+                let close = usize::from(text.ends_with('>'));
+                let inner = &text[2.min(text.len())..text.len() - close];
+                let name_start = start + 2 + (inner.len() - inner.trim_start().len());
+                let name_end = end - close - (inner.len() - inner.trim_end().len());
+
+                self.push(start, start + 2, OPERATOR);
+                match inner.trim().starts_with('{') {
+                    true => self.expression(name_start, name_end),
+                    false => self.push(name_start, name_end, tag_type(inner.trim())),
+                }
+                self.push(end - close, end, OPERATOR);
+            }
+            TokenType::FragmentOpen
+            | TokenType::FragmentClose
+            | TokenType::HTMLExprStart
+            | TokenType::HTMLExprEnd => self.push(start, end, OPERATOR),
+            TokenType::TextToken | TokenType::ClientDirective if self.in_tag => {
+                match text.find('=') {
+                    // `name="value"`, or `name=` before an Expression
+                    Some(index) => {
+                        self.push(start, start + index, ATTRIBUTE);
+                        self.push(start + index, start + index + 1, OPERATOR);
+                        self.push(start + index + 1, end, STRING);
+                    }
+                    None => self.push(start, end, ATTRIBUTE),
+                }
+            }
+            TokenType::BooleanAttribute => self.push(start, end, ATTRIBUTE),
+            TokenType::Expression | TokenType::ShorthandAttribute | TokenType::SpreadAttribute => {
+                self.expression(start, end)
+            }
+            _ => {}
+        }
+    }
+
+    // `{code}`, where the code can start with a block keyword like `#if` or with `...`
+    fn expression(&mut self, start: usize, end: usize) {
+        let text = &self.source[start..end];
+        let open = usize::from(text.starts_with('{'));
+        let close = usize::from(text.len() > open && text.ends_with('}'));
+        self.push(start, start + open, OPERATOR);
+
+        let code_start = start + open;
+        let code = &self.source[code_start..end - close];
+        let trimmed = code.trim_start();
+        let code_start = code_start + (code.len() - trimmed.len());
+
+        let mut rest = code_start;
+        if trimmed.starts_with("...") {
+            self.push(code_start, code_start + 3, OPERATOR);
+            rest += 3;
+        } else if trimmed.starts_with(['#', ':', '/', '@'])
+            && trimmed[1..].starts_with(|c: char| c.is_ascii_alphabetic())
+        {
+            let length = trimmed[1..]
+                .find(|c: char| !c.is_ascii_alphanumeric())
+                .map_or(trimmed.len(), |index| index + 1);
+            self.push(code_start, code_start + length, KEYWORD);
+            rest += length;
+        }
+
+        self.javascript(rest, end - close);
+        self.push(end - close, end, OPERATOR);
+    }
+
+    fn javascript(&mut self, start: usize, end: usize) {
+        let code = &self.source[start..end];
+        let mut index = 0;
+
+        while let Some(c) = code[index..].chars().next() {
+            let rest = &code[index..];
+            let token = match c {
+                '/' if rest.starts_with("//") => {
+                    Some((COMMENT, rest.find('\n').unwrap_or(rest.len())))
+                }
+                '/' if rest.starts_with("/*") => Some((
+                    COMMENT,
+                    rest[2..].find("*/").map_or(rest.len(), |index| index + 4),
+                )),
+                '"' | '\'' | '`' => Some((STRING, string_length(rest, c))),
+                c if c.is_ascii_digit() => Some((
+                    NUMBER,
+                    rest.find(|c: char| !(c.is_ascii_alphanumeric() || c == '.' || c == '_'))
+                        .unwrap_or(rest.len()),
+                )),
+                c if c.is_alphabetic() || c == '_' || c == '$' => {
+                    let length = word_length(rest);
+                    match JS_KEYWORDS.contains(&&rest[..length]) {
+                        true => Some((KEYWORD, length)),
+                        false => Some((VARIABLE, length)),
+                    }
+                }
+                _ => None,
+            };
+
+            match token {
+                Some((token_type, length)) => {
+                    self.push(start + index, start + index + length, token_type);
+                    index += length;
+                }
+                None => index += c.len_utf8(),
+            }
+        }
+    }
+
+    fn push(&mut self, start: usize, end: usize, token_type: u32) {
+        let start = start.max(self.end);
+        if start >= end {
+            return;
+        }
+        self.spans.push((start, end, token_type));
+        self.end = end;
+    }
+}
+
+// LSP tokens are relative to the previous one and can't span lines, so multi-line spans,
+// like a code block, become a token per line
+fn encode(document: &Document, spans: &[(usize, usize, u32)]) -> Vec<SemanticToken> {
+    let mut tokens = Vec::new();
+    let mut previous = Position::new(0, 0);
+
+    for &(start, end, token_type) in spans {
+        let mut line_start = start;
+        for line in document.text[start..end].split('\n') {
+            let length = line.trim_end_matches('\r').encode_utf16().count() as u32;
+            if length > 0 {
+                let position = document.position(line_start);
+                let delta_line = position.line - previous.line;
+                let delta_start = match delta_line {
+                    0 => position.character - previous.character,
+                    _ => position.character,
+                };
+                tokens.push(SemanticToken {
+                    delta_line,
+                    delta_start,
+                    length,
+                    token_type,
+                    token_modifiers_bitset: 0,
+                });
+                previous = position;
+            }
+            line_start += line.len() + 1;
+        }
+    }
+
+    return tokens;
+}
+
+fn tag_type(name: &str) -> u32 {
+    match name.starts_with(|c: char| c.is_uppercase()) || name.contains('.') {
+        true => COMPONENT,
+        false => TAG,
+    }
+}
+
+// The length of a string literal starting with `quote`, to the end of the code if unterminated
+fn string_length(rest: &str, quote: char) -> usize {
+    let mut escaped = false;
+    for (index, c) in rest.char_indices().skip(1) {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            c if c == quote => return index + 1,
+            _ => {}
+        }
+    }
+    return rest.len();
+}
+
+fn word_length(rest: &str) -> usize {
+    return rest
+        .find(|c: char| !(c.is_alphanumeric() || c == '_' || c == '$'))
+        .unwrap_or(rest.len());
+}
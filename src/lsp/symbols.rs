@@ -0,0 +1,154 @@
+// The outline of a template for `textDocument/documentSymbol`: the frontmatter with the
+// names it declares, then the elements nested like in the template.
+
+use lsp_types::{DocumentSymbol, SymbolKind};
+
+use super::document::Document;
+use crate::ast::{Attribute, Element, Node};
+use crate::declarations::{declarations, DeclarationKind};
+use crate::span::Span;
+use crate::token_type::TokenType;
+
+/// The symbols of the document, with its `{#if}` blocks resolved like in diagnostics
+pub fn document_symbols(document: &Document) -> Vec<DocumentSymbol> {
+    let tokens = document.tokens();
+    let mut symbols = Vec::new();
+
+    for token in tokens {
+        if !matches!(token.kind, TokenType::CodeBlock) {
+            continue;
+        }
+
+        // the code starts after the opening `---`
+        let code_start = token.span.start + "---".len();
+        let code = token.literal_str(&document.text).unwrap_or_default();
+        let children = declarations(&code)
+            .into_iter()
+            .filter(|declaration| declaration.kind != DeclarationKind::Parameter)
+            .map(|declaration| {
+                let span = Span::new(
+                    code_start + declaration.span.start,
+                    code_start + declaration.span.end,
+                );
+                let kind = match declaration.kind {
+                    DeclarationKind::Function => SymbolKind::FUNCTION,
+                    DeclarationKind::Class => SymbolKind::CLASS,
+                    DeclarationKind::Import => SymbolKind::MODULE,
+                    _ => SymbolKind::VARIABLE,
+                };
+                symbol(
+                    document,
+                    declaration.name,
+                    None,
+                    kind,
+                    span,
+                    span,
+                    Vec::new(),
+                )
+            })
+            .collect();
+
+        symbols.push(symbol(
+            document,
+            "frontmatter".to_string(),
+            None,
+            SymbolKind::NAMESPACE,
+            token.span,
+            Span::new(token.span.start, code_start),
+            children,
+        ));
+    }
+
+    symbols.extend(element_symbols(document, document.nodes()));
+    return symbols;
+}
+
+// Elements become symbols, the elements inside fragments and markup expressions are
+// children of the closest element
+fn element_symbols(document: &Document, nodes: &[Node]) -> Vec<DocumentSymbol> {
+    let mut symbols = Vec::new();
+
+    for node in nodes {
+        match node {
+            Node::Element(element) => {
+                let name = match element.dynamic {
+                    true => format!("{{{}}}", element.name),
+                    false => element.name.to_string(),
+                };
+                let kind = match is_component(element) {
+                    true => SymbolKind::CLASS,
+                    false => SymbolKind::FIELD,
+                };
+                symbols.push(symbol(
+                    document,
+                    name,
+                    detail(element),
+                    kind,
+                    element.span,
+                    element.start_tag,
+                    element_symbols(document, &element.children),
+                ));
+            }
+            Node::Fragment(children) | Node::HTMLExpr(children) => {
+                symbols.extend(element_symbols(document, children));
+            }
+            _ => {}
+        }
+    }
+
+    return symbols;
+}
+
+// `#main.card.wide` for `id="main" class="card wide"`, like a CSS selector
+fn detail(element: &Element) -> Option<String> {
+    let mut detail = String::new();
+
+    for attribute in &element.attributes {
+        let Attribute::Text(text) = attribute else {
+            continue;
+        };
+        let Some((name, value)) = text.split_once('=') else {
+            continue;
+        };
+        let value = value.trim().trim_matches(|c| c == '"' || c == '\'');
+        match name.trim() {
+            "id" if !value.is_empty() => detail.insert_str(0, &format!("#{}", value)),
+            "class" => {
+                for class in value.split_whitespace() {
+                    detail.push('.');
+                    detail.push_str(class);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    return Some(detail).filter(|detail| !detail.is_empty());
+}
+
+fn is_component(element: &Element) -> bool {
+    return !element.dynamic
+        && (element.name.starts_with(|c: char| c.is_uppercase()) || element.name.contains('.'));
+}
+
+fn symbol(
+    document: &Document,
+    name: String,
+    detail: Option<String>,
+    kind: SymbolKind,
+    span: Span,
+    selection: Span,
+    children: Vec<DocumentSymbol>,
+) -> DocumentSymbol {
+    #[allow(deprecated)] // `deprecated` is replaced by `tags`, but has to be set
+    return DocumentSymbol {
+        name,
+        detail,
+        kind,
+        tags: None,
+        deprecated: None,
+        range: document.range(span),
+        selection_range: document.range(selection),
+        children: Some(children).filter(|children| !children.is_empty()),
+    };
+}
@@ -1,22 +1,172 @@
-use clap::Parser;
-use regg::Regg;
+use std::{
+    num::NonZeroUsize,
+    panic,
+    path::{Path, PathBuf},
+};
+
+use clap::{Parser, Subcommand};
+use regg::{
+    codes,
+    diagnostic::{ColorChoice, ErrorFormat},
+    encoding::Encoding,
+    exit_code,
+    flags::Flags,
+    graph::GraphFormat,
+    ice, lsp, print_line, watch, Regg,
+};
 
 #[derive(Parser, Debug)]
 #[command(name = "Regg")]
 #[command(author = "Yash Gupta <yashguptaz@pm.me>")]
 #[command(version)]
 #[command(about = "Regg is a pet-project tempalting engine written in Rust by @yashguptaz", long_about = None)]
+#[command(args_conflicts_with_subcommands = true)]
 struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
+
     /// Run Regg on this file
     file: Option<String>,
+
+    /// Encoding of the file, `utf-8` or `latin-1`
+    #[arg(long, global = true, default_value_t = Encoding::Utf8)]
+    encoding: Encoding,
+
+    /// Print diagnostics as `human` readable text or as one `json` object per line
+    #[arg(long, global = true, value_name = "FORMAT", default_value_t = ErrorFormat::Human)]
+    error_format: ErrorFormat,
+
+    /// Print diagnostics in color: `auto`, `always` or `never`
+    #[arg(long, global = true, value_name = "WHEN", default_value_t = ColorChoice::Auto)]
+    color: ColorChoice,
+
+    /// Fail with exit code 1 when there are more than this many warnings
+    #[arg(long, global = true, value_name = "N")]
+    max_warnings: Option<usize>,
+
+    /// Stop after this many errors
+    #[arg(long, global = true, value_name = "N")]
+    max_errors: Option<NonZeroUsize>,
+
+    /// Report warnings as errors, `-D warnings`
+    #[arg(short = 'D', global = true, value_name = "warnings", value_parser = ["warnings"])]
+    deny: Option<String>,
+
+    /// If Regg crashes, write a bug report with the input that caused it to FILE
+    #[arg(
+        long,
+        value_name = "FILE",
+        num_args = 0..=1,
+        require_equals = true,
+        global = true,
+        default_missing_value = "regg-ice.txt"
+    )]
+    ice_dump: Option<PathBuf>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Report the errors and warnings in files and directories without printing tokens
+    Check {
+        /// Files, or directories to check the templates in
+        #[arg(required = true)]
+        paths: Vec<PathBuf>,
+
+        /// Check the files again every time they change
+        #[arg(long)]
+        watch: bool,
+
+        /// Check this many files at once, one per core by default
+        #[arg(short, long, value_name = "N")]
+        jobs: Option<NonZeroUsize>,
+    },
+    /// Explain a diagnostic code, like `E0002`, with an example and how to fix it
+    Explain {
+        /// The code, as shown in diagnostics
+        code: String,
+    },
+    /// Start a language server on stdin and stdout, for editors
+    Lsp,
+    /// Re-indent templates and normalize the spacing in tags and expressions
+    Fmt {
+        /// Files, or directories to format the templates in
+        #[arg(required = true)]
+        paths: Vec<PathBuf>,
+
+        /// Don't write the files, exit with code 1 if any would be reformatted
+        #[arg(long)]
+        check: bool,
+    },
+    /// Print which templates import which components, to see what pages pull in
+    Graph {
+        /// Files, or directories with the templates to start from
+        #[arg(required = true)]
+        paths: Vec<PathBuf>,
+
+        /// Print the graph for Graphviz, `dot`, or as `json`
+        #[arg(long, default_value_t = GraphFormat::Dot)]
+        format: GraphFormat,
+    },
 }
 
 fn main() {
-    let mut regg: Regg = Regg::new();
     let args = Args::parse();
+    let flags = match Flags::load(Path::new(".")) {
+        Ok(flags) => flags,
+        Err(error) => {
+            eprintln!("Error reading flags: {}", error);
+            std::process::exit(exit_code::CONFIG_ERROR)
+        }
+    };
+    let mut regg = Regg::builder()
+        .encoding(args.encoding)
+        .max_warnings(args.max_warnings)
+        .max_errors(args.max_errors.map(NonZeroUsize::get))
+        .strict(args.deny.is_some())
+        .error_format(args.error_format)
+        .color(args.color.enabled())
+        .flags(flags)
+        .build();
+    ice::install_hook(args.ice_dump);
+
+    let result = panic::catch_unwind(move || match (args.command, args.file) {
+        (Some(Command::Check { paths, watch, jobs }), _) => {
+            regg.set_print_tokens(false);
+            regg.set_jobs(jobs.map(NonZeroUsize::get));
+            if watch {
+                if let Err(error) = watch::watch(&mut regg, &paths) {
+                    eprintln!("Error watching files: {}", error);
+                    std::process::exit(exit_code::IO_ERROR)
+                }
+            }
+            std::process::exit(regg.check(&paths))
+        }
+        (Some(Command::Fmt { paths, check }), _) => {
+            regg.set_print_tokens(false);
+            std::process::exit(regg.format_files(&paths, check))
+        }
+        (Some(Command::Graph { paths, format }), _) => {
+            std::process::exit(regg.graph(&paths, format))
+        }
+        (Some(Command::Explain { code }), _) => match codes::explain(&code) {
+            Some(explanation) => print_line(explanation),
+            None => {
+                eprintln!("No diagnostic has the code `{}`", code);
+                std::process::exit(exit_code::USAGE_ERROR)
+            }
+        },
+        (Some(Command::Lsp), _) => {
+            if let Err(error) = lsp::run() {
+                eprintln!("Error in the language server: {}", error);
+                std::process::exit(exit_code::IO_ERROR)
+            }
+        }
+        (None, Some(file)) => regg.run_file(&file[..]),
+        (None, None) => regg.run_prompt(),
+    });
 
-    match args.file {
-        Some(file) => regg.run_file(&file[..]),
-        None => regg.run_prompt(),
+    if result.is_err() {
+        // the panic was already reported by the ICE hook
+        std::process::exit(exit_code::INTERNAL_ERROR)
     }
 }
@@ -0,0 +1,82 @@
+// Markdown pages, behind the `markdown` feature: the body is converted to HTML and the
+// frontmatter can name the layout the page goes into.
+
+use std::path::Path;
+
+use pulldown_cmark::{html, Options, Parser};
+
+use crate::codes;
+use crate::diagnostic::Diagnostic;
+use crate::{print_line, Regg};
+
+/// Markdown page: converted to HTML, which is printed like the tokens of a template
+pub fn markdown(regg: &mut Regg, path: &str, bytes: Vec<u8>) {
+    let content = regg.decode(&bytes);
+    let (frontmatter, body) = split_frontmatter(&content);
+
+    // `layout: ../layouts/Post.regg`, relative to the page
+    if let Some((line, layout)) = frontmatter.and_then(|frontmatter| field(frontmatter, "layout")) {
+        let dir = Path::new(path).parent().unwrap_or(Path::new(""));
+        if !dir.join(layout).is_file() {
+            let message = format!("Layout `{}` doesn't exist", layout);
+            regg.quote_source(&content);
+            regg.diagnostic(&Diagnostic::error(line, &message).with_code(codes::MISSING_LAYOUT));
+        }
+    }
+
+    let html = to_html(body);
+    if regg.prints_tokens() {
+        print_line(html);
+    }
+}
+
+/// Converts Markdown to HTML, with tables, footnotes, strikethrough and task lists
+pub fn to_html(markdown: &str) -> String {
+    let options = Options::ENABLE_TABLES
+        | Options::ENABLE_FOOTNOTES
+        | Options::ENABLE_STRIKETHROUGH
+        | Options::ENABLE_TASKLISTS;
+
+    let mut output = String::new();
+    html::push_html(&mut output, Parser::new_ext(markdown, options));
+    return output;
+}
+
+// The `key: value` lines between the `---` fences at the start, and the rest of the page.
+// Lines can end with `\r\n` too.
+fn split_frontmatter(content: &str) -> (Option<&str>, &str) {
+    let Some(rest) = content.strip_prefix("---").and_then(strip_line_end) else {
+        return (None, content);
+    };
+
+    match rest.find("\n---") {
+        Some(end) => {
+            let frontmatter = &rest[..end];
+            let body = &rest[end + "\n---".len()..];
+            (
+                Some(frontmatter.strip_suffix('\r').unwrap_or(frontmatter)),
+                strip_line_end(body).unwrap_or(body),
+            )
+        }
+        None => (None, content),
+    }
+}
+
+// `text` after the line break it starts with
+fn strip_line_end(text: &str) -> Option<&str> {
+    return text
+        .strip_prefix("\r\n")
+        .or_else(|| text.strip_prefix('\n'));
+}
+
+// The line (counting the opening fence) and value of `key: value` in the frontmatter
+fn field<'a>(frontmatter: &'a str, key: &str) -> Option<(usize, &'a str)> {
+    return frontmatter.lines().enumerate().find_map(|(index, line)| {
+        let (name, value) = line.trim_end_matches('\r').split_once(':')?;
+        if name.trim() != key {
+            return None;
+        }
+        let value = value.trim().trim_matches(|c| c == '"' || c == '\'');
+        return Some((index + 2, value));
+    });
+}
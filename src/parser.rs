@@ -0,0 +1,1265 @@
+use std::cell::Cell;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::iter::Peekable;
+use std::ops::{ControlFlow, Range};
+use std::vec;
+
+use crate::ast::{Attribute, DirectiveValue, Element, Node};
+use crate::codes;
+use crate::declarations::declared_names;
+use crate::diagnostic::Diagnostic;
+use crate::expr::{self, TokenKind};
+use crate::flags::{Flags, CONFIG_FILE, ENV_VAR};
+use crate::html::is_void_element;
+use crate::ice;
+use crate::intern::{Atom, Interner};
+use crate::scanner::Damage;
+use crate::span::Span;
+use crate::token::{Literal, Token, TokenStream};
+use crate::token_type::TokenType;
+
+/// How deep elements, fragments, markup expressions and `{#if}` blocks can be nested by
+/// default, the parser goes one call deeper for every level
+pub const MAX_DEPTH: usize = 256;
+
+// What a nested list of nodes is waiting to be closed by
+#[derive(Hash)]
+enum Open {
+    Element(Atom), // </foo>
+    Fragment,      // </>
+    HTMLExpr,      // `)
+    IfBlock,       // {:else} or {/if}
+}
+
+/// A parse kept to parse the source again after an edit, see `Parser::reparse`
+#[derive(Default)]
+pub struct Parsed {
+    pub nodes: Vec<Node>,
+    pub diagnostics: Vec<Diagnostic>,
+    steps: Vec<Step>, // in source order, every step before the ones inside of it
+    flags: Flags,
+    token_count: usize,
+}
+
+// One turn of `nodes`: the nodes parsed at a token, like an element with its children
+struct Step {
+    path: Vec<usize>, // where its first node is, the index in every list of children down the tree
+    nodes: usize,     // how many, more than one for a `{#if}` block
+    tokens: Range<usize>, // the tokens it consumed
+    looked: usize,    // the last token it looked at, it can peek past the ones it consumed
+    span: Span,       // from its first token to the last one it looked at
+    state: State,
+    declared: Vec<String>, // the names it declares
+    transitions: usize,    // `transitions` after it
+    diagnostics: Range<usize>,
+}
+
+// What a step depends on besides its tokens, the same tokens in the same state are parsed
+// to the same nodes
+#[derive(Clone, Copy, PartialEq)]
+struct State {
+    open: u64, // hashes, comparing them has to be quicker than parsing again
+    declared: u64,
+    transitions: usize,
+}
+
+// The parse before the edit, while parsing again
+struct Reuse {
+    nodes: Vec<Node>, // the nodes that are reused are taken out of it
+    diagnostics: Vec<Diagnostic>,
+    steps: Peekable<vec::IntoIter<Step>>,
+    damage: Damage,
+    after: Moved, // how far the steps after the edit moved
+}
+
+// How far a reused step moved, zero before the edit
+#[derive(Clone, Copy, PartialEq, Default)]
+struct Moved {
+    tokens: isize,
+    bytes: isize,
+    lines: isize,
+}
+
+pub struct Parser<'a> {
+    tokens: &'a TokenStream,
+    source: &'a str, // of the tokens
+    current: usize,
+    open: Vec<Open>,
+    declared: Vec<String>, // names declared by the frontmatter and expressions so far
+    declared_hash: u64,
+    transitions: usize, // elements with a `transition:*` attribute but no name so far
+    flags: Flags,
+    diagnostics: Vec<Diagnostic>,
+    furthest: Cell<usize>,    // the last token looked at
+    steps: Option<Vec<Step>>, // only kept by `parse_incremental` and `reparse`
+    path: Vec<usize>,         // of the step being parsed
+    in_if: usize, // `{#if}` blocks being parsed, the nodes of a branch end up elsewhere in the tree
+    reuse: Option<Reuse>,
+    max_depth: usize,
+    interner: Interner, // element and attribute names, `div` is parsed once per tag
+}
+
+impl<'a> Parser<'a> {
+    pub fn new(tokens: &'a TokenStream) -> Self {
+        Self {
+            tokens,
+            source: tokens.source(),
+            current: 0,
+            open: Vec::new(),
+            declared: Vec::new(),
+            declared_hash: 0,
+            transitions: 0,
+            flags: Flags::new(),
+            diagnostics: Vec::new(),
+            furthest: Cell::new(0),
+            steps: None,
+            path: Vec::new(),
+            in_if: 0,
+            reuse: None,
+            max_depth: MAX_DEPTH,
+            interner: Interner::new(),
+        }
+    }
+
+    /// Sets the flags `{#if flag("...")}` blocks are resolved with, all flags are off by default
+    pub fn with_flags(mut self, flags: Flags) -> Self {
+        self.flags = flags;
+        return self;
+    }
+
+    /// Sets how deep nodes can be nested, `MAX_DEPTH` by default. The content of a node
+    /// nested deeper is skipped with an error, so it can't overflow the stack.
+    pub fn with_max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = max_depth;
+        return self;
+    }
+
+    pub fn parse(&mut self) -> Vec<Node> {
+        return self.nodes();
+    }
+
+    /// Parses like `parse`, keeping what's needed to parse again after an edit
+    pub fn parse_incremental(&mut self) -> Parsed {
+        self.steps = Some(Vec::new());
+        let nodes = self.nodes();
+        return Parsed {
+            nodes,
+            diagnostics: self.diagnostics.clone(),
+            steps: self.steps.take().unwrap_or_default(),
+            flags: self.flags.clone(),
+            token_count: self.tokens.len(),
+        };
+    }
+
+    /// Parses the tokens `Scanner::relex` gave after an edit. The subtrees of `previous`
+    /// whose tokens the edit didn't touch, and that come after the same open elements and
+    /// declarations as before, are moved into the new tree instead of being parsed again.
+    pub fn reparse(&mut self, previous: Parsed, damage: Damage) -> Parsed {
+        // `{#if}` blocks were resolved with other flags otherwise
+        if previous.flags == self.flags {
+            self.reuse = Some(Reuse {
+                nodes: previous.nodes,
+                diagnostics: previous.diagnostics,
+                steps: previous.steps.into_iter().peekable(),
+                damage,
+                after: Moved {
+                    tokens: self.tokens.len() as isize - previous.token_count as isize,
+                    bytes: damage.new.end as isize - damage.old.end as isize,
+                    lines: damage.lines,
+                },
+            });
+        }
+        let parsed = self.parse_incremental();
+        self.reuse = None;
+        return parsed;
+    }
+
+    /// Errors and warnings found while parsing, in source order
+    pub fn diagnostics(&self) -> &Vec<Diagnostic> {
+        return &self.diagnostics;
+    }
+
+    // Parses nodes until the end of the source or a token closing one of the open nodes
+    fn nodes(&mut self) -> Vec<Node> {
+        let mut nodes = Vec::new();
+
+        while !self.is_at_end() {
+            if self.reuse_step(&mut nodes) {
+                continue;
+            }
+
+            // text merged into the Text before it is part of the step that Text came from
+            if self.merges_text(&nodes) {
+                let flow = self.node(&mut nodes);
+                self.extend_step(nodes.len());
+                if flow.is_break() {
+                    break;
+                }
+                continue;
+            }
+
+            let step = self.start_step(nodes.len());
+            let flow = self.node(&mut nodes);
+            self.end_step(step, nodes.len());
+            if flow.is_break() {
+                break;
+            }
+        }
+
+        return nodes;
+    }
+
+    // Parses the node at the current token into `nodes`, breaks on a token closing one of
+    // the open nodes
+    fn node(&mut self, nodes: &mut Vec<Node>) -> ControlFlow<()> {
+        let token = self.peek();
+
+        match token.kind {
+            TokenType::CodeBlock => {
+                self.declare(declared_names(&token.literal_string(self.source)));
+                nodes.push(Node::CodeBlock(token.literal_string(self.source)))
+            }
+            TokenType::TextToken | TokenType::Entity => {
+                // `a &amp; b` is three tokens but one text, with the entities as written
+                let text = match token.kind {
+                    TokenType::Entity => token.lexeme(self.source).to_string(),
+                    _ => token.literal_string(self.source),
+                };
+                match nodes.last_mut() {
+                    Some(Node::Text(previous)) => {
+                        previous.push_str(self.tokens.trivia(self.current));
+                        previous.push_str(&text);
+                    }
+                    _ => nodes.push(Node::Text(text)),
+                }
+            }
+            TokenType::Expression if block(token, self.source).starts_with("@html") => {
+                let code = block(token, self.source)["@html".len()..]
+                    .trim()
+                    .to_string();
+                self.push(raw_html_warning(token, &code));
+                nodes.push(Node::RawHTML(code))
+            }
+            TokenType::Expression if block(token, self.source).starts_with("#if") => {
+                let taken = self.if_block();
+                nodes.extend(taken);
+                return ControlFlow::Continue(());
+            }
+            TokenType::Expression if matches!(block(token, self.source), ":else" | "/if") => {
+                if self.closes_open_node(token) {
+                    return ControlFlow::Break(()); // let the `{#if}` consume it
+                }
+
+                let message = format!(
+                    "Unexpected `{}`, no `{{#if}}` is open",
+                    token.lexeme(self.source)
+                );
+                let diagnostic = Diagnostic::error(token.line(), &message)
+                    .with_code(codes::UNEXPECTED_BLOCK)
+                    .with_span(token.span);
+                self.push(diagnostic);
+            }
+            TokenType::Expression => {
+                // e.g. the parameters of `{ items.map(item => (`...`)) }`
+                self.declare(declared_names(&token.literal_string(self.source)));
+                nodes.push(Node::Expression(token.literal_string(self.source)))
+            }
+            TokenType::CData => nodes.push(Node::CData(token.literal_string(self.source))),
+            // `>` and `/>` outside of a tag are just text
+            TokenType::OpeningTagEnd | TokenType::SelfClosingTagEnd => {
+                nodes.push(Node::Text(token.lexeme(self.source).to_string()))
+            }
+            TokenType::OpeningTagStart | TokenType::DynamicTagStart => {
+                nodes.push(self.element());
+                return ControlFlow::Continue(());
+            }
+            TokenType::FragmentOpen => {
+                nodes.push(self.fragment());
+                return ControlFlow::Continue(());
+            }
+            TokenType::HTMLExprStart => {
+                nodes.push(self.html_expr());
+                return ControlFlow::Continue(());
+            }
+            TokenType::ClosingTag | TokenType::FragmentClose | TokenType::HTMLExprEnd => {
+                if self.closes_open_node(token) {
+                    return ControlFlow::Break(()); // let the node it belongs to consume it
+                }
+
+                if !self.closes_void_element(token, nodes) {
+                    if matches!(token.kind, TokenType::ClosingTag)
+                        && matches!(self.open.last(), Some(Open::Element(_)))
+                    {
+                        return ControlFlow::Break(()); // mismatched closing tag, reported by the element
+                    }
+
+                    let message = format!(
+                        "Unexpected `{}`, nothing to close",
+                        token.lexeme(self.source)
+                    );
+                    let diagnostic = Diagnostic::error(token.line(), &message)
+                        .with_code(codes::UNEXPECTED_CLOSING_TAG)
+                        .with_span(token.span);
+                    self.push(diagnostic);
+                }
+            }
+            TokenType::SpreadAttribute
+            | TokenType::ShorthandAttribute
+            | TokenType::BooleanAttribute
+            | TokenType::ClientDirective => {
+                let message = format!("Attribute `{}` outside of a tag", token.lexeme(self.source));
+                let diagnostic = Diagnostic::error(token.line(), &message)
+                    .with_code(codes::ATTRIBUTE_OUTSIDE_TAG)
+                    .with_span(token.span);
+                self.push(diagnostic);
+            }
+            TokenType::EOF => return ControlFlow::Break(()),
+        }
+
+        self.advance();
+        return ControlFlow::Continue(());
+    }
+
+    // Elements are parsed in pieces, the start and closing tags in functions of their own,
+    // so every level of nesting takes as little of the stack as possible
+    fn element(&mut self) -> Node {
+        let (mut element, has_children) = self.start_tag();
+        if !has_children {
+            return Node::Element(element);
+        }
+
+        // What the closing tag has to say: `div` or `{tagName}`
+        let tag = match element.dynamic {
+            true => Atom::from(format!("{{{}}}", element.name)),
+            false => element.name.clone(),
+        };
+        let foreign = is_foreign(&self.open);
+        element.children =
+            self.children(Open::Element(tag.clone()), element.start_tag, element.line);
+        self.end_tag(&mut element, &tag, foreign);
+        return Node::Element(element);
+    }
+
+    // `<foo ...>` as an element without children, and whether children come next: not for
+    // void and self-closing elements, or a start tag that's never closed
+    fn start_tag(&mut self) -> (Element, bool) {
+        let start = self.advance();
+        let name = self.name(start);
+        let line = start.line();
+        let dynamic = matches!(start.kind, TokenType::DynamicTagStart);
+        // What the closing tag has to say: `div` or `{tagName}`
+        let tag = if dynamic {
+            Atom::from(format!("{{{}}}", name))
+        } else {
+            name.clone()
+        };
+        let mut start_tag = start.span;
+
+        let mut attributes = Vec::new();
+        let mut self_closing = false;
+
+        loop {
+            let token = self.peek();
+
+            match token.kind {
+                TokenType::OpeningTagEnd => {
+                    start_tag = start_tag.to(self.advance().span);
+                    break;
+                }
+                TokenType::SelfClosingTagEnd => {
+                    start_tag = start_tag.to(self.advance().span);
+                    self_closing = true;
+                    break;
+                }
+                TokenType::TextToken if attribute_name(token, self.source) == "data:set" => {
+                    let value = self.peek_next();
+                    if token.literal_string(self.source).trim_end().ends_with('=')
+                        && matches!(value.kind, TokenType::Expression)
+                    {
+                        start_tag = start_tag.to(self.advance().span);
+                        attributes.push(Attribute::DataSet(value.literal_string(self.source)));
+                    } else {
+                        let diagnostic =
+                            Diagnostic::error(token.line(), "`data:set` expects an object")
+                                .with_code(codes::INVALID_DATA_SET)
+                                .with_span(token.span)
+                                .with_help("use an expression, like `data:set={user}`");
+                        self.push(diagnostic);
+                        attributes.push(Attribute::Text(token.literal_string(self.source)));
+                    }
+                }
+                TokenType::TextToken if attribute_name(token, self.source) == "set:html" => {
+                    let value = self.peek_next();
+                    if token.literal_string(self.source).trim_end().ends_with('=')
+                        && matches!(value.kind, TokenType::Expression)
+                    {
+                        start_tag = start_tag.to(self.advance().span);
+                        self.push(raw_html_warning(token, &value.literal_string(self.source)));
+                        attributes.push(Attribute::SetHTML(value.literal_string(self.source)));
+                    } else {
+                        let diagnostic =
+                            Diagnostic::error(token.line(), "`set:html` expects an expression")
+                                .with_code(codes::INVALID_SET_HTML)
+                                .with_span(token.span)
+                                .with_help("write static HTML as the element's children instead");
+                        self.push(diagnostic);
+                        attributes.push(Attribute::Text(token.literal_string(self.source)));
+                    }
+                }
+                TokenType::TextToken if attribute_name(token, self.source) == "transition:name" => {
+                    match attribute_value(token, self.source) {
+                        Some(value) => attributes.push(Attribute::TransitionName(value)),
+                        None => {
+                            // the name has to be the same on every page for the elements to match
+                            let diagnostic = Diagnostic::error(
+                                token.line(),
+                                "`transition:name` has to be a string",
+                            )
+                            .with_code(codes::INVALID_TRANSITION_NAME)
+                            .with_span(token.span)
+                            .with_help("use a fixed name, like `transition:name=\"hero\"`");
+                            self.push(diagnostic);
+                            attributes.push(Attribute::Text(token.literal_string(self.source)));
+                        }
+                    }
+                }
+                TokenType::TextToken
+                    if attribute_name(token, self.source) == "transition:persist" =>
+                {
+                    attributes.push(Attribute::TransitionPersist(attribute_value(
+                        token,
+                        self.source,
+                    )));
+                }
+                TokenType::BooleanAttribute
+                    if token.literal_string(self.source) == "transition:persist" =>
+                {
+                    attributes.push(Attribute::TransitionPersist(None));
+                }
+                TokenType::TextToken | TokenType::BooleanAttribute
+                    if attribute_name(token, self.source).starts_with("transition:") =>
+                {
+                    let message = format!(
+                        "Unknown transition directive `{}`",
+                        attribute_name(token, self.source)
+                    );
+                    let diagnostic = Diagnostic::warning(token.line(), &message)
+                        .with_code(codes::UNKNOWN_TRANSITION_DIRECTIVE)
+                        .with_span(token.span)
+                        .with_help("use `transition:name` or `transition:persist`");
+                    self.push(diagnostic);
+                    match token.kind {
+                        TokenType::BooleanAttribute => {
+                            attributes.push(Attribute::Boolean(self.name(token)))
+                        }
+                        _ => attributes.push(Attribute::Text(token.literal_string(self.source))),
+                    }
+                }
+                TokenType::ClientDirective => {
+                    let Some(Literal::Directive { name, value, .. }) = token.literal(self.source)
+                    else {
+                        unreachable!("a ClientDirective without its directive");
+                    };
+                    if !CLIENT_DIRECTIVES.contains(&name) {
+                        let message = format!("Unknown client directive `client:{}`", name);
+                        let diagnostic = Diagnostic::warning(token.line(), &message)
+                            .with_code(codes::UNKNOWN_CLIENT_DIRECTIVE)
+                            .with_span(token.span)
+                            .with_help("use `client:load`, `client:idle`, `client:visible`, `client:media` or `client:only`");
+                        self.push(diagnostic);
+                    }
+
+                    // `client:visible={{ rootMargin: "200px" }}`
+                    let next = self.peek_next();
+                    let value = match value {
+                        Some(text) => Some(DirectiveValue::Text(text.into_owned())),
+                        None if token.lexeme(self.source).trim_end().ends_with('=')
+                            && matches!(next.kind, TokenType::Expression) =>
+                        {
+                            start_tag = start_tag.to(self.advance().span);
+                            Some(DirectiveValue::Expression(next.literal_string(self.source)))
+                        }
+                        None => None,
+                    };
+                    attributes.push(Attribute::Client(name.to_string(), value));
+                }
+                TokenType::TextToken => {
+                    attributes.push(Attribute::Text(token.literal_string(self.source)))
+                }
+                TokenType::Expression => {
+                    attributes.push(Attribute::Expression(token.literal_string(self.source)))
+                }
+                TokenType::SpreadAttribute => {
+                    attributes.push(Attribute::Spread(token.literal_string(self.source)))
+                }
+                TokenType::BooleanAttribute => {
+                    attributes.push(Attribute::Boolean(self.name(token)))
+                }
+                TokenType::ShorthandAttribute => {
+                    let name = token.literal_string(self.source);
+                    if !self.declared.contains(&name) {
+                        let message = format!(
+                            "`{{{}}}` is short for `{}={{{}}}`, but `{}` isn't declared",
+                            name, name, name, name
+                        );
+                        let diagnostic = Diagnostic::warning(token.line(), &message)
+                            .with_code(codes::UNDECLARED_SHORTHAND)
+                            .with_span(token.span)
+                            .with_help(&format!("declare `{}` in the frontmatter", name));
+                        self.push(diagnostic);
+                    }
+                    attributes.push(Attribute::Shorthand(name));
+                }
+                _ => {
+                    let message = format!("Unterminated tag `<{}`, expected `>`", tag);
+                    let diagnostic = Diagnostic::error(line, &message)
+                        .with_code(codes::UNTERMINATED_TAG)
+                        .with_span(start_tag);
+                    self.push(diagnostic);
+                    let transition_id = self.transition_id(&tag, &attributes);
+                    let element = Element {
+                        name,
+                        dynamic,
+                        attributes,
+                        children: Vec::new(),
+                        self_closing,
+                        line,
+                        start_tag,
+                        span: start_tag,
+                        transition_id,
+                    };
+                    return (element, false);
+                }
+            }
+
+            start_tag = start_tag.to(self.advance().span);
+        }
+
+        let transition_id = self.transition_id(&tag, &attributes);
+
+        // Void elements like `<br>` never have children, with or without `/>`, but SVG and
+        // MathML don't have void elements
+        let foreign = is_foreign(&self.open);
+        let has_children = !self_closing && (dynamic || foreign || !is_void_element(&name));
+        let element = Element {
+            name,
+            dynamic,
+            attributes,
+            children: Vec::new(),
+            self_closing,
+            line,
+            start_tag,
+            span: start_tag,
+            transition_id,
+        };
+        return (element, has_children);
+    }
+
+    // The closing tag of `element`, after its children
+    fn end_tag(&mut self, element: &mut Element, tag: &str, foreign: bool) {
+        let (line, start_tag) = (element.line, element.start_tag);
+        let token = self.peek();
+        let is_closing_tag = matches!(token.kind, TokenType::ClosingTag);
+
+        if is_closing_tag && tag_matches(&token.literal_string(self.source), tag, foreign) {
+            self.advance();
+        } else if is_closing_tag && !self.closes_open_node(token) {
+            // `<span> ... </div>` without an open `<div>`, most likely a typo
+            let message = format!(
+                "Mismatched closing tag `{}`, expected `</{}>`",
+                token.lexeme(self.source),
+                tag
+            );
+            let diagnostic = Diagnostic::error(token.line(), &message)
+                .with_code(codes::MISMATCHED_CLOSING_TAG)
+                .with_span(token.span)
+                .with_label(line, start_tag, &format!("`<{}>` is opened here", tag))
+                .with_help(&format!("did you mean `</{}>`?", tag));
+            self.push(diagnostic);
+            self.advance();
+        } else {
+            let message = format!("`<{}>` on line {} is never closed", tag, line);
+            let mut diagnostic = Diagnostic::warning(token.line(), &message)
+                .with_code(codes::UNCLOSED_ELEMENT)
+                .with_span(start_tag);
+            if is_closing_tag {
+                diagnostic = diagnostic
+                    .with_label(
+                        token.line(),
+                        token.span,
+                        &format!("`{}` closes its parent", token.lexeme(self.source)),
+                    )
+                    .with_help(&format!(
+                        "add `</{}>` before `{}`",
+                        tag,
+                        token.lexeme(self.source)
+                    ));
+            } else {
+                diagnostic = diagnostic.with_help(&format!("add `</{}>`", tag));
+            }
+            self.push(diagnostic);
+        }
+
+        // up to the closing tag, or the last child if there's none
+        element.span = start_tag.to(self.previous().span);
+    }
+
+    fn fragment(&mut self) -> Node {
+        let start = self.advance();
+        let line = start.line();
+
+        let children = self.children(Open::Fragment, start.span, line);
+
+        let token = self.peek();
+        if matches!(token.kind, TokenType::FragmentClose) {
+            self.advance();
+        } else {
+            let message = format!("Fragment `<>` on line {} is never closed", line);
+            let diagnostic = Diagnostic::warning(token.line(), &message)
+                .with_code(codes::UNCLOSED_FRAGMENT)
+                .with_help("add `</>`");
+            self.push(diagnostic);
+        }
+
+        return Node::Fragment(children);
+    }
+
+    fn html_expr(&mut self) -> Node {
+        let start = self.advance();
+        let line = start.line();
+
+        let children = self.children(Open::HTMLExpr, start.span, line);
+
+        let token = self.peek();
+        if matches!(token.kind, TokenType::HTMLExprEnd) {
+            self.advance();
+        } else {
+            let message = format!(
+                "Unterminated markup expression from line {}, expected `` `) ``",
+                line
+            );
+            let diagnostic = Diagnostic::error(token.line(), &message)
+                .with_code(codes::UNTERMINATED_MARKUP_EXPRESSION);
+            self.push(diagnostic);
+        }
+
+        return Node::HTMLExpr(children);
+    }
+
+    // `transition:name="foo"` identifies the element as `foo`. Without a name, elements
+    // with `transition:persist` are numbered in source order, like `div-0`, so the same
+    // template gives the same identifiers on every page.
+    fn transition_id(&mut self, tag: &str, attributes: &[Attribute]) -> Option<String> {
+        let mut persist = None;
+        for attribute in attributes {
+            match attribute {
+                Attribute::TransitionName(name) => return Some(name.clone()),
+                Attribute::TransitionPersist(name) => persist = Some(name.clone()),
+                _ => {}
+            }
+        }
+
+        match persist? {
+            Some(name) => return Some(name),
+            None => {
+                let id = format!("{}-{}", tag, self.transitions);
+                self.transitions += 1;
+                return Some(id);
+            }
+        }
+    }
+
+    // `{#if flag("beta")} ... {:else} ... {/if}` is resolved while parsing: only the
+    // nodes of the branch that is taken end up in the tree, the other one is dropped.
+    fn if_block(&mut self) -> Vec<Node> {
+        let start = self.advance();
+        let condition = block(start, self.source)["#if".len()..].trim();
+        let enabled = self.flag_condition(start, condition);
+
+        if self.is_too_deep(start.span, start.line()) {
+            self.skip_nested();
+        }
+        self.open.push(Open::IfBlock);
+        self.in_if += 1;
+        let then = self.nodes();
+        let mut otherwise = Vec::new();
+        if block(self.peek(), self.source) == ":else" {
+            self.advance();
+            otherwise = self.nodes();
+        }
+        self.in_if -= 1;
+        self.open.pop();
+
+        let token = self.peek();
+        if block(token, self.source) == "/if" {
+            self.advance();
+        } else {
+            let message = format!(
+                "`{}` on line {} is never closed, expected `{{/if}}`",
+                start.lexeme(self.source).trim(),
+                start.line()
+            );
+            let diagnostic = Diagnostic::error(token.line(), &message)
+                .with_code(codes::UNCLOSED_IF)
+                .with_span(start.span);
+            self.push(diagnostic);
+        }
+
+        if enabled {
+            return then;
+        }
+        return otherwise;
+    }
+
+    // Parses the children of a node waiting for `open`, the one at `span`
+    fn children(&mut self, open: Open, span: Span, line: usize) -> Vec<Node> {
+        if self.is_too_deep(span, line) {
+            self.skip_nested();
+            return Vec::new();
+        }
+
+        self.open.push(open);
+        let children = self.nodes();
+        self.open.pop();
+        return children;
+    }
+
+    // Whether the node at `span` is nested too deep to parse its content, reported once
+    fn is_too_deep(&mut self, span: Span, line: usize) -> bool {
+        if self.open.len() < self.max_depth {
+            return false;
+        }
+
+        let message = format!("Nested more than {} levels deep", self.max_depth);
+        let diagnostic = Diagnostic::error(line, &message)
+            .with_code(codes::NESTING_TOO_DEEP)
+            .with_span(span)
+            .with_help("its content is skipped, split the markup up into components");
+        self.push(diagnostic);
+        return true;
+    }
+
+    // Skips the tokens inside the node just opened, up to the one closing it. They're
+    // counted instead of parsed, without going any deeper.
+    fn skip_nested(&mut self) {
+        let mut depth = 1;
+        let mut opens_element = false;
+
+        while !self.is_at_end() {
+            let token = self.peek();
+            match token.kind {
+                TokenType::OpeningTagStart => {
+                    opens_element = !is_void_element(&token.literal_string(self.source))
+                }
+                TokenType::DynamicTagStart => opens_element = true,
+                TokenType::OpeningTagEnd if opens_element => depth += 1,
+                TokenType::FragmentOpen | TokenType::HTMLExprStart => depth += 1,
+                TokenType::Expression if block(token, self.source).starts_with("#if") => depth += 1,
+                TokenType::ClosingTag | TokenType::FragmentClose | TokenType::HTMLExprEnd => {
+                    depth -= 1
+                }
+                TokenType::Expression if block(token, self.source) == "/if" => depth -= 1,
+                _ => {}
+            }
+            if depth == 0 {
+                return; // the closing token is the node's
+            }
+            self.advance();
+        }
+    }
+
+    // Evaluates `flag("name")` or `!flag("name")`, the only conditions known at build time
+    fn flag_condition(&mut self, start: &Token, condition: &str) -> bool {
+        let tokens = expr::Lexer::new(condition).tokenize();
+        let (negated, call) = match tokens.split_first() {
+            Some((not, call)) if not.kind == TokenKind::Bang => (true, call),
+            _ => (false, &tokens[..]),
+        };
+
+        let name = match call {
+            [function, open, name, close]
+                if function.text == "flag"
+                    && open.kind == TokenKind::LeftParen
+                    && name.kind == TokenKind::String
+                    && close.kind == TokenKind::RightParen =>
+            {
+                let quote = name.text.chars().next().filter(|c| matches!(c, '"' | '\''));
+                quote.and_then(|quote| name.text[1..].strip_suffix(quote))
+            }
+            _ => None,
+        };
+
+        let Some(name) = name else {
+            let message = format!("Can't evaluate `{}` at build time", condition);
+            let diagnostic = Diagnostic::error(start.line(), &message)
+                .with_code(codes::UNKNOWN_CONDITION)
+                .with_span(start.span)
+                .with_help("`{#if}` only takes `flag(\"name\")` or `!flag(\"name\")`, use `{ condition && (`...`) }` otherwise");
+            self.push(diagnostic);
+            return false;
+        };
+
+        let enabled = match self.flags.get(name) {
+            Some(enabled) => enabled,
+            None => {
+                let message = format!("Flag `{}` isn't defined, treating it as off", name);
+                let diagnostic = Diagnostic::warning(start.line(), &message)
+                    .with_code(codes::UNDEFINED_FLAG)
+                    .with_span(start.span)
+                    .with_help(&format!(
+                        "add `{} = true` to the `[flags]` of {} or `{}` to {}",
+                        name, CONFIG_FILE, name, ENV_VAR
+                    ));
+                self.push(diagnostic);
+                false
+            }
+        };
+
+        return enabled != negated;
+    }
+
+    // Starts recording a step whose first node goes at `index` of its list, with what
+    // `end_step` needs to finish it
+    fn start_step(&mut self, index: usize) -> Option<(usize, usize, usize)> {
+        if self.steps.is_none() || self.in_if > 0 {
+            return None;
+        }
+
+        self.path.push(index);
+        let step = Step {
+            path: self.path.clone(),
+            nodes: 0,
+            tokens: self.current..self.current,
+            looked: self.current,
+            span: Span::new(0, 0),
+            state: self.state(),
+            declared: Vec::new(),
+            transitions: 0,
+            diagnostics: self.diagnostics.len()..self.diagnostics.len(),
+        };
+        let steps = self.steps.get_or_insert_with(Vec::new);
+        steps.push(step);
+        let furthest = self.furthest.replace(self.current);
+        return Some((steps.len() - 1, furthest, self.declared.len()));
+    }
+
+    // `length` is the length of the list the step's nodes were added to
+    fn end_step(&mut self, started: Option<(usize, usize, usize)>, length: usize) {
+        let Some((index, furthest, declared)) = started else {
+            return;
+        };
+        self.path.pop();
+        let looked = self.furthest.get();
+        self.furthest.set(looked.max(furthest));
+
+        let Some(steps) = self.steps.as_mut() else {
+            return;
+        };
+        if self.current == steps[index].tokens.start {
+            // nothing was parsed, like for a closing tag that closes the parent
+            steps.truncate(index);
+            return;
+        }
+
+        let step = &mut steps[index];
+
+        let first = step.path.last().copied().unwrap_or_default();
+        step.nodes = length - first;
+        step.tokens.end = self.current;
+        step.looked = looked;
+        step.span = self.tokens[step.tokens.start]
+            .span
+            .to(self.tokens[looked.min(self.tokens.len() - 1)].span);
+        step.declared = self.declared[declared..].to_vec();
+        step.transitions = self.transitions;
+        step.diagnostics.end = self.diagnostics.len();
+    }
+
+    // Adds the text token just parsed to the step whose nodes end with the Text it went into,
+    // `length` is the length of the list. Steps in between that added no nodes, like a stray
+    // `</p>`, end up inside of it, unless one is a closing tag: whether `</img>` closes a
+    // void element depends on the nodes before the step, so it's parsed again instead.
+    fn extend_step(&mut self, length: usize) {
+        let (Some(steps), 0) = (self.steps.as_mut(), self.in_if) else {
+            return;
+        };
+        let depth = self.path.len();
+        let Some(index) = steps.iter().rposition(|step| {
+            step.path.len() == depth + 1
+                && step.path[..depth] == self.path[..]
+                && step.path[depth] + step.nodes == length
+                && step.nodes > 0
+        }) else {
+            return;
+        };
+        let step = &mut steps[index];
+        let skipped = &self.tokens[step.tokens.end..self.current];
+        if skipped
+            .iter()
+            .any(|token| matches!(token.kind, TokenType::ClosingTag))
+        {
+            steps.truncate(index);
+            return;
+        }
+
+        step.tokens.end = self.current;
+        step.looked = step.looked.max(self.furthest.get());
+        step.span = self.tokens[step.tokens.start]
+            .span
+            .to(self.tokens[step.looked.min(self.tokens.len() - 1)].span);
+        step.diagnostics.end = self.diagnostics.len();
+    }
+
+    // Whether the current token is text going into the Text at the end of `nodes`
+    fn merges_text(&self, nodes: &[Node]) -> bool {
+        return matches!(self.peek().kind, TokenType::TextToken | TokenType::Entity)
+            && matches!(nodes.last(), Some(Node::Text(_)));
+    }
+
+    // Moves the nodes of the previous parse at the current token into `nodes`, if the edit
+    // didn't change them
+    fn reuse_step(&mut self, nodes: &mut Vec<Node>) -> bool {
+        // the closing tag of a void element depends on the nodes before it, and text
+        // depends on whether there's text before it to go into
+        if self.reuse.is_none()
+            || self.in_if > 0
+            || matches!(self.peek().kind, TokenType::ClosingTag)
+            || self.merges_text(nodes)
+        {
+            return false;
+        }
+        let state = self.state();
+        let Some(mut reuse) = self.reuse.take() else {
+            return false;
+        };
+        let reused = self.take_step(&mut reuse, state, nodes);
+        self.reuse = Some(reuse);
+        return reused;
+    }
+
+    fn take_step(&mut self, reuse: &mut Reuse, state: State, nodes: &mut Vec<Node>) -> bool {
+        let moved = loop {
+            let Some(step) = reuse.steps.peek() else {
+                return false;
+            };
+            match step.moved(&reuse.damage, reuse.after) {
+                Some(moved) if moved.token(step.tokens.start) > self.current => return false,
+                Some(moved) if moved.token(step.tokens.start) == self.current => break moved,
+                _ => reuse.steps.next(),
+            };
+        };
+
+        // the line numbers in the messages would be wrong
+        let Some(step) = reuse.steps.next_if(|step| {
+            step.state == state && (moved.lines == 0 || step.diagnostics.is_empty())
+        }) else {
+            return false;
+        };
+        let mut inner = Vec::new();
+        while let Some(next) = reuse
+            .steps
+            .next_if(|next| next.tokens.start < step.tokens.end)
+        {
+            inner.push(next);
+        }
+
+        let (Some(&first), Some(list)) = (
+            step.path.last(),
+            children(&mut reuse.nodes, &step.path[..step.path.len() - 1]),
+        ) else {
+            return false;
+        };
+        let Some(taken) = list.get_mut(first..first + step.nodes) else {
+            return false;
+        };
+
+        let index = nodes.len();
+        for node in taken {
+            let mut node = std::mem::replace(node, Node::Text(String::new()));
+            if moved != Moved::default() {
+                shift_node(&mut node, moved);
+            }
+            nodes.push(node);
+        }
+
+        let diagnostics = self.diagnostics.len();
+        for diagnostic in &reuse.diagnostics[step.diagnostics.clone()] {
+            let mut diagnostic = diagnostic.clone();
+            diagnostic.shift(moved.bytes, moved.lines);
+            self.diagnostics.push(diagnostic);
+        }
+
+        self.current = moved.token(step.tokens.end);
+        self.furthest
+            .set(self.furthest.get().max(moved.token(step.looked)));
+        self.transitions = step.transitions;
+        self.declare(step.declared.clone());
+
+        // kept for the next edit, with the steps inside of it
+        let mut path = self.path.clone();
+        path.push(index);
+        let depth = step.path.len();
+        let start = step.diagnostics.start;
+        if let Some(steps) = self.steps.as_mut() {
+            for step in std::iter::once(step).chain(inner) {
+                let mut moved_step = step;
+                moved_step.path.splice(..depth, path.iter().copied());
+                moved_step.tokens =
+                    moved.token(moved_step.tokens.start)..moved.token(moved_step.tokens.end);
+                moved_step.looked = moved.token(moved_step.looked);
+                moved_step.span = moved.span(moved_step.span);
+                moved_step.diagnostics = moved_step.diagnostics.start - start + diagnostics
+                    ..moved_step.diagnostics.end - start + diagnostics;
+                steps.push(moved_step);
+            }
+        }
+        return true;
+    }
+
+    fn state(&self) -> State {
+        let mut hasher = DefaultHasher::new();
+        self.open.hash(&mut hasher);
+        return State {
+            open: hasher.finish(),
+            declared: self.declared_hash,
+            transitions: self.transitions,
+        };
+    }
+
+    fn declare(&mut self, names: Vec<String>) {
+        for name in names {
+            let mut hasher = DefaultHasher::new();
+            (self.declared_hash, &name).hash(&mut hasher);
+            self.declared_hash = hasher.finish();
+            self.declared.push(name);
+        }
+    }
+
+    fn closes_open_node(&self, token: &Token) -> bool {
+        return self
+            .open
+            .iter()
+            .enumerate()
+            .any(|(i, open)| match (open, &token.kind) {
+                (Open::Element(name), TokenType::ClosingTag) => tag_matches(
+                    &token.literal_string(self.source),
+                    name,
+                    is_foreign(&self.open[..i]),
+                ),
+                (Open::Fragment, TokenType::FragmentClose) => true,
+                (Open::HTMLExpr, TokenType::HTMLExprEnd) => true,
+                (Open::IfBlock, TokenType::Expression) => {
+                    matches!(block(token, self.source), ":else" | "/if")
+                }
+                _ => false,
+            });
+    }
+
+    // Handles the closing tag of a void element among `siblings`, the nodes parsed before it
+    fn closes_void_element(&mut self, token: &Token, siblings: &[Node]) -> bool {
+        let name = token.literal_string(self.source);
+        if !matches!(token.kind, TokenType::ClosingTag)
+            || !is_void_element(&name)
+            || is_foreign(&self.open)
+        {
+            return false;
+        }
+
+        let opening = siblings.iter().rposition(|node| match node {
+            Node::Element(element) => element.name.eq_ignore_ascii_case(&name),
+            _ => false,
+        });
+
+        match opening {
+            Some(index) => {
+                // `<img></img>` is redundant but harmless, `<img>foo</img>` is not
+                if index + 1 < siblings.len() {
+                    let message = format!(
+                        "`<{}>` is a void element and can't have children, remove `</{}>` and its content",
+                        name, name
+                    );
+                    let diagnostic = Diagnostic::error(token.line(), &message)
+                        .with_code(codes::VOID_ELEMENT_CHILDREN)
+                        .with_span(token.span);
+                    self.push(diagnostic);
+                }
+                return true;
+            }
+            None => return false,
+        }
+    }
+
+    fn is_at_end(&self) -> bool {
+        return matches!(self.peek().kind, TokenType::EOF);
+    }
+
+    fn peek(&self) -> &'a Token {
+        // `scan_tokens` always ends with an EOF token
+        return self.look(self.current);
+    }
+
+    // The tag or attribute name of `token`, one `Atom` for every time it's used
+    fn name(&mut self, token: &Token) -> Atom {
+        let name = token.literal_str(self.source).unwrap_or_default();
+        return self.interner.intern(&name);
+    }
+
+    fn previous(&self) -> &'a Token {
+        return &self.tokens[self.current.saturating_sub(1)];
+    }
+
+    fn peek_next(&self) -> &'a Token {
+        return self.look(self.current + 1);
+    }
+
+    fn look(&self, index: usize) -> &'a Token {
+        let index = index.min(self.tokens.len() - 1);
+        self.furthest.set(self.furthest.get().max(index));
+        return &self.tokens[index];
+    }
+
+    fn advance(&mut self) -> &'a Token {
+        let token = self.peek();
+        ice::set_span(token.span);
+        if !self.is_at_end() {
+            self.current += 1;
+        }
+        return token;
+    }
+
+    fn push(&mut self, diagnostic: Diagnostic) {
+        self.diagnostics.push(diagnostic);
+    }
+}
+
+impl Step {
+    // How far the edit moved the step, `None` if it changed the tokens the step looked at
+    fn moved(&self, damage: &Damage, after: Moved) -> Option<Moved> {
+        if self.span.end <= damage.old.start {
+            return Some(Moved::default());
+        }
+        if self.span.start >= damage.old.end {
+            return Some(after);
+        }
+        return None;
+    }
+}
+
+impl Moved {
+    fn token(&self, index: usize) -> usize {
+        return (index as isize + self.tokens) as usize;
+    }
+
+    fn span(&self, span: Span) -> Span {
+        let start = (span.start as isize + self.bytes) as usize;
+        let end = (span.end as isize + self.bytes) as usize;
+        return Span::new(start, end);
+    }
+
+    fn line(&self, line: usize) -> usize {
+        return (line as isize + self.lines) as usize;
+    }
+}
+
+// The list of children at `path`, the index of a node in every list down the tree
+fn children<'n>(nodes: &'n mut Vec<Node>, path: &[usize]) -> Option<&'n mut Vec<Node>> {
+    let Some((&index, rest)) = path.split_first() else {
+        return Some(nodes);
+    };
+    match nodes.get_mut(index)? {
+        Node::Element(element) => return children(&mut element.children, rest),
+        Node::Fragment(children_of) | Node::HTMLExpr(children_of) => {
+            return children(children_of, rest)
+        }
+        _ => return None,
+    }
+}
+
+fn shift_node(node: &mut Node, moved: Moved) {
+    let children = match node {
+        Node::Element(element) => {
+            element.line = moved.line(element.line);
+            element.start_tag = moved.span(element.start_tag);
+            element.span = moved.span(element.span);
+            &mut element.children
+        }
+        Node::Fragment(children) | Node::HTMLExpr(children) => children,
+        _ => return,
+    };
+    for child in children {
+        shift_node(child, moved);
+    }
+}
+
+// When a component with a `client:*` directive is hydrated
+const CLIENT_DIRECTIVES: [&str; 5] = ["load", "idle", "visible", "media", "only"];
+
+// Whether the content of the innermost open element is SVG or MathML, `<foreignObject>`
+// has HTML again
+fn is_foreign(open: &[Open]) -> bool {
+    for open in open.iter().rev() {
+        let Open::Element(name) = open else {
+            continue;
+        };
+        if name == "foreignObject" {
+            return false;
+        }
+        if name.eq_ignore_ascii_case("svg") || name.eq_ignore_ascii_case("math") {
+            return true;
+        }
+    }
+    return false;
+}
+
+// HTML tag names are case-insensitive, SVG and MathML ones aren't: `</lineargradient>`
+// doesn't close `<linearGradient>`
+fn tag_matches(closing: &str, name: &str, foreign: bool) -> bool {
+    return match foreign {
+        true => closing == name,
+        false => closing.eq_ignore_ascii_case(name),
+    };
+}
+
+// Expressions are HTML-escaped when rendered, so opting out is worth a second look
+fn raw_html_warning(token: &Token, code: &str) -> Diagnostic {
+    let message = format!("`{}` is rendered as raw HTML, without escaping", code);
+    return Diagnostic::warning(token.line(), &message)
+        .with_code(codes::RAW_HTML)
+        .with_span(token.span)
+        .with_help("make sure it can't contain user input, or use `{ ... }` to escape it");
+}
+
+// The code of an Expression token, where blocks like `{#if ...}` and `{/if}` are recognized
+fn block<'a>(token: &Token, source: &'a str) -> &'a str {
+    return match token.literal(source) {
+        Some(Literal::Expr(code) | Literal::CodeBlock(code)) => code.trim(),
+        _ => "",
+    };
+}
+
+// `bar` for the `foo="bar"`, `foo='bar'` and `foo=bar` attribute tokens, `None` without a value
+fn attribute_value(token: &Token, source: &str) -> Option<String> {
+    let text = token.literal_str(source).unwrap_or_default();
+    let (_, value) = text.split_once('=')?;
+    let value = value.trim();
+    let value = match value.chars().next() {
+        Some(quote @ ('"' | '\'')) => value[1..].strip_suffix(quote).unwrap_or(&value[1..]),
+        _ => value,
+    };
+
+    if value.is_empty() {
+        return None; // `foo=` followed by an Expression
+    }
+    return Some(value.to_string());
+}
+
+// `foo` for the `foo="bar"`, `foo=bar` and `foo=` (followed by an Expression) attribute tokens
+fn attribute_name<'a>(token: &Token, source: &'a str) -> &'a str {
+    return token
+        .lexeme(source)
+        .split('=')
+        .next()
+        .unwrap_or_default()
+        .trim();
+}
@@ -0,0 +1,370 @@
+// The props a component declares in its frontmatter, with `interface Props { ... }` or
+// `type Props = { ... }`, so the attributes passed to it can be checked. Like
+// `declarations`, it reads the tokens and doesn't type check anything: a type is only
+// known when it's a string, a number or a boolean, anything else is `Other`.
+
+use std::collections::HashMap;
+
+use crate::codes;
+use crate::declarations::is_identifier;
+use crate::diagnostic::Diagnostic;
+use crate::expr::{Lexer, Token, TokenKind};
+use crate::span::Span;
+use crate::token;
+use crate::token_type::TokenType;
+
+/// The props of a component
+#[derive(Debug, Clone)]
+pub struct Props {
+    pub props: Vec<Prop>,
+    pub rest: bool, // has an index signature, `[key: string]: unknown`, any other prop is fine
+    pub span: Span, // of `Props`, in bytes from the start of the code
+}
+
+#[derive(Debug, Clone)]
+pub struct Prop {
+    pub name: String,
+    pub optional: bool, // `title?: string`, or a type with `undefined`
+    pub kind: PropKind,
+    pub type_text: String, // the type as written, `"primary" | "secondary"`
+    pub span: Span,        // of the name
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PropKind {
+    String,  // `string`, `"primary" | "secondary"`
+    Number,  // `number`, `1 | 2`
+    Boolean, // `boolean`, `true`
+    Other,   // objects, arrays, functions, other types and unions of different kinds
+}
+
+impl Props {
+    /// The prop called `name`
+    pub fn get(&self, name: &str) -> Option<&Prop> {
+        return self.props.iter().find(|prop| prop.name == name);
+    }
+}
+
+/// The `Props` that `code` declares, `None` if it doesn't
+pub fn props(code: &str) -> Option<Props> {
+    let tokens = Lexer::new(code).tokenize();
+
+    for (i, token) in tokens.iter().enumerate() {
+        let rest = &tokens[i + 1..];
+        if rest.first().is_none_or(|name| name.text != "Props") {
+            continue;
+        }
+
+        // `interface Props extends Base {`, `type Props = {`
+        let body = match token.text.as_str() {
+            "interface" => rest.iter().position(|token| token.text == "{"),
+            "type" if rest.get(1).is_some_and(|token| token.text == "=") => {
+                Some(2).filter(|&open| rest.get(open).is_some_and(|token| token.text == "{"))
+            }
+            _ => None,
+        };
+        let Some(open) = body else {
+            continue;
+        };
+
+        let mut props = Props {
+            props: Vec::new(),
+            rest: false,
+            span: rest[0].span,
+        };
+        members(code, &rest[open + 1..], &mut props);
+        return Some(props);
+    }
+
+    return None;
+}
+
+/// Checks the attributes passed to components against their props: required props that are
+/// missing, props that aren't declared and strings passed where a number, a boolean or an
+/// object is expected. `components` are the props of the components by the name they're
+/// used with in `tokens`.
+pub fn check_props(
+    tokens: &token::TokenStream,
+    components: &HashMap<String, Props>,
+) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+
+    for (i, start) in tokens.iter().enumerate() {
+        if !matches!(start.kind, TokenType::OpeningTagStart) {
+            continue;
+        }
+        let name = tokens.literal_str(start).unwrap_or_default();
+        let Some(props) = components.get(&*name) else {
+            continue;
+        };
+
+        let mut passed = Vec::new();
+        let mut spread = false;
+        for token in &tokens[i + 1..] {
+            match token.kind {
+                TokenType::TextToken | TokenType::BooleanAttribute => {
+                    passed.push(attribute(token, tokens.source()))
+                }
+                TokenType::ShorthandAttribute => passed.push(Passed {
+                    name: tokens.literal_str(token).unwrap_or_default().into_owned(),
+                    name_span: token.span,
+                    value: Value::Expression,
+                    value_span: token.span,
+                }),
+                TokenType::SpreadAttribute => spread = true,
+                TokenType::Expression | TokenType::ClientDirective => {}
+                _ => break,
+            }
+        }
+
+        for attribute in &passed {
+            // directives like `transition:name`, and `slot`, are for Regg, not the component
+            if attribute.name.contains(':') || attribute.name == "slot" {
+                continue;
+            }
+            match props.get(&attribute.name) {
+                Some(prop) => diagnostics.extend(mismatch(&name, prop, attribute, start.line())),
+                None if !props.rest => {
+                    let message = format!("`<{}>` has no prop `{}`", name, attribute.name);
+                    let diagnostic = Diagnostic::warning(start.line(), &message)
+                        .with_code(codes::UNKNOWN_PROP)
+                        .with_span(attribute.name_span)
+                        .with_help(&format!("declare it in the `Props` of `{}`", name));
+                    diagnostics.push(diagnostic);
+                }
+                None => {}
+            }
+        }
+
+        if spread {
+            continue; // `{...rest}` can pass any of them
+        }
+        for prop in props.props.iter().filter(|prop| !prop.optional) {
+            if passed.iter().any(|attribute| attribute.name == prop.name) {
+                continue;
+            }
+            let message = format!("`<{}>` is missing the prop `{}`", name, prop.name);
+            let diagnostic = Diagnostic::warning(start.line(), &message)
+                .with_code(codes::MISSING_PROP)
+                .with_span(start.span)
+                .with_help(&format!("pass it, like `{}={{...}}`", prop.name));
+            diagnostics.push(diagnostic);
+        }
+    }
+
+    return diagnostics;
+}
+
+// An attribute passed to a component
+struct Passed {
+    name: String,
+    name_span: Span,
+    value: Value,
+    value_span: Span, // the whole attribute if it has no value of its own
+}
+
+enum Value {
+    Text(String), // `title="Home"`
+    Expression,   // `count={3}`
+    True,         // `open`
+}
+
+// `name="value"`, `name=value`, `name=` before an Expression or `name`
+fn attribute(token: &token::Token, source: &str) -> Passed {
+    let text = token.lexeme(source);
+    let Some((name, value)) = text.split_once('=') else {
+        return Passed {
+            name: text.trim().to_string(),
+            name_span: token.span,
+            value: Value::True,
+            value_span: token.span,
+        };
+    };
+
+    let name_span = Span::new(token.span.start, token.span.start + name.trim_end().len());
+    let trimmed = value.trim_start();
+    let value_start = token.span.start + text.len() - trimmed.len();
+    let (value, value_span) = match trimmed.is_empty() {
+        true => (Value::Expression, token.span),
+        false => (
+            Value::Text(trimmed.to_string()),
+            Span::new(value_start, token.span.end),
+        ),
+    };
+    return Passed {
+        name: name.trim().to_string(),
+        name_span,
+        value,
+        value_span,
+    };
+}
+
+// A value that can't be what the prop expects, only when that's obvious from the source
+fn mismatch(component: &str, prop: &Prop, attribute: &Passed, line: usize) -> Option<Diagnostic> {
+    let expected = match prop.kind {
+        PropKind::String => "a string",
+        PropKind::Number => "a number",
+        PropKind::Boolean => "a boolean",
+        PropKind::Other if is_object_type(&prop.type_text) => "an object",
+        PropKind::Other => return None, // maybe a string, like a type alias of string literals
+    };
+
+    let (message, help) = match &attribute.value {
+        Value::Text(text) if prop.kind != PropKind::String => (
+            format!(
+                "`{}` of `<{}>` expects {}, `{}` is a string",
+                prop.name, component, expected, text
+            ),
+            format!("pass an expression, like `{}={{...}}`", prop.name),
+        ),
+        Value::True if matches!(prop.kind, PropKind::String | PropKind::Number) => (
+            format!(
+                "`{}` of `<{}>` expects {}, `{}` on its own passes `true`",
+                prop.name, component, expected, prop.name
+            ),
+            format!("give it a value, like `{}={{...}}`", prop.name),
+        ),
+        _ => return None,
+    };
+    let diagnostic = Diagnostic::warning(line, &message)
+        .with_code(codes::PROP_TYPE_MISMATCH)
+        .with_span(attribute.value_span)
+        .with_help(&help);
+    return Some(diagnostic);
+}
+
+// `{ id: number }`, `string[]`, `Array<string>`, `(event: Event) => void`
+fn is_object_type(type_text: &str) -> bool {
+    return type_text.starts_with(['{', '('])
+        || type_text.ends_with("[]")
+        || type_text.starts_with("Array<")
+        || type_text.starts_with("Record<");
+}
+
+// The members of an object type, up to its `}`
+fn members(code: &str, tokens: &[Token], props: &mut Props) {
+    let mut i = 0;
+
+    while let Some(token) = tokens.get(i) {
+        match token.text.as_str() {
+            "}" => return,
+            ";" | "," => i += 1,
+            "[" => {
+                props.rest = true;
+                i = member_end(code, tokens, i);
+            }
+            "readonly" if tokens.get(i + 1).is_some_and(is_member_name) => i += 1,
+            _ if is_member_name(token) => {
+                let mut next = i + 1;
+                let optional = tokens.get(next).is_some_and(|token| token.text == "?");
+                if optional {
+                    next += 1;
+                }
+
+                let end = member_end(code, tokens, next);
+                let (kind, type_text, undefined) = match tokens.get(next) {
+                    Some(colon) if colon.text == ":" && next + 1 < end => {
+                        let first = &tokens[next + 1];
+                        let last = &tokens[end - 1];
+                        let types = &tokens[next + 1..end];
+                        let text = &code[first.span.start..last.span.end];
+                        (kind(types), text.to_string(), has_undefined(types))
+                    }
+                    _ => (PropKind::Other, String::new(), false), // a method, `onClick(): void`
+                };
+
+                props.props.push(Prop {
+                    name: name(token),
+                    optional: optional || undefined,
+                    kind,
+                    type_text,
+                    span: token.span,
+                });
+                i = end;
+            }
+            _ => i += 1,
+        }
+    }
+}
+
+// Where the member starting at `start` ends: at a `;` or `,`, at the `}` of the object
+// type, or at a line break that isn't in the middle of the type, like after `|`
+fn member_end(code: &str, tokens: &[Token], start: usize) -> usize {
+    let mut depth = 0;
+
+    for i in start..tokens.len() {
+        let token = &tokens[i];
+        if depth == 0 && i > start {
+            let previous = &tokens[i - 1];
+            let line_break = code[previous.span.end..token.span.start].contains('\n');
+            let continued = ["|", "&", ":", "?", "=>"]
+                .iter()
+                .any(|operator| previous.text == *operator || token.text == *operator);
+            if line_break && !continued {
+                return i;
+            }
+        }
+
+        match token.text.as_str() {
+            ";" | "," if depth == 0 => return i,
+            "}" | ")" | "]" if depth == 0 => return i,
+            "{" | "(" | "[" => depth += 1,
+            "}" | ")" | "]" => depth -= 1,
+            _ if token.text.chars().all(|c| c == '<') => depth += token.text.len(),
+            _ if token.text.chars().all(|c| c == '>') && depth > 0 => {
+                depth = depth.saturating_sub(token.text.len())
+            }
+            _ => {}
+        }
+    }
+
+    return tokens.len();
+}
+
+// The kind of a type, `string | undefined` is a string
+fn kind(types: &[Token]) -> PropKind {
+    let mut kinds = types
+        .split(|token| token.text == "|")
+        .filter(|members| !members.is_empty())
+        .filter_map(|members| match members {
+            [token] if matches!(token.text.as_str(), "undefined" | "null") => None,
+            [token] => Some(match (token.kind, token.text.as_str()) {
+                (TokenKind::String, _) | (_, "string") => PropKind::String,
+                (TokenKind::Number, _) | (_, "number") => PropKind::Number,
+                (TokenKind::Boolean, _) | (_, "boolean") => PropKind::Boolean,
+                _ => PropKind::Other,
+            }),
+            _ => Some(PropKind::Other),
+        });
+
+    let Some(first) = kinds.next() else {
+        return PropKind::Other;
+    };
+    return match kinds.all(|kind| kind == first) {
+        true => first,
+        false => PropKind::Other,
+    };
+}
+
+fn has_undefined(types: &[Token]) -> bool {
+    return types
+        .split(|token| token.text == "|")
+        .any(|members| matches!(members, [token] if token.text == "undefined"));
+}
+
+// `title`, or a quoted name like `"aria-label"`
+fn is_member_name(token: &Token) -> bool {
+    return matches!(
+        token.kind,
+        TokenKind::Identifier | TokenKind::String | TokenKind::Boolean | TokenKind::Null
+    ) || is_identifier(&token.text);
+}
+
+fn name(token: &Token) -> String {
+    if token.kind != TokenKind::String {
+        return token.text.clone();
+    }
+    let quote = token.text.chars().next().unwrap_or('"');
+    let text = &token.text[1..];
+    return text.strip_suffix(quote).unwrap_or(text).to_string();
+}
@@ -1,299 +1,1308 @@
-use crate::token::Token;
+use std::{borrow::Cow, sync::Arc, thread};
+
+use crate::codes;
+use crate::declarations::is_identifier;
+use crate::diagnostic::Diagnostic;
+use crate::expr;
+use crate::html::{decode_character_reference, is_void_element, MAX_CHARACTER_REFERENCE_LENGTH};
+use crate::ice;
+use crate::span::Span;
+use crate::token::{Literal, Token, TokenStream};
 use crate::token_type::TokenType;
-use crate::Regg;
 
-pub struct Scanner {
-    source: String,
-    tokens: Vec<Token>,
+/// Source length (in bytes) from which `Regg::run` lexes with `scan_tokens_parallel`
+pub const PARALLEL_THRESHOLD: usize = 1024 * 1024;
+
+/// The byte order mark some Windows editors start UTF-8 files with, skipped like whitespace
+pub const BOM: char = '\u{feff}';
+
+// How far past the end of a token the scanner may look to decide where it ends, in bytes:
+// text ends before a character reference, which is at most this long
+const LOOKAHEAD: usize = MAX_CHARACTER_REFERENCE_LENGTH;
+
+// Elements whose content is text up to their end tag, not markup
+const RAW_TEXT_END_TAGS: [&str; 2] = ["</script", "</style"];
+
+/// Caps on what the scanner takes on, so pathological or hostile input ends with a
+/// `LIMIT_EXCEEDED` error instead of scanning for hours or filling the memory with tokens.
+/// Scanning stops at the first limit hit, the token that would go over it isn't added.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ScannerOptions {
+    pub max_input_size: usize,   // of the source, in bytes
+    pub max_depth: usize,        // elements, fragments and markup expressions open at once
+    pub max_tokens: usize,       // not counting EOF
+    pub max_token_length: usize, // of one text, expression, code block..., in bytes
+}
+
+impl ScannerOptions {
+    /// Limits far above what any real template needs
+    pub fn new() -> Self {
+        Self {
+            max_input_size: 256 * 1024 * 1024,
+            max_depth: 1024,
+            max_tokens: 1 << 24,
+            max_token_length: 16 * 1024 * 1024,
+        }
+    }
+
+    /// No limits at all
+    pub fn unlimited() -> Self {
+        Self {
+            max_input_size: usize::MAX,
+            max_depth: usize::MAX,
+            max_tokens: usize::MAX,
+            max_token_length: usize::MAX,
+        }
+    }
+}
+
+impl Default for ScannerOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// What the scanner is in the middle of, which decides what the next characters are.
+///
+/// The scanner starts in `Data`. A `<` that starts a tag switches to `TagOpen`, which scans
+/// the tag name and goes on to `TagInterior` for the attributes, or back to `Data` after a
+/// closing tag or fragment. The `>` of the tag goes back to `Data`, or to `RawText` for
+/// `<script>` and `<style>`, whose content is one TextToken up to the end tag. `{` switches to
+/// `Expression` from `Data` and from `TagInterior` (`foo={bar}`), which returns where it came
+/// from after the `}`. `---` switches to `Frontmatter` until the closing fence.
+///
+/// After every `<tag` the mode is `TagInterior`, and after every `<![CDATA[...]]>` it's
+/// `Data`, whatever came before them. `relex` and `scan_tokens_parallel` rely on it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    Data,
+    TagOpen,
+    TagInterior,
+    Expression { in_tag: bool },
+    RawText(&'static str), // the end tag, `</script` or `</style`
+    Frontmatter,
+}
+
+/// A change to a source, the bytes of `span` are replaced with `text`
+#[derive(Debug, Clone)]
+pub struct TextEdit {
+    pub span: Span,
+    pub text: String,
+}
+
+/// What `relex` scanned again: the tokens in `old`, a span of the source before the edit,
+/// were replaced by the ones in `new`. The tokens after them only moved, `lines` lines down.
+#[derive(Debug, Clone, Copy)]
+pub struct Damage {
+    pub old: Span,
+    pub new: Span,
+    pub lines: isize,
+}
+
+pub struct Scanner<'a> {
+    source: &'a str,
+    tokens: TokenStream,
     start: usize,
     current: usize,
     line: usize,
+    mode: Mode,
+    raw_text_end: Option<&'static str>, // `</script` once `<script` is scanned, until its `>`
+    diagnostics: Vec<Diagnostic>,
+    damage: Option<Damage>, // set by `relex`
+    options: ScannerOptions,
+    depth: usize,                // elements, fragments and markup expressions open
+    opens_element: bool,         // whether the tag being scanned opens an element, `<br` doesn't
+    limited: bool,               // a limit was hit, scanning stopped
+    lost_start: bool, // an expression goes on after markup whose start this scanner didn't see
+    shared: Option<Arc<String>>, // the source, for the tokens to share instead of copying it
 }
 
-impl Scanner {
-    pub fn new(source: String) -> Self {
+impl<'a> Scanner<'a> {
+    pub fn new(source: &'a str) -> Self {
         Self {
             source,
-            tokens: Vec::new(),
+            tokens: TokenStream::default(),
             start: 0,
             current: 0,
             line: 1,
+            mode: Mode::Data,
+            raw_text_end: None,
+            diagnostics: Vec::new(),
+            damage: None,
+            options: ScannerOptions::new(),
+            depth: 0,
+            opens_element: false,
+            limited: false,
+            lost_start: false,
+            shared: None,
         }
     }
 
-    pub fn scan_tokens(&mut self) -> &Vec<Token> {
-        while !self.is_at_end() {
+    /// Scans `source` like `new`, the tokens share it instead of copying it
+    pub fn shared(source: &'a Arc<String>) -> Self {
+        let mut scanner = Scanner::new(source);
+        scanner.shared = Some(Arc::clone(source));
+        return scanner;
+    }
+
+    /// Scans with `options` instead of the default limits
+    pub fn with_options(mut self, options: ScannerOptions) -> Self {
+        self.options = options;
+        return self;
+    }
+
+    pub fn scan_tokens(&mut self) -> &TokenStream {
+        if self.source.len() > self.options.max_input_size {
+            let message = format!(
+                "Limit exceeded: the source is longer than {} bytes",
+                self.options.max_input_size
+            );
+            self.limit_exceeded(&message, None);
+        }
+        self.scan_until(self.source.len());
+        self.add_eof();
+        self.tokens.set_source(self.shared_source());
+
+        return &self.tokens;
+    }
+
+    /// Lexes the source in up to `chunk_count` chunks on separate threads.
+    ///
+    /// The source is split right before top-level tags, outside of code blocks, expressions
+    /// and raw text elements. Every chunk starts with the line number it has in the whole
+    /// source, so the stitched tokens and diagnostics are identical to the ones from
+    /// `scan_tokens`. If a token of one chunk runs past the start of the next chunk, or the
+    /// next chunk doesn't start where scanning it on its own does, it's scanned again from
+    /// where the previous one stopped.
+    pub fn scan_tokens_parallel(&mut self, chunk_count: usize) -> &TokenStream {
+        if self.source.len() > self.options.max_input_size {
+            return self.scan_tokens();
+        }
+
+        let source = self.source;
+        let options = self.options;
+        let boundaries = chunk_boundaries(source, chunk_count);
+        let ice_source = ice::current_source();
+
+        let chunks: Vec<(usize, Scanner)> = thread::scope(|scope| {
+            let handles: Vec<_> = boundaries
+                .windows(2)
+                .map(|range| {
+                    let (start, end) = (range[0], range[1]);
+                    let ice_source = ice_source.clone();
+                    scope.spawn(move || {
+                        ice::share_source(ice_source);
+                        let mut chunk = Scanner::starting_at(source, start).with_options(options);
+                        chunk.scan_until(end);
+                        (start, chunk)
+                    })
+                })
+                .collect();
+
+            handles
+                .into_iter()
+                .map(|handle| handle.join().expect("Scanner thread panicked"))
+                .collect()
+        });
+
+        for ((start, chunk), range) in chunks.into_iter().zip(boundaries.windows(2)) {
+            if self.limited {
+                break;
+            }
+            // The chunk was scanned as if it came after text, and without the tokens before
+            // it. If the previous chunk overran its boundary or ended in a tag, or the chunk
+            // needed those tokens, the chunk is scanned again from where the previous one
+            // stopped, with everything before it.
+            if start == self.current && self.mode == Mode::Data && !chunk.lost_start {
+                self.append(chunk);
+            } else {
+                self.scan_until(range[1]);
+            }
+        }
+        // every chunk counts its own tokens
+        if !self.limited && self.tokens.len() > self.options.max_tokens {
+            self.tokens.truncate(self.options.max_tokens);
+            let last = self.tokens.last().copied();
+            if let Some(last) = last {
+                (self.start, self.current, self.line) = (last.span.end, last.span.end, last.line());
+            }
+            self.diagnostics
+                .retain(|diagnostic| diagnostic.line <= self.line);
+            self.too_many_tokens();
+        }
+
+        self.add_eof();
+        self.tokens.set_source(self.shared_source());
+
+        return &self.tokens;
+    }
+
+    /// Scans the source after an edit again, given the tokens of the source before it.
+    ///
+    /// Only the tokens around the edit are scanned: from the last tag that starts far enough
+    /// before it, up to the first tag after it that starts where it did before. The tokens
+    /// before are kept as they are and the ones after are moved by the edit, which is
+    /// possible because the scanner is in the same state after every `<tag`, no matter what
+    /// came before it. `diagnostics` only has the errors of the tokens scanned again.
+    pub fn relex(&mut self, old_tokens: TokenStream, edit: &TextEdit) -> TokenStream {
+        let old: Vec<Token> = old_tokens.to_vec();
+        let old_len = old.last().map_or(0, |eof| eof.span.end);
+        let new_end = edit.span.start + edit.text.len();
+        let shift = new_end as isize - edit.span.end as isize;
+
+        // the last tag to start from, the tokens before it can't have looked into the edit
+        let touched = old.partition_point(|token| token.span.end < edit.span.start);
+        let restart = (1..touched.min(old.len())).rev().find(|&index| {
+            is_tag_start(&old[index])
+                && starts_tag_in_data(old_tokens.lexeme(&old[index]))
+                && old[index].span.start <= edit.span.start
+                && old[index - 1].span.end + LOOKAHEAD <= edit.span.start
+        });
+        let (kept, start) = match restart {
+            Some(index) => (index, old[index].span.start),
+            None => (0, 0),
+        };
+        self.start = start;
+        self.current = start;
+        self.mode = Mode::Data;
+        self.line = match kept {
+            0 => 1 + self.source[..start].matches('\n').count(),
+            _ => {
+                let previous = &old[kept - 1];
+                previous.line() + self.source[previous.span.end..start].matches('\n').count()
+            }
+        };
+
+        let mut old = old.into_iter();
+        let mut tokens: Vec<Token> = old.by_ref().take(kept).collect();
+        let mut old = old.peekable();
+        while !self.is_at_end() && !self.limited {
             self.start = self.current;
+            ice::set_span(Span::new(self.start, self.start));
+            let count = self.tokens.len();
             self.scan_token();
+
+            // back in step once a tag after the edit starts where it did before
+            let Some(token) = self.tokens.get(count).filter(|token| is_tag_start(token)) else {
+                continue;
+            };
+            if token.span.start < new_end {
+                continue;
+            }
+            let old_start = (token.span.start as isize - shift) as usize;
+            while old.next_if(|old| old.span.start < old_start).is_some() {}
+            let Some(same) = old.next_if(|old| {
+                old.span.start == old_start
+                    && is_tag_start(old)
+                    && old_tokens.lexeme(old) == token.lexeme(self.source)
+            }) else {
+                continue;
+            };
+
+            let line_shift = token.line as isize - same.line as isize;
+            self.damage = Some(Damage {
+                old: Span::new(start, old_start),
+                new: Span::new(start, token.span.start),
+                lines: line_shift,
+            });
+            tokens.extend(std::mem::take(&mut self.tokens));
+            tokens.extend(old.map(|mut token| {
+                token.span = Span::new(
+                    (token.span.start as isize + shift) as usize,
+                    (token.span.end as isize + shift) as usize,
+                );
+                token.line = (token.line as isize + line_shift) as u32;
+                token
+            }));
+            return TokenStream::shared(self.shared_source(), tokens);
         }
 
-        self.tokens.push(Token {
-            token_type: TokenType::EOF,
-            lexeme: "".to_string(),
-            literal: None,
-            line: self.line,
+        self.damage = Some(Damage {
+            old: Span::new(start, old_len),
+            new: Span::new(start, self.source.len()),
+            lines: 0,
         });
+        self.add_eof();
+        tokens.extend(std::mem::take(&mut self.tokens));
+        return TokenStream::shared(self.shared_source(), tokens);
+    }
 
-        return &self.tokens;
+    /// What the last `relex` scanned again
+    pub fn damage(&self) -> Option<Damage> {
+        return self.damage;
+    }
+
+    /// The tokens, once the source is scanned
+    pub fn into_tokens(self) -> TokenStream {
+        return self.tokens;
+    }
+
+    /// Errors found while scanning, in source order
+    pub fn diagnostics(&self) -> &Vec<Diagnostic> {
+        return &self.diagnostics;
     }
 
+    fn shared_source(&self) -> Arc<String> {
+        return self
+            .shared
+            .clone()
+            .unwrap_or_else(|| Arc::new(self.source.to_string()));
+    }
+
+    fn starting_at(source: &'a str, offset: usize) -> Self {
+        let mut scanner = Scanner::new(source);
+        scanner.start = offset;
+        scanner.current = offset;
+        scanner.line = 1 + source[..offset].matches('\n').count();
+        return scanner;
+    }
+
+    fn append(&mut self, chunk: Scanner) {
+        // the chunk was scanned as if nothing was open before it, the nesting adds up here
+        let mut kept = chunk.tokens.len();
+        for (index, token) in chunk.tokens.iter().enumerate() {
+            let depth = self.nest(token.kind, token.span);
+            if depth > self.options.max_depth {
+                kept = index;
+                break;
+            }
+            self.depth = depth;
+        }
+        let too_deep = chunk.tokens.get(kept).copied();
+
+        self.tokens.extend(chunk.tokens.into_iter().take(kept));
+        self.current = chunk.current;
+        self.line = chunk.line;
+        self.mode = chunk.mode;
+        self.raw_text_end = chunk.raw_text_end;
+        self.limited = chunk.limited;
+        match too_deep {
+            // the chunk's diagnostics all come after it
+            Some(token) => {
+                (self.start, self.current, self.line) =
+                    (token.span.start, token.span.end, token.line());
+                self.too_deep();
+            }
+            None => self.diagnostics.extend(chunk.diagnostics),
+        }
+    }
+
+    fn scan_until(&mut self, end: usize) {
+        while !self.is_at_end() && !self.limited && self.current < end {
+            self.start = self.current;
+            ice::set_span(Span::new(self.start, self.start));
+            self.scan_token();
+        }
+    }
+
+    fn add_eof(&mut self) {
+        self.tokens.push(Token::new(
+            TokenType::EOF,
+            Span::new(self.current, self.current),
+            self.line,
+        ));
+    }
+
+    // One token, or none when the mode only changes. Each mode either consumes something or
+    // hands over to one that does, so the scanner always moves on.
     fn scan_token(&mut self) {
-        // Current character being scanned
-        let c = self.advance();
-        match c {
-            // Code Block
-            '-' => {
-                if self.match_char('-') {
-                    if self.match_char('-') {
-                        self.code_block(); // ---
-                    }
-                }
+        if self.current == 0 && self.source.starts_with(BOM) {
+            self.advance();
+            return; // trivia of the first token, not text
+        }
+
+        match self.mode {
+            Mode::Data => self.data(),
+            Mode::TagOpen => self.tag_open(),
+            Mode::TagInterior => self.tag_interior(),
+            Mode::Expression { in_tag } => {
+                self.expression();
+                self.mode = match in_tag {
+                    true => Mode::TagInterior,
+                    false => Mode::Data,
+                };
             }
-            '{' => {
-                self.expression(); // { foo }
+            Mode::RawText(end_tag) => self.raw_text(end_tag),
+            Mode::Frontmatter => {
+                self.advance(); // consume `---`
+                self.advance();
+                self.advance();
+                self.code_block();
+                self.mode = Mode::Data;
             }
-            '<' => {
-                if self.match_char('/') {
-                    self.closing_tag(); // '</foo>'
-                } else {
-                    self.opening_tag_start();
-                }
+        }
+    }
+
+    fn data(&mut self) {
+        let rest = &self.source[self.current..];
+        if rest.starts_with("---") {
+            self.mode = Mode::Frontmatter;
+        } else if rest.starts_with('{') {
+            self.mode = Mode::Expression { in_tag: false };
+        } else if rest.starts_with("<![CDATA[") {
+            self.cdata();
+        } else if self.is_tag_start() {
+            self.mode = Mode::TagOpen;
+        } else if let Some((length, _)) = self.character_reference() {
+            for _ in 0..length {
+                self.advance();
             }
-            '>' => {
-                self.add_token(TokenType::OpeningTagEnd, None);
+            self.add_token(TokenType::Entity);
+        } else if !self.html_expr_delimiter() {
+            match self.advance() {
+                ' ' | '\r' | '\t' | '\n' => {} // `\n` is counted by `advance`
+                _ => self.text_token(),
             }
-            '/' => {
-                if self.match_char('>') {
-                    self.add_token(TokenType::SelfClosingTagEnd, None)
-                }
+        }
+    }
+
+    // `<` and what it opens or closes
+    fn tag_open(&mut self) {
+        self.mode = Mode::Data;
+        if self.source[self.current..].starts_with("<![CDATA[") {
+            // in a tag that wasn't closed, it's the same CDATA as outside of one
+            self.raw_text_end = None;
+            self.cdata();
+            return;
+        }
+
+        self.advance(); // consume `<`
+
+        if self.match_char('/') {
+            if self.match_char('>') {
+                self.add_token(TokenType::FragmentClose); // '</>'
+            } else {
+                self.closing_tag(); // '</foo>'
             }
-            '(' => {
-                if self.match_char('`') {
-                    self.add_token(TokenType::HTMLExprStart, None)
-                }
+        } else if self.match_char('>') {
+            self.add_token(TokenType::FragmentOpen); // '<>'
+        } else if self.match_char('{') {
+            self.dynamic_tag_start(); // '<{foo}'
+            self.mode = Mode::TagInterior;
+        } else {
+            self.opening_tag_start();
+            self.mode = Mode::TagInterior;
+        }
+    }
+
+    // The attributes of an opening tag, up to its `>` or `/>`
+    fn tag_interior(&mut self) {
+        let rest = &self.source[self.current..];
+        if let Some(braced) = rest.strip_prefix('{') {
+            if braced.trim_start().starts_with("...") {
+                self.advance();
+                self.braced_attribute(TokenType::SpreadAttribute); // <foo {...bar}>
+            } else if self.is_shorthand_attribute() {
+                self.advance();
+                self.braced_attribute(TokenType::ShorthandAttribute); // <foo {bar}>
+            } else {
+                self.mode = Mode::Expression { in_tag: true }; // <foo bar={baz}>
             }
-            '`' => {
-                if self.match_char(')') {
-                    self.add_token(TokenType::HTMLExprEnd, None);
-                    self.start += 2;
-                    self.expression();
-                }
+        } else if rest.starts_with('<') {
+            self.mode = Mode::TagOpen; // the tag wasn't closed, reported by the parser
+        } else if rest.starts_with("/>") {
+            self.advance();
+            self.advance();
+            self.add_token(TokenType::SelfClosingTagEnd);
+            self.raw_text_end = None;
+            self.mode = Mode::Data;
+        } else if rest.starts_with('>') {
+            self.advance();
+            self.add_token(TokenType::OpeningTagEnd);
+            self.mode = match self.raw_text_end.take() {
+                Some(end_tag) => Mode::RawText(end_tag),
+                None => Mode::Data,
+            };
+        } else if !self.html_expr_delimiter() {
+            match self.advance() {
+                ' ' | '\r' | '\t' | '\n' => {}
+                _ => self.attribute(),
             }
-            // ignore whitespaces
-            ' ' => {}
-            '\r' => {}
-            '\t' => {}
-            '\n' => self.line += 1,
-            _ => self.text_token(),
         }
     }
 
-    fn code_block(&mut self) {
-        // consume current character until frontmatter fence (---) is reached
-        while !self.is_at_end()
-            && self.peek().unwrap() != '-'
-            && self.peek_next().unwrap() != '-'
-            && self.peek_third().unwrap() != '-'
-        {
-            if self.peek().unwrap() == '\n' {
-                self.line += 1;
+    // `(\`` starts markup inside an expression, `\`)` ends it and the expression goes on
+    fn html_expr_delimiter(&mut self) -> bool {
+        let rest = &self.source[self.current..];
+        if rest.starts_with("(`") {
+            self.advance();
+            self.advance();
+            self.add_token(TokenType::HTMLExprStart);
+            self.mode = Mode::Data;
+            return true;
+        }
+        if rest.starts_with("`)") {
+            self.advance();
+            self.advance();
+            self.add_token(TokenType::HTMLExprEnd);
+            self.mode = Mode::Expression { in_tag: false };
+            return true;
+        }
+        return false;
+    }
+
+    // `<![CDATA[ ... ]]>`, its content is text even if it looks like markup
+    fn cdata(&mut self) {
+        let content_start = self.current + "<![CDATA[".len();
+        let content_end = match self.source[content_start..].find("]]>") {
+            Some(length) => content_start + length,
+            None => self.source.len(),
+        };
+        while self.current < content_end {
+            self.advance();
+        }
+
+        if self.is_at_end() {
+            self.unterminated(
+                codes::UNTERMINATED_CDATA,
+                "Unterminated CDATA section, expected `]]>`",
+                Span::new(self.start, content_start),
+                "CDATA section",
+            );
+        } else {
+            self.advance(); // consume `]]>`
+            self.advance();
+            self.advance();
+        }
+
+        self.add_token(TokenType::CData);
+    }
+
+    // The content of `<script>` or `<style>`, a single TextToken up to the end tag
+    fn raw_text(&mut self, end_tag: &'static str) {
+        while !self.is_at_end() && !self.is_end_tag(end_tag) {
+            // `"</script>"` in a string or a comment doesn't end the script
+            let skipped = match end_tag {
+                "</script" => js_string_or_comment_length(&self.source[self.current..]),
+                _ => None,
+            };
+            let end = self.current + skipped.unwrap_or(1);
+            while self.current < end {
+                self.advance();
             }
+        }
+
+        if self.current > self.start {
+            self.add_token(TokenType::TextToken);
+        }
+        self.mode = Mode::Data;
+    }
+
+    // `</script` followed by whitespace, `>` or `/`, in any case
+    fn is_end_tag(&self, end_tag: &str) -> bool {
+        let rest = &self.source.as_bytes()[self.current..];
+        return rest.len() >= end_tag.len()
+            && rest[..end_tag.len()].eq_ignore_ascii_case(end_tag.as_bytes())
+            && rest
+                .get(end_tag.len())
+                .is_none_or(|&c| c.is_ascii_whitespace() || c == b'>' || c == b'/');
+    }
+
+    // The length in characters and the value of the character reference at `current`
+    fn character_reference(&self) -> Option<(usize, String)> {
+        let rest = &self.source[self.current..];
+        if !rest.starts_with('&') {
+            return None;
+        }
+
+        let end = rest
+            .bytes()
+            .take(MAX_CHARACTER_REFERENCE_LENGTH)
+            .position(|c| c == b';')?;
+        let reference = &rest[..=end];
+        let value = decode_character_reference(reference)?;
+        return Some((reference.chars().count(), value));
+    }
 
+    // `<` followed by something a tag starts with, `a < b` and `<3` are text
+    fn is_tag_start(&mut self) -> bool {
+        return self.peek() == '<' && matches!(self.peek_next(), '/' | '>' | '{' | '!')
+            || self.peek() == '<' && self.peek_next().is_alphabetic();
+    }
+
+    fn code_block(&mut self) {
+        // consume current character until frontmatter fence (---) is reached
+        while !self.is_at_end() && !self.source[self.current..].starts_with("---") {
             self.advance();
         }
 
         if self.is_at_end() {
-            let mut regg = Regg::new();
-            regg.error(self.line, "Unterminated frontmatter fence token `---`");
+            self.unterminated(
+                codes::UNTERMINATED_FRONTMATTER,
+                "Unterminated frontmatter fence token `---`",
+                Span::new(self.start, self.start + 3),
+                "frontmatter",
+            );
+            self.add_token(TokenType::CodeBlock);
+            return;
         }
 
-        self.advance(); // consumes white space
         self.advance(); // consume `---`
         self.advance();
         self.advance();
-        self.advance(); // consumes white space
 
-        // Get Code Block, trim `---` from start and end
-        let value = &self.source[self.start + 3..self.current - 3];
-        self.add_token(TokenType::CodeBlock, Some(value.to_string()));
+        self.add_token(TokenType::CodeBlock);
     }
 
     fn opening_tag_start(&mut self) {
-        // consume characters until space is reached
-        while !self.is_at_end() && self.peek().unwrap() != ' ' {
-            if self.peek().unwrap() == '\n' {
-                self.line += 1;
-            }
-            if self.peek().unwrap() == '>' {
-                break;
+        // consume characters until whitespace is reached
+        while !self.is_at_end() && !self.peek().is_whitespace() {
+            if self.peek() == '>' || (self.peek() == '/' && self.peek_next() == '>') {
+                break; // OpeningTagEnd || SelfClosingTagEnd, e.g. `<br/>`
             }
             self.advance();
         }
 
         // Get the HTML Tag's Name
         let value = &self.source[self.start + 1..self.current];
-        self.add_token(TokenType::OpeningTagStart, Some(value.to_string()));
+        self.add_token(TokenType::OpeningTagStart);
+        self.raw_text_end = RAW_TEXT_END_TAGS
+            .iter()
+            .find(|end_tag| end_tag[2..].eq_ignore_ascii_case(value))
+            .copied();
     }
 
-    fn closing_tag(&mut self) {
-        // consume characters until space is reached
-        while !self.is_at_end() && self.peek().unwrap() != '>' {
-            if self.peek().unwrap() == '\n' {
-                self.line += 1;
+    fn dynamic_tag_start(&mut self) {
+        // consume the tag name's expression up to the matching `}`
+        let mut depth = 1;
+        while !self.is_at_end() && depth > 0 {
+            match self.advance() {
+                '{' => depth += 1,
+                '}' => depth -= 1,
+                _ => {}
             }
+        }
+
+        if depth > 0 {
+            self.unterminated(
+                codes::UNTERMINATED_DYNAMIC_TAG,
+                "Unterminated dynamic tag name, expected `}`",
+                Span::new(self.start, self.start + 2),
+                "dynamic tag",
+            );
+        }
+
+        self.add_token(TokenType::DynamicTagStart);
+    }
 
+    fn closing_tag(&mut self) {
+        // consume characters until space is reached
+        while !self.is_at_end() && self.peek() != '>' {
             self.advance();
         }
 
-        self.advance();
+        if self.is_at_end() {
+            self.unterminated(
+                codes::UNTERMINATED_CLOSING_TAG,
+                "Unterminated closing tag, expected `>`",
+                Span::new(self.start, self.start + 2),
+                "closing tag",
+            );
+        } else {
+            self.advance(); // consume `>`
+        }
 
-        let value = &self.source[self.start + 2..self.current - 1];
-        self.add_token(TokenType::ClosingTag, Some(value.to_string()));
+        self.add_token(TokenType::ClosingTag);
     }
 
     fn text_token(&mut self) {
-        // consume characters until '>' (attribute end) or beginning of an HTML element `<`
-        while !self.is_at_end() {
-            if self.peek().unwrap() == '>'
-                || self.peek().unwrap() == '<'
-                || (self.peek().unwrap() == '/' && self.peek_next().unwrap() == '>')
-            {
-                break; // OpeningTagStart || OpeningTagEnd || SelfClosingTagEnd
-            }
+        // consume characters until a tag, an expression or a character reference starts,
+        // `>` and `/` are text
+        while !self.is_at_end()
+            && self.peek() != '{'
+            && !self.is_tag_start()
+            && self.character_reference().is_none()
+        {
+            self.advance();
+        }
 
-            if self.peek().unwrap() == '{' {
-                break; // Break if an expression token '{' is reached
-            }
+        self.add_token(TokenType::TextToken);
+    }
 
-            if self.peek().unwrap() == '\n' {
-                self.line += 1;
+    fn attribute(&mut self) {
+        // consume the attribute's name
+        while !self.is_at_end() && !self.is_attribute_name_end() {
+            self.advance();
+        }
+
+        let name_end = self.current;
+        if !self.source[self.current..].trim_start().starts_with('=') {
+            // `<input disabled>`
+            let name = &self.source[self.start..self.current];
+            if client_directive(name, None).is_some() {
+                self.add_token(TokenType::ClientDirective);
+                return;
             }
+            self.add_token(TokenType::BooleanAttribute);
+            return;
+        }
 
+        while self.peek() != '=' {
+            self.advance(); // consumes whitespace
+        }
+        self.advance(); // consume `=`
+        while self.peek().is_whitespace() {
             self.advance();
         }
+        let value_start = self.current;
 
-        // Get the HTML Tag's Name
-        let value = &self.source[self.start..self.current];
-        self.add_token(TokenType::TextToken, Some(value.to_string()));
+        match self.peek() {
+            '"' | '\'' => {
+                let opened = Span::new(self.current, self.current + 1);
+                let quote = self.advance();
+                while !self.is_at_end() && self.peek() != quote {
+                    self.advance();
+                }
+
+                if self.is_at_end() {
+                    self.unterminated(
+                        codes::UNTERMINATED_ATTRIBUTE_VALUE,
+                        "Unterminated attribute value, expected a closing quote",
+                        opened,
+                        "string",
+                    );
+                } else {
+                    self.advance(); // consume the closing quote
+                }
+            }
+            '{' => {} // `foo={bar}`, the value is scanned as an Expression
+            _ => {
+                // unquoted, `<input type=text>`
+                while !self.is_at_end() && !self.is_attribute_name_end() {
+                    self.advance();
+                }
+            }
+        }
+
+        let name = &self.source[self.start..name_end];
+        let value = &self.source[value_start..self.current];
+        if client_directive(name, Some(value)).is_some() {
+            self.add_token(TokenType::ClientDirective);
+            return;
+        }
+
+        self.add_token(TokenType::TextToken);
+    }
+
+    fn is_attribute_name_end(&mut self) -> bool {
+        let c = self.peek();
+        return c.is_whitespace()
+            || c == '='
+            || c == '>'
+            || c == '<'
+            || c == '{'
+            || (c == '/' && self.peek_next() == '>');
     }
 
     fn expression(&mut self) {
+        let resumed = self.source[..self.start].ends_with("`)");
+        if !resumed {
+            self.advance(); // consume `{`
+        }
+
         // consume all the characters before `}`
+        let mut closed = false;
         while !self.is_at_end() {
-            if self.peek().unwrap() == '\n' {
-                self.line += 1;
-            }
-
-            if self.peek().unwrap() == '(' && self.peek_next().unwrap() == '`' {
+            if self.peek() == '(' && self.peek_next() == '`' {
                 break; // Break if an HTML Expr is upcoming
             }
 
             if self.advance() == '}' {
+                closed = true;
                 break; // Break if the current scanned character is '}'
             }
         }
 
-        if self.is_at_end() {
-            let mut regg = Regg::new();
-            regg.error(self.line, "Unterminated curly brace `}`");
+        if self.is_at_end() && !closed {
+            let opened = self.expression_start();
+            self.unterminated(
+                codes::UNTERMINATED_EXPRESSION,
+                "Unterminated curly brace `}`",
+                Span::new(opened, opened + 1),
+                "expression",
+            );
         }
 
         // if `}` present
-        if self.peek().unwrap() == '}' {
-            self.advance(); // consumes whitespace
+        if self.peek() == '}' {
             self.advance(); // consume `}`
         }
 
-        if self.get_nth_char(self.start - 1).unwrap() == ')' {
-            // Expression is started following an HTMLExprEnd
-            let value = &self.source[self.start..self.current - 1];
-            self.add_token(TokenType::Expression, Some(value.to_string()));
-        } else {
-            // Get the JavaScript Expression, trim the `{` and `}`
-            let value = &self.source[self.start + 1..self.current - 1];
-            self.add_token(TokenType::Expression, Some(value.to_string()));
-        }
+        self.add_token(TokenType::Expression);
     }
 
-    fn peek(&mut self) -> Option<char> {
-        if self.is_at_end() {
-            return Some('\0');
+    // Where the `{` of the expression being scanned is. After `(\`...\`)` the expression
+    // goes on, its `{` is before the markup.
+    fn expression_start(&mut self) -> usize {
+        if !self.source[..self.start].ends_with("`)") {
+            return self.start;
         }
 
-        return self.get_nth_char(self.current);
+        let mut depth = 0;
+        for (i, token) in self.tokens.iter().enumerate().rev() {
+            match token.kind {
+                TokenType::HTMLExprEnd => depth += 1,
+                TokenType::HTMLExprStart if depth == 1 => {
+                    return i
+                        .checked_sub(1)
+                        .map_or(self.start, |i| self.tokens[i].span.start);
+                }
+                TokenType::HTMLExprStart => depth -= 1,
+                _ => {}
+            }
+        }
+        // the markup was scanned by someone else, see `relex` and `scan_tokens_parallel`
+        self.lost_start = true;
+        return self.start;
     }
 
-    fn peek_next(&mut self) -> Option<char> {
-        if self.current + 1 >= self.source.len() {
-            // Contemplate: Isn't it better to have an error that next character cannot be peeked?
-            return Some('\0');
+    // `{bar}` inside a tag that isn't the value of an attribute (`foo={bar}`)
+    fn is_shorthand_attribute(&mut self) -> bool {
+        if self.source[..self.start].trim_end().ends_with('=') {
+            return false;
         }
 
-        return self.get_nth_char(self.current + 1);
+        return match self.source[self.current + 1..].split_once('}') {
+            Some((name, _)) => is_identifier(name.trim()),
+            None => false,
+        };
     }
 
-    fn peek_third(&mut self) -> Option<char> {
-        if self.current + 2 >= self.source.len() {
-            // Contemplate: Isn't it better to have an error that next character cannot be peeked?
-            return Some('\0');
+    // A SpreadAttribute or ShorthandAttribute
+    fn braced_attribute(&mut self, token_type: TokenType) {
+        // consume all the characters before `}`
+        while !self.is_at_end() && self.peek() != '}' {
+            self.advance();
         }
 
-        return self.get_nth_char(self.current + 2);
+        if self.is_at_end() {
+            self.unterminated(
+                codes::UNTERMINATED_ATTRIBUTE_EXPRESSION,
+                "Unterminated attribute, expected `}`",
+                Span::new(self.start, self.start + 1),
+                "attribute",
+            );
+        } else {
+            self.advance(); // consume `}`
+        }
+
+        self.add_token(token_type);
+    }
+
+    // The peeks are `\0` past the end of the source
+    fn peek(&mut self) -> char {
+        return self.char_at(self.current).unwrap_or('\0');
+    }
+
+    fn peek_next(&mut self) -> char {
+        return self.nth_char_from_current(1).unwrap_or('\0');
     }
 
     fn match_char(&mut self, expected: char) -> bool {
-        let mut regg = Regg::new();
         if self.is_at_end() {
             return false;
         }
 
-        match self.get_nth_char(self.current) {
+        match self.char_at(self.current) {
             Some(current_char) => {
                 if current_char != expected {
                     return false;
                 }
 
-                self.current += 1;
+                self.current += current_char.len_utf8();
                 return true;
             }
             None => {
-                regg.error(self.line, "Scanner went out of bound");
+                self.error(codes::SCANNER_OUT_OF_BOUNDS, "Scanner went out of bound");
                 return false;
             }
         }
     }
 
     fn is_at_end(&mut self) -> bool {
-        // TODO: Handle Errors Better
-        return self.current >= self.source.len().try_into().unwrap();
+        return self.current >= self.source.len();
     }
 
+    // `\0` at the end of the source, which it doesn't move past
     fn advance(&mut self) -> char {
-        let mut regg = Regg::new();
-        let return_char = self.get_nth_char(self.current);
-        self.current = self.current + 1;
+        if self.is_at_end() {
+            return '\0';
+        }
+
+        let return_char = self.char_at(self.current);
 
         match return_char {
-            Some(char) => return char,
+            Some(char) => {
+                self.current += char.len_utf8();
+                if char == '\n' {
+                    self.line += 1;
+                }
+                return char;
+            }
             None => {
-                regg.error(self.line, "Character does not exist");
+                self.current += 1;
+                self.error(codes::SCANNER_OUT_OF_BOUNDS, "Character does not exist");
                 return '\0';
             }
         }
     }
 
-    fn add_token(&mut self, token_type: TokenType, literal: Option<String>) {
-        let text = &self.source[self.start..self.current];
+    // The literal isn't kept, `literal` finds it in the lexeme again when it's asked for
+    fn add_token(&mut self, kind: TokenType) {
+        let length = self.current - self.start;
+        if length > self.options.max_token_length {
+            let message = format!(
+                "Limit exceeded: a token is longer than {} bytes",
+                self.options.max_token_length
+            );
+            self.limit_exceeded(&message, Some(Span::new(self.start, self.current)));
+            return;
+        }
+        if self.tokens.len() >= self.options.max_tokens {
+            self.too_many_tokens();
+            return;
+        }
+        let depth = self.nest(kind, Span::new(self.start, self.current));
+        if depth > self.options.max_depth {
+            self.too_deep();
+            return;
+        }
+        self.depth = depth;
+
+        self.tokens.push(Token::new(
+            kind,
+            Span::new(self.start, self.current),
+            self.line,
+        ));
+    }
 
-        self.tokens.push(Token {
-            token_type,
-            lexeme: text.to_string(),
-            literal,
-            line: self.line,
-        })
+    // How deep the markup is nested after a token of `kind` at `span`. Only an element's `>`
+    // opens it, once it's known not to be a void or self-closing element.
+    fn nest(&mut self, kind: TokenType, span: Span) -> usize {
+        return match kind {
+            TokenType::OpeningTagStart => {
+                self.opens_element = !is_void_element(&self.source[span.start + 1..span.end]);
+                self.depth
+            }
+            TokenType::DynamicTagStart => {
+                self.opens_element = true;
+                self.depth
+            }
+            TokenType::OpeningTagEnd if self.opens_element => self.depth + 1,
+            TokenType::FragmentOpen | TokenType::HTMLExprStart => self.depth + 1,
+            TokenType::ClosingTag | TokenType::FragmentClose | TokenType::HTMLExprEnd => {
+                self.depth.saturating_sub(1)
+            }
+            _ => self.depth,
+        };
     }
 
-    fn get_nth_char(&mut self, index: usize) -> Option<char> {
-        return self.source.chars().nth(index);
+    fn too_deep(&mut self) {
+        let message = format!(
+            "Limit exceeded: more than {} elements nested",
+            self.options.max_depth
+        );
+        self.limit_exceeded(&message, Some(Span::new(self.start, self.current)));
     }
+
+    fn too_many_tokens(&mut self) {
+        let message = format!(
+            "Limit exceeded: more than {} tokens",
+            self.options.max_tokens
+        );
+        self.limit_exceeded(&message, Some(Span::new(self.start, self.start)));
+    }
+
+    // Stops scanning, the rest of the source isn't scanned
+    fn limit_exceeded(&mut self, message: &str, span: Option<Span>) {
+        let mut diagnostic = Diagnostic::error(self.line, message)
+            .with_code(codes::LIMIT_EXCEEDED)
+            .with_help("scanning stopped here, raise the limit with `ScannerOptions` if the template is fine");
+        if let Some(span) = span {
+            diagnostic = diagnostic.with_span(span);
+        }
+        self.diagnostics.push(diagnostic);
+        self.limited = true;
+    }
+
+    fn error(&mut self, code: &'static str, message: &str) {
+        self.diagnostics
+            .push(Diagnostic::error(self.line, message).with_code(code));
+    }
+
+    // At the end of the source, with a label where `what` starts
+    fn unterminated(&mut self, code: &'static str, message: &str, opened: Span, what: &str) {
+        let line = self.line
+            - self.source[opened.start..self.current]
+                .matches('\n')
+                .count();
+        // no line in the message, the label has it and moves with edits
+        let label = format!("{} opened here", what);
+        self.diagnostics.push(
+            Diagnostic::error(self.line, message)
+                .with_code(code)
+                .with_span(Span::new(self.current, self.current))
+                .with_label(line, opened, &label),
+        );
+    }
+
+    // `offset` is a byte offset into the source, `None` if it isn't on a character boundary
+    fn char_at(&mut self, offset: usize) -> Option<char> {
+        return self
+            .source
+            .get(offset..)
+            .and_then(|rest| rest.chars().next());
+    }
+
+    fn nth_char_from_current(&mut self, n: usize) -> Option<char> {
+        return self
+            .source
+            .get(self.current..)
+            .and_then(|rest| rest.chars().nth(n));
+    }
+}
+
+/// The literal of `token`, found in its lexeme in `source` the way the scanner found it
+pub(crate) fn literal<'a>(token: &Token, source: &'a str) -> Option<Literal<'a>> {
+    let text = token.lexeme(source);
+    let literal = match token.kind {
+        TokenType::TextToken => Literal::Str(without_cr(text)),
+        TokenType::Entity => Literal::Str(Cow::Owned(decode_character_reference(text)?)),
+        TokenType::OpeningTagStart => Literal::Str(Cow::Borrowed(text.get(1..)?)),
+        TokenType::BooleanAttribute => Literal::Str(Cow::Borrowed(text)),
+        TokenType::DynamicTagStart => {
+            // trim the `<{` and `}`
+            let inner = text.get(2..)?;
+            Literal::Expr(inner.strip_suffix('}').unwrap_or(inner).trim())
+        }
+        TokenType::ClosingTag => {
+            let inner = text.get(2..)?;
+            let value = inner.strip_suffix('>').unwrap_or(inner).trim();
+            match value.strip_prefix('{') {
+                // `</{ foo }>` closes `<{foo}>`
+                Some(expression) => {
+                    let expression = expression.strip_suffix('}').unwrap_or(expression).trim();
+                    Literal::Str(Cow::Owned(format!("{{{}}}", expression)))
+                }
+                None => Literal::Str(Cow::Borrowed(value)),
+            }
+        }
+        TokenType::CData => {
+            let content = text.get("<![CDATA[".len()..)?;
+            Literal::Str(Cow::Borrowed(
+                content.strip_suffix("]]>").unwrap_or(content),
+            ))
+        }
+        TokenType::CodeBlock => {
+            // up to the closing fence, if there's one
+            let code = text.get(3..)?;
+            Literal::CodeBlock(code.find("---").map_or(code, |end| &code[..end]))
+        }
+        TokenType::Expression => {
+            // after `(\`...\`)` the expression goes on without a `{`
+            let resumed = source[..token.span.start].ends_with("`)");
+            let code = match resumed {
+                true => text,
+                false => text.get(1..)?,
+            };
+            expression_literal(without_last_char(code))
+        }
+        TokenType::SpreadAttribute | TokenType::ShorthandAttribute => {
+            // trim the `{`, `...` and `}`
+            let inner = text.get(1..)?;
+            Literal::Expr(
+                inner
+                    .trim_end_matches('}')
+                    .trim()
+                    .trim_start_matches("...")
+                    .trim(),
+            )
+        }
+        TokenType::ClientDirective => match text.split_once('=') {
+            Some((name, value)) => client_directive(name.trim_end(), Some(value.trim_start()))?,
+            None => client_directive(text, None)?,
+        },
+        TokenType::OpeningTagEnd
+        | TokenType::SelfClosingTagEnd
+        | TokenType::FragmentOpen
+        | TokenType::FragmentClose
+        | TokenType::HTMLExprStart
+        | TokenType::HTMLExprEnd
+        | TokenType::EOF => return None,
+    };
+    return Some(literal);
+}
+
+// `{42}`, `{true}` and `{null}` are values already, anything else is code
+fn expression_literal(code: &str) -> Literal<'_> {
+    let mut tokens = expr::Lexer::new(code);
+    return match (tokens.next(), tokens.next()) {
+        (
+            Some(expr::Token {
+                literal: Some(literal),
+                ..
+            }),
+            None,
+        ) => literal,
+        _ => Literal::Expr(code),
+    };
+}
+
+// The literal of a `client:*` directive, given the attribute's name and its value as written
+fn client_directive<'a>(name: &'a str, value: Option<&'a str>) -> Option<Literal<'a>> {
+    let directive = name.strip_prefix("client:")?;
+    let value = value.filter(|value| !value.is_empty()).map(|value| {
+        let unquoted = match value.chars().next() {
+            Some(quote @ ('"' | '\'')) => value[1..].strip_suffix(quote).unwrap_or(&value[1..]),
+            _ => value,
+        };
+        return without_cr(unquoted);
+    });
+
+    return Some(Literal::Directive {
+        namespace: "client",
+        name: directive,
+        value,
+    });
+}
+
+// The length of the string literal or comment at the start of `code`, `None` if there's none
+// or it isn't closed. `'` and `"` strings end at the line, `//` comments at the line or at
+// `</script`, like `foo() // done</script>`.
+fn js_string_or_comment_length(code: &str) -> Option<usize> {
+    if let Some(comment) = code.strip_prefix("//") {
+        let line = &comment[..comment.find('\n').unwrap_or(comment.len())];
+        return Some(2 + line.find("</").unwrap_or(line.len()));
+    }
+    if let Some(comment) = code.strip_prefix("/*") {
+        return comment.find("*/").map(|end| 2 + end + 2);
+    }
+
+    let quote = code
+        .chars()
+        .next()
+        .filter(|c| matches!(c, '"' | '\'' | '`'))?;
+    let mut escaped = false;
+    for (i, c) in code.char_indices().skip(1) {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '\n' if quote != '`' => return None,
+            _ if c == quote => return Some(i + 1),
+            _ => {}
+        }
+    }
+    return None;
+}
+
+// Text as it's rendered, with `\n` line endings even in files with `\r\n`. The lexeme keeps
+// the `\r`, it's the source.
+fn without_cr(text: &str) -> Cow<'_, str> {
+    if !text.contains("\r\n") {
+        return Cow::Borrowed(text);
+    }
+    return Cow::Owned(text.replace("\r\n", "\n"));
+}
+
+// `text` without its last character, which closes it or is the one before `(\``
+fn without_last_char(text: &str) -> &str {
+    let mut chars = text.chars();
+    chars.next_back();
+    return chars.as_str();
+}
+
+// The scanner handles a `<tag` the same way inside or outside of a tag, and is in a tag
+// after it, so it's in the same state after every one of them
+fn is_tag_start(token: &Token) -> bool {
+    return matches!(
+        token.kind,
+        TokenType::OpeningTagStart | TokenType::DynamicTagStart
+    );
+}
+
+// Whether scanning from `Data` at `lexeme` starts a tag, inside a tag `<<` does too
+fn starts_tag_in_data(lexeme: &str) -> bool {
+    let mut chars = lexeme.chars().skip(1);
+    return chars
+        .next()
+        .is_some_and(|c| matches!(c, '/' | '>' | '{' | '!') || c.is_alphabetic());
+}
+
+/// Picks the offsets `scan_tokens_parallel` splits `source` at, including `0` and `source.len()`.
+///
+/// A chunk may only start at a `<` that begins a line and lies outside of code blocks (`---`),
+/// expressions (`{ }`) and the raw text of `<script>` and `<style>` elements.
+fn chunk_boundaries(source: &str, chunk_count: usize) -> Vec<usize> {
+    let bytes = source.as_bytes();
+    let chunk_size = source.len() / chunk_count.max(1);
+    let mut boundaries = vec![0];
+
+    let mut in_code_block = false;
+    let mut in_expression = false;
+    let mut html_expr_depth: usize = 0;
+    let mut raw_text_end: Option<&str> = None;
+    let mut at_line_start = true;
+
+    let mut i = 0;
+    while i < bytes.len() {
+        if !source.is_char_boundary(i) {
+            i += 1;
+            continue;
+        }
+
+        let rest = &source[i..];
+
+        if let Some(end_tag) = raw_text_end {
+            if rest.starts_with(end_tag) {
+                raw_text_end = None;
+            }
+        } else if in_code_block {
+            if rest.starts_with("---") {
+                in_code_block = false;
+                i += 3;
+                continue;
+            }
+        } else if in_expression {
+            if rest.starts_with("(`") {
+                in_expression = false;
+                html_expr_depth += 1;
+            } else if bytes[i] == b'}' {
+                in_expression = false;
+            }
+        } else if rest.starts_with("`)") {
+            // The scanner resumes the surrounding expression after every HTMLExprEnd
+            html_expr_depth = html_expr_depth.saturating_sub(1);
+            in_expression = true;
+        } else if rest.starts_with("---") {
+            in_code_block = true;
+            i += 3;
+            continue;
+        } else if bytes[i] == b'{' {
+            in_expression = true;
+        } else if bytes[i] == b'<' {
+            if rest.starts_with("<script") {
+                raw_text_end = Some("</script");
+            } else if rest.starts_with("<style") {
+                raw_text_end = Some("</style");
+            } else if rest.starts_with("<![CDATA[") {
+                raw_text_end = Some("]]>");
+            }
+
+            let last = *boundaries.last().unwrap();
+            if at_line_start && html_expr_depth == 0 && i > last && i - last >= chunk_size {
+                boundaries.push(i);
+            }
+        }
+
+        match bytes[i] {
+            b'\n' => at_line_start = true,
+            b' ' | b'\t' | b'\r' => {}
+            _ => at_line_start = false,
+        }
+
+        i += 1;
+    }
+
+    boundaries.push(source.len());
+    return boundaries;
 }
@@ -0,0 +1,162 @@
+// Diagnostics for the terminal, in the style of rustc: the message, the file and position
+// it's about, and the lines of the source with the spans underlined.
+//
+// error: Unterminated tag `<p`, expected `>`
+//  --> pages/index.regg:2:1
+//   |
+// 2 | <p {x
+//   | ^^^^^^
+//   |
+//   = help: add `>`
+
+use crate::diagnostic::{Diagnostic, Severity};
+use crate::line_index::{ColumnEncoding, LineIndex};
+use crate::span::Span;
+
+const RED: &str = "\x1b[1;31m";
+const YELLOW: &str = "\x1b[1;33m";
+const BLUE: &str = "\x1b[1;34m";
+const BOLD: &str = "\x1b[1m";
+const RESET: &str = "\x1b[0m";
+
+// Tabs are shown as this many spaces, so the underlines line up
+const TAB_WIDTH: usize = 4;
+
+/// Renders a diagnostic about `file`, with snippets of its source when it's known, in
+/// ANSI colors if `color`. Ends with an empty line to separate it from the next one.
+pub fn render(
+    diagnostic: &Diagnostic,
+    file: &str,
+    source: Option<(&str, &LineIndex)>,
+    color: bool,
+) -> String {
+    let paint = |style: &str, text: &str| match color {
+        true => format!("{}{}{}", style, text, RESET),
+        false => text.to_string(),
+    };
+
+    let style = match diagnostic.severity {
+        Severity::Error => RED,
+        Severity::Warning => YELLOW,
+    };
+    let severity = match diagnostic.code {
+        Some(code) => format!("{}[{}]", diagnostic.severity, code),
+        None => diagnostic.severity.to_string(),
+    };
+    let mut output = format!(
+        "{}{}\n",
+        paint(style, &severity),
+        paint(BOLD, &format!(": {}", diagnostic.message))
+    );
+
+    // the span knows better than `line`, which is where the scanner or parser noticed it
+    let primary = source
+        .zip(diagnostic.span)
+        .map(|((_, index), span)| position(index, span.start));
+    let line = primary.map_or(diagnostic.line, |(line, _)| line);
+    let labels: Vec<(usize, Span, &str)> = diagnostic
+        .labels
+        .iter()
+        .map(|label| {
+            let line = source.map_or(label.line, |(_, index)| position(index, label.span.start).0);
+            (line, label.span, label.message.as_str())
+        })
+        .collect();
+
+    let last_line = labels.iter().map(|(line, ..)| *line).fold(line, usize::max);
+    let width = last_line.to_string().len();
+    let gutter = |number: &str| paint(BLUE, &format!("{:>width$} |", number));
+
+    let column = primary.map_or(String::new(), |(_, column)| format!(":{}", column));
+    output += &format!(
+        "{}{} {}:{}{}\n",
+        " ".repeat(width),
+        paint(BLUE, "-->"),
+        file,
+        line,
+        column
+    );
+
+    if let Some((source, index)) = source {
+        let mut snippets = vec![(line, diagnostic.span, '^', style, "")];
+        for (line, span, message) in &labels {
+            snippets.push((*line, Some(*span), '-', BLUE, message));
+        }
+
+        // a label on the line just shown is underlined below it
+        let mut shown = None;
+        for (line, span, marker, style, message) in snippets {
+            let Some((line_start, text)) = line_at(source, index, line) else {
+                continue;
+            };
+            if shown != Some(line) {
+                output += &format!("{}\n", gutter(""));
+                output += &format!("{} {}\n", gutter(&line.to_string()), expand_tabs(text));
+                shown = Some(line);
+            }
+
+            if let Some(span) = span {
+                let (start, length) = underline(source, line_start, text, span);
+                let mut underline = marker.to_string().repeat(length);
+                if !message.is_empty() {
+                    underline += &format!(" {}", message);
+                }
+                output += &format!(
+                    "{} {}{}\n",
+                    gutter(""),
+                    " ".repeat(start),
+                    paint(style, &underline)
+                );
+            }
+        }
+    }
+
+    if let Some(help) = &diagnostic.help {
+        output += &format!("{}\n", gutter(""));
+        output += &format!(
+            "{} {} help: {}\n",
+            " ".repeat(width),
+            paint(BLUE, "="),
+            help
+        );
+    }
+
+    output += "\n";
+    return output;
+}
+
+// The line and column, both from 1, of a byte offset, the column counted in characters
+fn position(index: &LineIndex, offset: usize) -> (usize, usize) {
+    let position = index.line_column(offset, ColumnEncoding::Utf32);
+    return (position.line + 1, position.column + 1);
+}
+
+// Where a line, counted from 1, starts in `source`, and its text without the line break
+fn line_at<'a>(source: &'a str, index: &LineIndex, line: usize) -> Option<(usize, &'a str)> {
+    let start = index.line_start(line.checked_sub(1)?)?;
+    let end = index.line_end(line - 1)?;
+    return Some((start, source[start..end].trim_end_matches('\r')));
+}
+
+// Where the underline of `span` starts in the shown `line`, and how long it is. A span that
+// goes on past the end of the line is underlined to the end of it.
+fn underline(source: &str, line_start: usize, line: &str, span: Span) -> (usize, usize) {
+    let start = floor_char_boundary(source, span.start).clamp(line_start, line_start + line.len());
+    let end = floor_char_boundary(source, span.end).clamp(start, line_start + line.len());
+
+    let column = expand_tabs(&source[line_start..start]).chars().count();
+    let length = expand_tabs(&source[start..end]).chars().count();
+    return (column, length.max(1));
+}
+
+fn expand_tabs(text: &str) -> String {
+    return text.replace('\t', &" ".repeat(TAB_WIDTH));
+}
+
+fn floor_char_boundary(source: &str, offset: usize) -> usize {
+    let mut offset = offset.min(source.len());
+    while !source.is_char_boundary(offset) {
+        offset -= 1;
+    }
+    return offset;
+}
@@ -0,0 +1,26 @@
+use std::fmt;
+
+/// A range of byte offsets into the source, `end` is exclusive
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize) -> Self {
+        Self { start, end }
+    }
+
+    /// The smallest span covering both `self` and `other`
+    pub fn to(&self, other: Span) -> Span {
+        return Span::new(self.start.min(other.start), self.end.max(other.end));
+    }
+}
+
+// `4..10`, like the range of the bytes
+impl fmt::Display for Span {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}..{}", self.start, self.end)
+    }
+}
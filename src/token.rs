@@ -1,34 +1,314 @@
+use std::{borrow::Cow, fmt, ops::Deref, slice, sync::Arc};
+
+use serde_json::{json, Value};
+
+use crate::scanner;
+use crate::span::Span;
 use crate::token_type::TokenType;
 
-#[derive(Debug)]
+/// The value of a token, so it doesn't have to be parsed again out of the lexeme. It borrows
+/// from the source where it can.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Literal<'a> {
+    Str(Cow<'a, str>),  // tag names, text and attributes, `class="foo"` as a whole
+    Number(f64),        // `{42}`, `{1.5e3}`
+    Bool(bool),         // `{true}`, `{false}`
+    Null,               // `{null}`
+    CodeBlock(&'a str), // the frontmatter between the fences
+    Expr(&'a str),      // `{foo}`, `<{foo}>`, `{...foo}` without the braces and dots
+    Directive {
+        namespace: &'a str,          // `client`
+        name: &'a str,               // `load`
+        value: Option<Cow<'a, str>>, // without the quotes, `None` if it's an expression or there's none
+    },
+}
+
+impl<'a> Literal<'a> {
+    /// The text of anything but a number, a boolean, `null` or a directive
+    pub fn as_str(&self) -> Option<&str> {
+        return match self {
+            Literal::Str(text) => Some(text),
+            Literal::CodeBlock(text) | Literal::Expr(text) => Some(text),
+            Literal::Number(_) | Literal::Bool(_) | Literal::Null | Literal::Directive { .. } => {
+                None
+            }
+        };
+    }
+
+    /// Like `as_str`, borrowing from the source instead of the literal
+    pub fn into_str(self) -> Option<Cow<'a, str>> {
+        return match self {
+            Literal::Str(text) => Some(text),
+            Literal::CodeBlock(text) | Literal::Expr(text) => Some(Cow::Borrowed(text)),
+            Literal::Number(_) | Literal::Bool(_) | Literal::Null | Literal::Directive { .. } => {
+                None
+            }
+        };
+    }
+
+    pub fn to_json(&self) -> Value {
+        return match self {
+            Literal::Str(text) => json!(text),
+            Literal::CodeBlock(text) | Literal::Expr(text) => json!(text),
+            Literal::Number(number) => json!(number),
+            Literal::Bool(boolean) => json!(boolean),
+            Literal::Null => Value::Null,
+            Literal::Directive {
+                namespace,
+                name,
+                value,
+            } => json!({ "namespace": namespace, "name": name, "value": value }),
+        };
+    }
+}
+
+/// A token, where it is and what it is. Its text isn't copied out of the source, `lexeme`
+/// and `literal` read it from there when they're asked for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct Token {
-    pub token_type: TokenType,
-    pub lexeme: String,
-    pub literal: Option<String>,
-    pub line: usize,
+    pub kind: TokenType,
+    pub span: Span,
+    pub line: u32,
 }
 
 impl Token {
-    pub fn new(
-        token_type: TokenType,
-        lexeme: String,
-        literal: Option<String>,
-        line: usize,
-    ) -> Self {
+    pub fn new(kind: TokenType, span: Span, line: usize) -> Self {
         Self {
-            token_type,
-            lexeme,
-            literal,
-            line,
+            kind,
+            span,
+            line: line as u32,
         }
     }
 
-    pub fn to_string(&mut self) -> String {
-        match &self.literal {
-            Some(literal) => {
-                return self.token_type.to_string() + " " + &self.lexeme[..] + " " + &literal[..]
-            }
-            None => return self.token_type.to_string() + " " + &self.lexeme[..] + " " + "NULL",
+    /// The line the token ends on
+    pub fn line(&self) -> usize {
+        return self.line as usize;
+    }
+
+    /// The token as written in `source`, the source it was scanned from
+    pub fn lexeme<'a>(&self, source: &'a str) -> &'a str {
+        return source
+            .get(self.span.start..self.span.end)
+            .unwrap_or_default();
+    }
+
+    /// The value of the token, `None` for punctuation like `>`
+    pub fn literal<'a>(&self, source: &'a str) -> Option<Literal<'a>> {
+        return scanner::literal(self, source);
+    }
+
+    /// The text of the literal, `None` if there's none or it's a number, a boolean or `null`
+    pub fn literal_str<'a>(&self, source: &'a str) -> Option<Cow<'a, str>> {
+        return self.literal(source).and_then(Literal::into_str);
+    }
+
+    /// The literal as text, numbers and booleans too, and empty if there's none
+    pub fn literal_string(&self, source: &str) -> String {
+        return self
+            .literal(source)
+            .as_ref()
+            .map(Literal::to_string)
+            .unwrap_or_default();
+    }
+
+    /// The token as a JSON object
+    pub fn to_json(&self, source: &str) -> Value {
+        return json!({
+            "type": self.kind.to_string(),
+            "lexeme": self.lexeme(source),
+            "literal": self.literal(source).as_ref().map(Literal::to_json),
+            "line": self.line,
+            "span": { "start": self.span.start, "end": self.span.end },
+        });
+    }
+
+    /// The token as `OpeningTagStart <div div`, like `Display` with its source
+    pub fn display<'a>(&'a self, source: &'a str) -> impl fmt::Display + 'a {
+        return DisplayToken {
+            token: self,
+            source,
+        };
+    }
+}
+
+struct DisplayToken<'a> {
+    token: &'a Token,
+    source: &'a str,
+}
+
+/// The tokens of a source in source order, ending with `EOF`. Derefs to a slice of them.
+///
+/// The stream shares the source the tokens were scanned from, `lexeme`, `literal` and
+/// `trivia` read the text of a token from it.
+#[derive(Debug, Default)]
+pub struct TokenStream {
+    source: Arc<String>,
+    tokens: Vec<Token>,
+}
+
+impl TokenStream {
+    pub fn new(source: &str, tokens: Vec<Token>) -> Self {
+        return Self::shared(Arc::new(source.to_string()), tokens);
+    }
+
+    pub(crate) fn shared(source: Arc<String>, tokens: Vec<Token>) -> Self {
+        Self { source, tokens }
+    }
+
+    /// The source the tokens were scanned from
+    pub fn source(&self) -> &str {
+        return &self.source;
+    }
+
+    /// The token as written, see `Token::lexeme`
+    pub fn lexeme(&self, token: &Token) -> &str {
+        return token.lexeme(&self.source);
+    }
+
+    /// The value of the token, see `Token::literal`
+    pub fn literal(&self, token: &Token) -> Option<Literal<'_>> {
+        return token.literal(&self.source);
+    }
+
+    /// The text of the literal of the token, see `Token::literal_str`
+    pub fn literal_str(&self, token: &Token) -> Option<Cow<'_, str>> {
+        return token.literal_str(&self.source);
+    }
+
+    /// The whitespace between the token at `index` and the one before it
+    pub fn trivia(&self, index: usize) -> &str {
+        let start = match index {
+            0 => 0,
+            _ => self.tokens[index - 1].span.end,
+        };
+        return self
+            .source
+            .get(start..self.tokens[index].span.start)
+            .unwrap_or_default();
+    }
+
+    /// The token as a JSON object, see `Token::to_json`
+    pub fn to_json(&self, token: &Token) -> Value {
+        return token.to_json(&self.source);
+    }
+
+    /// The token `offset` is in. Between two tokens it's the one before, like for a cursor
+    /// right after a tag name, and `None` in whitespace no token covers.
+    pub fn token_at(&self, offset: usize) -> Option<&Token> {
+        let index = self.tokens.partition_point(|token| token.span.end < offset);
+        return self
+            .tokens
+            .get(index)
+            .filter(|token| token.span.start <= offset && !matches!(token.kind, TokenType::EOF));
+    }
+
+    /// The tokens that overlap `span`, or that an empty `span` is in
+    pub fn tokens_in_range(&self, span: Span) -> &[Token] {
+        if span.start == span.end {
+            return self.token_at(span.start).map_or(&[], slice::from_ref);
+        }
+
+        let start = self
+            .tokens
+            .partition_point(|token| token.span.end <= span.start);
+        let end = self
+            .tokens
+            .partition_point(|token| token.span.start < span.end)
+            .max(start);
+        return &self.tokens[start..end];
+    }
+
+    /// The source the tokens were scanned from, byte for byte: every token's trivia then its
+    /// lexeme. The whitespace after the last token is the trivia of `EOF`.
+    pub fn reconstruct(&self) -> String {
+        let mut source = String::new();
+        for (index, token) in self.tokens.iter().enumerate() {
+            source.push_str(self.trivia(index));
+            source.push_str(self.lexeme(token));
+        }
+        return source;
+    }
+
+    pub(crate) fn push(&mut self, token: Token) {
+        self.tokens.push(token);
+    }
+
+    pub(crate) fn truncate(&mut self, len: usize) {
+        self.tokens.truncate(len);
+    }
+
+    // The tokens are scanned from `source`
+    pub(crate) fn set_source(&mut self, source: Arc<String>) {
+        self.source = source;
+    }
+}
+
+impl Deref for TokenStream {
+    type Target = [Token];
+
+    fn deref(&self) -> &[Token] {
+        return &self.tokens;
+    }
+}
+
+impl Extend<Token> for TokenStream {
+    fn extend<I: IntoIterator<Item = Token>>(&mut self, tokens: I) {
+        self.tokens.extend(tokens);
+    }
+}
+
+impl IntoIterator for TokenStream {
+    type Item = Token;
+    type IntoIter = std::vec::IntoIter<Token>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        return self.tokens.into_iter();
+    }
+}
+
+impl<'a> IntoIterator for &'a TokenStream {
+    type Item = &'a Token;
+    type IntoIter = slice::Iter<'a, Token>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        return self.tokens.iter();
+    }
+}
+
+// Numbers as JavaScript would print them, `1.5e3` is `1500`
+impl fmt::Display for Literal<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Literal::Str(text) => write!(f, "{}", text),
+            Literal::CodeBlock(text) | Literal::Expr(text) => write!(f, "{}", text),
+            Literal::Number(number) => write!(f, "{}", number),
+            Literal::Bool(boolean) => write!(f, "{}", boolean),
+            Literal::Null => write!(f, "null"),
+            Literal::Directive {
+                namespace,
+                name,
+                value,
+            } => match value {
+                Some(value) => write!(f, "{}:{}=\"{}\"", namespace, name, value),
+                None => write!(f, "{}:{}", namespace, name),
+            },
+        }
+    }
+}
+
+// `OpeningTagStart 0..4 on line 1`, `display` has the text of the token too
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {} on line {}", self.kind, self.span, self.line)
+    }
+}
+
+impl fmt::Display for DisplayToken<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (token, lexeme) = (self.token, self.token.lexeme(self.source));
+        match token.literal(self.source) {
+            Some(literal) => write!(f, "{} {} {}", token.kind, lexeme, literal),
+            None => write!(f, "{} {} NULL", token.kind, lexeme),
         }
     }
 }
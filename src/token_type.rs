@@ -4,29 +4,61 @@ use std::fmt;
 // Astro Compiler:  https://github.com/withastro/compiler/blob/5d3923f3802b411eb9072f274ffcb9c04f9d4be1/internal/token.go#L24
 // Lox:             https://github.com/munificent/craftinginterpreters/blob/01e6f5b8f3e5dfa65674c2f9cf4700d73ab41cf8/java/com/craftinginterpreters/lox/TokenType.java
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum TokenType {
     // Any Text nodes which can contain a JSExpression
     TextToken,
+    Entity, // `&amp;`, `&#39;` or `&#x27;` in text, the literal is the character
 
     /* HTML */
     OpeningTagStart,   // `<div` | `<span`
+    DynamicTagStart,   // `<{tagName}`, the tag name is an expression
     OpeningTagEnd,     // `>`
     ClosingTag,        // `</div>` | `</span>`
     SelfClosingTagEnd, //  />`
+    FragmentOpen,      // `<>`
+    FragmentClose,     // `</>`
+    CData,             // `<![CDATA[ ... ]]>`, the literal is the content as written
 
     /* Regg Specific */
-    Expression,    // Code Expression: anything between `{` and `}`
-    CodeBlock,     // Code Block: anything between two `---`
-    HTMLExprStart, // (`
-    HTMLExprEnd,   // `)
+    Expression,         // Code Expression: anything between `{` and `}`
+    CodeBlock,          // Code Block: anything between two `---`
+    HTMLExprStart,      // (`
+    HTMLExprEnd,        // `)
+    SpreadAttribute,    // {...props} inside an opening tag
+    ShorthandAttribute, // {title} inside an opening tag, short for title={title}
+    BooleanAttribute,   // `disabled` in <input disabled>
+    ClientDirective, // `client:load`, `client:media="..."` or `client:only=`, inside an opening tag
 
     EOF,
 }
 
+// The names are part of the JSON of tokens, they're spelled out so they stay the same
+// whatever `Debug` prints
 impl fmt::Display for TokenType {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{:?}", self)
+        let name = match self {
+            TokenType::TextToken => "TextToken",
+            TokenType::Entity => "Entity",
+            TokenType::OpeningTagStart => "OpeningTagStart",
+            TokenType::DynamicTagStart => "DynamicTagStart",
+            TokenType::OpeningTagEnd => "OpeningTagEnd",
+            TokenType::ClosingTag => "ClosingTag",
+            TokenType::SelfClosingTagEnd => "SelfClosingTagEnd",
+            TokenType::FragmentOpen => "FragmentOpen",
+            TokenType::FragmentClose => "FragmentClose",
+            TokenType::CData => "CData",
+            TokenType::Expression => "Expression",
+            TokenType::CodeBlock => "CodeBlock",
+            TokenType::HTMLExprStart => "HTMLExprStart",
+            TokenType::HTMLExprEnd => "HTMLExprEnd",
+            TokenType::SpreadAttribute => "SpreadAttribute",
+            TokenType::ShorthandAttribute => "ShorthandAttribute",
+            TokenType::BooleanAttribute => "BooleanAttribute",
+            TokenType::ClientDirective => "ClientDirective",
+            TokenType::EOF => "EOF",
+        };
+        write!(f, "{}", name)
     }
 }
 
@@ -43,15 +75,25 @@ impl fmt::Display for TokenType {
 
 /* Regg's Context Free Grammar */
 // Frontmatter -> CodeBlock
-// HTMLElement ->  OpeningTagStart TextNode* OpeningTagEnd (HTMLElement* | TextNode) (ClosingTag | SelfClosingTag)
+// HTMLElement ->  (OpeningTagStart | DynamicTagStart) TextNode* OpeningTagEnd (HTMLElement* | TextNode) (ClosingTag | SelfClosingTag)
+// Fragment -> FragmentOpen (HTMLElement* | TextNode) FragmentClose
 // TextNode -> Expression* (HTMLExprStart HTMLElement* HTMLExprEnd)* Expression*
 //
+// Entity               -> &bar;            ; bar = a name, #digits or #xhex
 // OpeningTagStart      -> <foo             ; foo = \[A-Za-z]\
+// DynamicTagStart      -> <{bar}           ; bar = \*\, closed by </{bar}>
 // OpeningTagEnd        -> >                ;
 // SelfClosingTag       -> />               ;
 // ClosingTag           -> </foo>           ; foo = \[A-Za-z]\
+// FragmentOpen         -> <>               ;
+// FragmentClose        -> </>              ;
+// CData                -> <![CDATA[bar]]>  ; bar = \*\
 //
 // CodeBlock            -> --- bar ---      ; bar = \*\
 // Expression           -> { bar }          ; bar = \*\
 // HTMLExprStart        -> (`               ;
 // HTMLExprEnd          -> `)               ;
+// SpreadAttribute      -> {...bar}         ; bar = \*\, only inside an opening tag
+// ShorthandAttribute   -> {bar}            ; bar = identifier, only inside an opening tag
+// BooleanAttribute     -> bar              ; bar = attribute name without `=`, only inside an opening tag
+// ClientDirective      -> client:bar="baz" ; bar = name, the value is optional, only inside an opening tag
@@ -0,0 +1,51 @@
+// WebAssembly bindings behind the `wasm` feature, for a playground in the browser: the
+// tokens, the tree and the diagnostics of a template as plain JavaScript objects.
+
+use serde::Serialize;
+use serde_json::{json, Value};
+use wasm_bindgen::prelude::*;
+
+use crate::ast::Node;
+use crate::parser::Parser;
+use crate::scanner::Scanner;
+
+/// `{ tokens, diagnostics }`, the scanner's tokens and errors
+#[wasm_bindgen]
+pub fn tokenize(source: &str) -> Result<JsValue, JsError> {
+    let mut scanner = Scanner::new(source);
+    let tokens = scanner.scan_tokens();
+    let tokens: Vec<Value> = tokens.iter().map(|token| tokens.to_json(token)).collect();
+    let diagnostics: Vec<Value> = scanner
+        .diagnostics()
+        .iter()
+        .map(|diagnostic| diagnostic.to_json())
+        .collect();
+
+    return to_js(json!({ "tokens": tokens, "diagnostics": diagnostics }));
+}
+
+/// `{ nodes, diagnostics }`, the tree and the errors and warnings of the scanner and parser
+#[wasm_bindgen]
+pub fn parse(source: &str) -> Result<JsValue, JsError> {
+    let mut scanner = Scanner::new(source);
+    let tokens = scanner.scan_tokens();
+    let mut parser = Parser::new(tokens);
+    let nodes: Vec<Value> = parser.parse().iter().map(Node::to_json).collect();
+    let parser_diagnostics = parser.diagnostics().clone();
+
+    let diagnostics: Vec<Value> = scanner
+        .diagnostics()
+        .iter()
+        .chain(&parser_diagnostics)
+        .map(|diagnostic| diagnostic.to_json())
+        .collect();
+
+    return to_js(json!({ "nodes": nodes, "diagnostics": diagnostics }));
+}
+
+// Objects become plain objects instead of `Map`s
+fn to_js(value: Value) -> Result<JsValue, JsError> {
+    return value
+        .serialize(&serde_wasm_bindgen::Serializer::json_compatible())
+        .map_err(|error| JsError::new(&error.to_string()));
+}
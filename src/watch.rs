@@ -0,0 +1,89 @@
+// `regg check --watch`: checks the files again every time they change on disk.
+
+use std::{
+    io::{self, Write},
+    path::{Path, PathBuf},
+    sync::mpsc,
+    time::Duration,
+};
+
+use notify::{EventKind, RecursiveMode, Watcher};
+
+use crate::diagnostic::ErrorFormat;
+use crate::Regg;
+
+// Editors often write a file in several steps, the events within this window are one change
+const DEBOUNCE: Duration = Duration::from_millis(100);
+
+/// Checks `paths`, then checks the files among them that changed every time something
+/// changes, clearing the screen in between. Only returns if the watcher fails.
+pub fn watch(regg: &mut Regg, paths: &[PathBuf]) -> notify::Result<()> {
+    let (sender, receiver) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(sender)?;
+    for path in paths {
+        watcher.watch(path, RecursiveMode::Recursive)?;
+    }
+
+    clear_screen(regg);
+    let code = regg.check(paths);
+    waiting(paths.len(), code);
+
+    while let Ok(event) = receiver.recv() {
+        let mut changed = Vec::new();
+        collect(regg, event?, &mut changed);
+        while let Ok(event) = receiver.recv_timeout(DEBOUNCE) {
+            collect(regg, event?, &mut changed);
+        }
+
+        if changed.is_empty() {
+            continue;
+        }
+
+        clear_screen(regg);
+        let code = regg.check(&changed);
+        waiting(changed.len(), code);
+    }
+
+    return Ok(());
+}
+
+// Adds the files Regg handles that were created or modified by `event`
+fn collect(regg: &Regg, event: notify::Event, changed: &mut Vec<PathBuf>) {
+    if !matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_)) {
+        return;
+    }
+
+    for path in event.paths {
+        if path.is_file() && regg.handles(&path) && !is_hidden(&path) && !changed.contains(&path) {
+            changed.push(path);
+        }
+    }
+}
+
+// e.g. the `.index.regg.swp` files some editors write next to the file
+fn is_hidden(path: &Path) -> bool {
+    return path
+        .file_name()
+        .is_some_and(|name| name.to_string_lossy().starts_with('.'));
+}
+
+fn clear_screen(regg: &Regg) {
+    if regg.error_format() == ErrorFormat::Json {
+        return; // escape codes would break the JSON lines
+    }
+    // diagnostics go to stderr, so that's the screen to clear
+    eprint!("\x1b[2J\x1b[H");
+    let _ = io::stderr().flush();
+}
+
+fn waiting(files: usize, code: i32) {
+    let status = if code == crate::exit_code::OK {
+        "ok"
+    } else {
+        "failed"
+    };
+    eprintln!(
+        "[watch] checked {} path(s): {}, waiting for changes...",
+        files, status
+    );
+}
@@ -0,0 +1,77 @@
+#![allow(clippy::needless_return)]
+
+use std::sync::{Arc, Mutex};
+
+use regg::builder::ReggBuilder;
+use regg::codes;
+use regg::diagnostic::Severity;
+use regg::Regg;
+
+// Runs `source` and gives what reached the error sink: the file, the severity and the code
+fn run(builder: ReggBuilder, source: &str) -> Vec<(String, Severity, &'static str)> {
+    let sunk = Arc::new(Mutex::new(Vec::new()));
+    let sink = Arc::clone(&sunk);
+    let mut regg = builder
+        .error_sink(move |file, diagnostic| {
+            let code = diagnostic.code.unwrap_or_default();
+            sink.lock()
+                .unwrap()
+                .push((file.to_string(), diagnostic.severity, code));
+        })
+        .build();
+    regg.set_print_tokens(false);
+    regg.run(source);
+
+    let sunk = sunk.lock().unwrap().clone();
+    return sunk;
+}
+
+#[test]
+fn diagnostics_go_to_the_error_sink() {
+    let sunk = run(Regg::builder().source_name("page.regg"), "<div>");
+    assert_eq!(
+        sunk,
+        [(
+            "page.regg".to_string(),
+            Severity::Warning,
+            codes::UNCLOSED_ELEMENT
+        )]
+    );
+
+    let sunk = run(Regg::builder(), "<div>");
+    assert_eq!(sunk[0].0, "<input>");
+}
+
+#[test]
+fn strict_mode_makes_warnings_errors() {
+    let sunk = run(Regg::builder().strict(true), "<div>");
+    assert_eq!(sunk[0].1, Severity::Error);
+}
+
+#[test]
+fn fail_fast_stops_at_the_first_error() {
+    let source = "<p></p></p></span>";
+    assert_eq!(run(Regg::builder(), source).len(), 2);
+    assert_eq!(run(Regg::builder().fail_fast(true), source).len(), 1);
+    assert_eq!(
+        run(Regg::builder().max_errors(Some(2)), &source.repeat(2)).len(),
+        2
+    );
+}
+
+#[test]
+fn unused_imports_are_warned_about() {
+    let source = "---\nimport Card from \"./Card.regg\";\n---\n<p></p>";
+    let sunk = run(Regg::builder(), source);
+    assert_eq!(
+        sunk,
+        [(
+            "<input>".to_string(),
+            Severity::Warning,
+            codes::UNUSED_IMPORT
+        )]
+    );
+
+    let source = "---\nimport Card from \"./Card.regg\";\n---\n<Card />";
+    assert!(run(Regg::builder(), source).is_empty());
+}
@@ -0,0 +1,56 @@
+#![allow(clippy::needless_return)]
+
+use regg::codes;
+use regg::declarations::unused_imports;
+use regg::scanner::Scanner;
+
+// The names the warnings point at, with their lines
+fn unused(source: &str) -> Vec<(String, usize)> {
+    let mut scanner = Scanner::new(source);
+    let tokens = scanner.scan_tokens();
+
+    return unused_imports(tokens)
+        .into_iter()
+        .map(|diagnostic| {
+            assert_eq!(diagnostic.code, Some(codes::UNUSED_IMPORT));
+            let span = diagnostic.span.unwrap();
+            (source[span.start..span.end].to_string(), diagnostic.line)
+        })
+        .collect();
+}
+
+#[test]
+fn imports_nothing_uses_are_reported() {
+    let source = "---
+import Card from \"./Card.regg\";
+import { format, parse as parseDate } from \"./dates.js\";
+import * as Icons from \"./icons\";
+---
+<p>{format(date)}</p>
+";
+    assert_eq!(
+        unused(source),
+        [
+            ("Card".to_string(), 2),
+            ("parseDate".to_string(), 3),
+            ("Icons".to_string(), 4),
+        ]
+    );
+}
+
+#[test]
+fn imports_used_anywhere_are_not_reported() {
+    let source = "---
+import Card from \"./Card.regg\";
+import * as Icons from \"./icons\";
+import Layout from \"./Layout.regg\";
+import { title } from \"./site.js\";
+import { link } from \"./links.js\";
+import { heading } from \"./tags.js\";
+import { format } from \"./dates.js\";
+const date = format(new Date());
+---
+<Layout><Card {title} {...link} /><Icons.Star /><{heading}>{date}</{heading}></Layout>
+";
+    assert!(unused(source).is_empty());
+}
@@ -0,0 +1,136 @@
+#![allow(clippy::needless_return)]
+
+// Conformance tests: every `tests/fixtures/**/*.regg` is scanned and parsed, and the tokens,
+// the tree and the diagnostics are compared with the `.expected.json` next to it.
+//
+//   cargo test --test fixtures                      check every fixture
+//   cargo test --test fixtures -- --bless           write the snapshots again
+//   cargo test --test fixtures -- attributes        only the fixtures whose path has `attributes`
+//
+// Review blessed snapshots like any other diff, that's the point.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use serde_json::{json, Value};
+
+use regg::ast::Node;
+use regg::parser::Parser;
+use regg::scanner::Scanner;
+
+fn main() -> ExitCode {
+    let arguments: Vec<String> = std::env::args().skip(1).collect();
+    let bless = arguments.iter().any(|argument| argument == "--bless");
+    let filters: Vec<&String> = arguments
+        .iter()
+        .filter(|argument| !argument.starts_with('-'))
+        .collect();
+
+    let root = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures");
+    let mut fixtures = Vec::new();
+    collect(&root, &mut fixtures);
+    fixtures.sort();
+
+    let mut passed = 0;
+    let mut failed = Vec::new();
+    for fixture in &fixtures {
+        let name = fixture
+            .strip_prefix(&root)
+            .unwrap_or(fixture)
+            .display()
+            .to_string();
+        if !filters.is_empty() && !filters.iter().any(|filter| name.contains(filter.as_str())) {
+            continue;
+        }
+
+        let source = fs::read_to_string(fixture).expect("failed to read fixture");
+        let actual = snapshot(&source);
+        let expected_path = fixture.with_extension("expected.json");
+
+        if bless {
+            fs::write(&expected_path, &actual).expect("failed to write snapshot");
+            println!("blessed {}", name);
+            passed += 1;
+            continue;
+        }
+
+        match fs::read_to_string(&expected_path) {
+            Ok(expected) if expected == actual => {
+                println!("ok      {}", name);
+                passed += 1;
+            }
+            Ok(expected) => {
+                println!("FAILED  {}", name);
+                print_difference(&expected, &actual);
+                failed.push(name);
+            }
+            Err(_) => {
+                println!("FAILED  {} (no snapshot, run with `-- --bless`)", name);
+                failed.push(name);
+            }
+        }
+    }
+
+    println!();
+    println!("{} passed, {} failed", passed, failed.len());
+    if failed.is_empty() {
+        return ExitCode::SUCCESS;
+    }
+    println!("run `cargo test --test fixtures -- --bless` to update the snapshots");
+    return ExitCode::FAILURE;
+}
+
+fn collect(directory: &Path, fixtures: &mut Vec<PathBuf>) {
+    let Ok(entries) = fs::read_dir(directory) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            collect(&path, fixtures);
+        } else if path
+            .extension()
+            .is_some_and(|extension| extension == "regg")
+        {
+            fixtures.push(path);
+        }
+    }
+}
+
+fn snapshot(source: &str) -> String {
+    let mut scanner = Scanner::new(source);
+    let tokens = scanner.scan_tokens();
+    let mut parser = Parser::new(tokens);
+    let nodes: Vec<Value> = parser.parse().iter().map(Node::to_json).collect();
+    let parser_diagnostics = parser.diagnostics().clone();
+    let tokens: Vec<Value> = tokens.iter().map(|token| tokens.to_json(token)).collect();
+    let diagnostics: Vec<Value> = scanner
+        .diagnostics()
+        .iter()
+        .chain(&parser_diagnostics)
+        .map(|diagnostic| diagnostic.to_json())
+        .collect();
+
+    let value = json!({ "tokens": tokens, "nodes": nodes, "diagnostics": diagnostics });
+    return serde_json::to_string_pretty(&value).expect("failed to serialize snapshot") + "\n";
+}
+
+// Only the first line that differs, the whole snapshot is rarely useful
+fn print_difference(expected: &str, actual: &str) {
+    let mut expected_lines = expected.lines();
+    let mut actual_lines = actual.lines();
+    let mut line = 1;
+    loop {
+        match (expected_lines.next(), actual_lines.next()) {
+            (Some(a), Some(b)) if a == b => line += 1,
+            (None, None) => return,
+            (a, b) => {
+                println!("  line {}", line);
+                println!("  - {}", a.unwrap_or("<end of file>"));
+                println!("  + {}", b.unwrap_or("<end of file>"));
+                return;
+            }
+        }
+    }
+}
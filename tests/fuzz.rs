@@ -0,0 +1,40 @@
+#![allow(clippy::needless_return)]
+
+// Inputs that crashed `fuzz_scan` or `fuzz_parse`, checked in under `fuzz/regressions`. Each
+// one is scanned and parsed the way the fuzz targets do it, whole and cut at every character.
+
+use std::fs;
+use std::path::Path;
+
+use regg::parser::Parser;
+use regg::scanner::Scanner;
+use regg::token_type::TokenType;
+
+fn scan_and_parse(source: &str) {
+    let mut scanner = Scanner::new(source);
+    let tokens = scanner.scan_tokens();
+    let eof = tokens.last().expect("expected an EOF token");
+    assert!(matches!(eof.kind, TokenType::EOF), "{:?}", source);
+
+    let mut parser = Parser::new(tokens);
+    parser.parse();
+}
+
+#[test]
+fn crash_corpus_does_not_panic() {
+    let directory = Path::new(env!("CARGO_MANIFEST_DIR")).join("fuzz/regressions");
+    let mut count = 0;
+    for entry in fs::read_dir(directory).expect("missing fuzz/regressions") {
+        let bytes = fs::read(entry.expect("failed to read entry").path()).unwrap();
+        // the fuzz targets skip invalid UTF-8 too
+        let Ok(source) = std::str::from_utf8(&bytes) else {
+            continue;
+        };
+        for (end, _) in source.char_indices() {
+            scan_and_parse(&source[..end]);
+        }
+        scan_and_parse(source);
+        count += 1;
+    }
+    assert!(count > 0, "no regressions found");
+}
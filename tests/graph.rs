@@ -0,0 +1,146 @@
+#![allow(clippy::needless_return)]
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use regg::graph::Graph;
+
+// A directory of templates, named after the test so tests can run at the same time
+fn templates(name: &str, files: &[(&str, &str)]) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("regg-graph-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    for (file, source) in files {
+        let path = dir.join(file);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, source).unwrap();
+    }
+    return dir;
+}
+
+fn is_template(path: &Path) -> bool {
+    return path
+        .extension()
+        .is_some_and(|extension| extension == "regg");
+}
+
+fn names(graph: &Graph, chain: &[usize]) -> Vec<String> {
+    return chain
+        .iter()
+        .map(|&file| {
+            graph.files[file]
+                .file_name()
+                .unwrap()
+                .to_string_lossy()
+                .to_string()
+        })
+        .collect();
+}
+
+#[test]
+fn import_cycles_are_found_with_their_chain() {
+    let dir = templates(
+        "cycle",
+        &[
+            ("A.regg", "---\nimport B from \"./B.regg\";\n---\n<B />\n"),
+            (
+                "B.regg",
+                "---\nimport C from \"./nested/C.regg\";\n---\n<C />\n",
+            ),
+            (
+                "nested/C.regg",
+                "---\nimport A from \"../A.regg\";\n---\n<A />\n",
+            ),
+        ],
+    );
+
+    let graph = Graph::build(&[dir.join("A.regg")], is_template);
+    assert_eq!(graph.files.len(), 3);
+
+    let chain = graph.cycle(0).expect("expected a cycle");
+    assert_eq!(
+        names(&graph, &chain),
+        ["A.regg", "B.regg", "C.regg", "A.regg"]
+    );
+
+    // the span is of the source string, in the importing template
+    let edge = graph.edge(chain[0], chain[1]).unwrap();
+    let source = fs::read_to_string(dir.join("A.regg")).unwrap();
+    assert_eq!(&source[edge.span.start..edge.span.end], "\"./B.regg\"");
+    assert_eq!(edge.line, 2);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn a_template_importing_itself_is_a_cycle() {
+    let dir = templates(
+        "self",
+        &[("A.regg", "---\nimport A from \"./A.regg\";\n---\n")],
+    );
+
+    let graph = Graph::build(&[dir.join("A.regg")], is_template);
+    let chain = graph.cycle(0).expect("expected a cycle");
+    assert_eq!(names(&graph, &chain), ["A.regg", "A.regg"]);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn shared_imports_are_not_cycles() {
+    let dir = templates(
+        "shared",
+        &[
+            ("Page.regg", "---\nimport A from \"./A.regg\";\nimport B from \"./B.regg\";\n---\n"),
+            ("A.regg", "---\nimport Button from \"./Button.regg\";\n---\n"),
+            ("B.regg", "---\nimport Button from \"./Button.regg\";\nimport x from \"lodash\";\nimport y from \"./y.js\";\n---\n"),
+            ("Button.regg", "<button />\n"),
+            ("y.js", "import Page from \"./Page.regg\";\n"),
+        ],
+    );
+
+    let graph = Graph::build(&[dir.join("Page.regg")], is_template);
+    assert_eq!(graph.files.len(), 4);
+    assert!((0..graph.files.len()).all(|file| graph.cycle(file).is_none()));
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn graphs_are_exported_as_dot_and_json() {
+    let dir = templates(
+        "export",
+        &[
+            (
+                "Page.regg",
+                "---\nimport Card from \"./Card.regg\";\nimport Again from \"./Card.regg\";\n---\n",
+            ),
+            (
+                "Card.regg",
+                "---\nimport Button from \"./Button.regg\";\n---\n",
+            ),
+            ("Button.regg", "<button />\n"),
+        ],
+    );
+
+    let graph = Graph::build(&[dir.join("Page.regg")], is_template);
+    assert_eq!(graph.dependencies(0), 2);
+    assert_eq!(graph.dependencies(2), 0);
+
+    let dot = graph.to_dot();
+    assert!(dot.starts_with("digraph regg {\n"));
+    assert!(dot.contains(&format!(
+        "0 [label=\"{}\"];",
+        dir.join("Page.regg").display()
+    )));
+    assert_eq!(dot.matches("0 -> 1;").count(), 1, "{}", dot);
+    assert!(dot.contains("1 -> 2;"));
+
+    let json = graph.to_json();
+    assert_eq!(json["files"][0]["imports"], 2);
+    assert_eq!(json["files"][0]["dependencies"], 2);
+    assert_eq!(json["imports"].as_array().unwrap().len(), 3);
+    assert_eq!(json["imports"][2]["line"], 2);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,51 @@
+#![allow(clippy::needless_return)]
+
+use regg::ast::{Attribute, Element, Node};
+use regg::intern::Interner;
+use regg::parser::Parser;
+use regg::scanner::Scanner;
+
+fn elements(nodes: &[Node]) -> Vec<&Element> {
+    let mut elements = Vec::new();
+    for node in nodes {
+        if let Node::Element(element) = node {
+            elements.push(element);
+            elements.extend(self::elements(&element.children));
+        }
+    }
+    return elements;
+}
+
+#[test]
+fn repeated_names_share_one_allocation() {
+    let mut scanner = Scanner::new("<div hidden><div hidden>text</div></div><p hidden />");
+    let tokens = scanner.scan_tokens();
+    let nodes = Parser::new(tokens).parse();
+    let elements = elements(&nodes);
+
+    assert_eq!(elements.len(), 3);
+    assert_eq!(elements[0].name.as_ptr(), elements[1].name.as_ptr());
+    let names: Vec<*const u8> = elements
+        .iter()
+        .map(|element| match &element.attributes[0] {
+            Attribute::Boolean(name) => name.as_ptr(),
+            attribute => panic!("expected a name, got {:?}", attribute),
+        })
+        .collect();
+    assert!(names.iter().all(|&name| name == names[0]));
+}
+
+#[test]
+fn long_strings_are_not_interned() {
+    let mut interner = Interner::new();
+    let long = "a".repeat(100);
+    assert_ne!(
+        interner.intern(&long).as_ptr(),
+        interner.intern(&long).as_ptr()
+    );
+    assert_eq!(
+        interner.intern("div").as_ptr(),
+        interner.intern("div").as_ptr()
+    );
+    assert_eq!(interner.len(), 1);
+}
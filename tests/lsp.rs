@@ -0,0 +1,66 @@
+#![cfg(not(target_arch = "wasm32"))]
+#![allow(clippy::needless_return)]
+
+use std::thread;
+
+use lsp_server::{Connection, ErrorCode, Message, Notification, Request, RequestId, Response};
+use serde_json::json;
+
+// Sends `request` and waits for its response, skipping the notifications before it
+fn send(client: &Connection, id: i32, method: &str, params: serde_json::Value) -> Response {
+    let request = Request::new(RequestId::from(id), method.to_string(), params);
+    client.sender.send(request.into()).unwrap();
+    loop {
+        match client.receiver.recv().unwrap() {
+            Message::Response(response) => return response,
+            _ => continue,
+        }
+    }
+}
+
+fn notify(client: &Connection, method: &str, params: serde_json::Value) {
+    let notification = Notification::new(method.to_string(), params);
+    client.sender.send(notification.into()).unwrap();
+}
+
+#[test]
+fn invalid_params_are_answered_without_stopping_the_server() {
+    let (server, client) = Connection::memory();
+    let serving = thread::spawn(move || regg::lsp::serve(&server).is_ok());
+
+    send(&client, 1, "initialize", json!({ "capabilities": {} }));
+    notify(&client, "initialized", json!({}));
+
+    notify(
+        &client,
+        "textDocument/didOpen",
+        json!({ "textDocument": 1 }),
+    );
+    let response = send(
+        &client,
+        2,
+        "textDocument/hover",
+        json!({ "position": "top" }),
+    );
+    assert_eq!(response.id, RequestId::from(2));
+    assert_eq!(
+        response.error.map(|error| error.code),
+        Some(ErrorCode::InvalidParams as i32)
+    );
+
+    let uri = "file:///page.regg";
+    let document = json!({ "uri": uri, "languageId": "regg", "version": 1, "text": "<p>" });
+    notify(
+        &client,
+        "textDocument/didOpen",
+        json!({ "textDocument": document }),
+    );
+    let params = json!({ "textDocument": { "uri": uri } });
+    let response = send(&client, 3, "textDocument/foldingRange", params);
+    assert_eq!(response.id, RequestId::from(3));
+    assert!(response.error.is_none());
+
+    send(&client, 4, "shutdown", json!(null));
+    notify(&client, "exit", json!(null));
+    assert!(serving.join().unwrap());
+}
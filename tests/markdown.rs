@@ -0,0 +1,43 @@
+#![cfg(feature = "markdown")]
+#![allow(clippy::needless_return)]
+
+use std::fs;
+use std::sync::{Arc, Mutex};
+
+use regg::codes;
+use regg::Regg;
+
+// The codes and lines of the diagnostics of a page
+fn check(name: &str, page: &str) -> Vec<(&'static str, usize)> {
+    let dir = std::env::temp_dir().join(format!("regg-markdown-{}-{}", name, std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("page.md");
+    fs::write(&path, page).unwrap();
+
+    let sunk = Arc::new(Mutex::new(Vec::new()));
+    let sink = Arc::clone(&sunk);
+    let mut regg = Regg::builder()
+        .error_sink(move |_, diagnostic| {
+            let code = diagnostic.code.unwrap_or_default();
+            sink.lock().unwrap().push((code, diagnostic.line));
+        })
+        .build();
+    regg.set_print_tokens(false);
+    regg.check(&[path]);
+    fs::remove_dir_all(&dir).unwrap();
+
+    let sunk = sunk.lock().unwrap().clone();
+    return sunk;
+}
+
+#[test]
+fn frontmatter_names_the_layout() {
+    let page = "---\ntitle: Hello\nlayout: ../missing/Post.regg\n---\n# Hello\n";
+    assert_eq!(check("lf", page), [(codes::MISSING_LAYOUT, 3)]);
+}
+
+#[test]
+fn frontmatter_with_crlf_line_endings() {
+    let page = "---\r\ntitle: Hello\r\nlayout: \"../missing/Post.regg\"\r\n---\r\n# Hello\r\n";
+    assert_eq!(check("crlf", page), [(codes::MISSING_LAYOUT, 3)]);
+}
@@ -0,0 +1,71 @@
+#![allow(clippy::needless_return)]
+
+// The parser goes one call deeper for every nested node, so however deep a template nests,
+// it has to stop at `MAX_DEPTH` with an error instead of overflowing the stack.
+
+use regg::ast::Node;
+use regg::codes;
+use regg::parser::{Parser, MAX_DEPTH};
+use regg::scanner::{Scanner, ScannerOptions};
+
+// The tree and the codes of the diagnostics, the scanner's limits are lifted so the parser
+// sees all of the nesting
+fn parse(source: &str, max_depth: usize) -> (Vec<Node>, Vec<&'static str>) {
+    let mut scanner = Scanner::new(source).with_options(ScannerOptions::unlimited());
+    let tokens = scanner.scan_tokens();
+    let mut parser = Parser::new(tokens).with_max_depth(max_depth);
+    let nodes = parser.parse();
+    let codes = parser
+        .diagnostics()
+        .iter()
+        .filter_map(|diagnostic| diagnostic.code)
+        .collect();
+    return (nodes, codes);
+}
+
+fn depth(nodes: &[Node]) -> usize {
+    let mut depth = 0;
+    let mut nodes = nodes;
+    while let Some(children) = nodes.iter().find_map(|node| match node {
+        Node::Element(element) => Some(&element.children),
+        Node::Fragment(children) | Node::HTMLExpr(children) => Some(children),
+        _ => None,
+    }) {
+        depth += 1;
+        nodes = children;
+    }
+    return depth;
+}
+
+#[test]
+fn deep_nesting_is_cut_off_with_an_error() {
+    let nested = 100_000;
+    let source = format!(
+        "{}text{}<p>after</p>",
+        "<div>".repeat(nested),
+        "</div>".repeat(nested)
+    );
+
+    let (nodes, codes) = parse(&source, MAX_DEPTH);
+    assert_eq!(codes, [codes::NESTING_TOO_DEEP]);
+    assert_eq!(depth(&nodes), MAX_DEPTH + 1);
+
+    // the closing tags after the skipped content still close their elements
+    assert_eq!(nodes.len(), 2);
+    assert!(matches!(&nodes[1], Node::Element(element) if element.name == "p"));
+}
+
+#[test]
+fn every_kind_of_node_counts_towards_the_depth() {
+    let source = "<><div>{#if flag(\"beta\")}<section>deep</section>{/if}</div></>";
+    let (nodes, codes) = parse(source, 3);
+    assert_eq!(codes, [codes::UNDEFINED_FLAG, codes::NESTING_TOO_DEEP]);
+    assert_eq!(depth(&nodes), 2);
+
+    let (nodes, codes) = parse("<ul>{items.map(item => (`<li>{item}</li>`))}</ul>", 2);
+    assert_eq!(codes, [codes::NESTING_TOO_DEEP]);
+    assert_eq!(depth(&nodes), 3);
+
+    let (_, codes) = parse(source, 4);
+    assert_eq!(codes, [codes::UNDEFINED_FLAG]);
+}
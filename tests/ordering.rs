@@ -0,0 +1,146 @@
+#![allow(clippy::needless_return)]
+
+// Builds have to be reproducible: attributes stay in source order and the same template
+// always gives the same tokens, tree and formatting, so page diffs show real changes only.
+
+use regg::ast::{Attribute, Node};
+use regg::format::format;
+use regg::parser::Parser;
+use regg::scanner::Scanner;
+
+// The attributes of the first element
+fn attribute_names(source: &str) -> Vec<String> {
+    let mut scanner = Scanner::new(source);
+    let tokens = scanner.scan_tokens();
+    let mut parser = Parser::new(tokens);
+    let nodes = parser.parse();
+
+    let element = nodes
+        .into_iter()
+        .find_map(|node| match node {
+            Node::Element(element) => Some(element),
+            _ => None,
+        })
+        .expect("expected an element");
+    return element
+        .attributes
+        .iter()
+        .map(|attribute| match attribute {
+            Attribute::Text(text) => format!("text {}", text),
+            Attribute::Expression(code) => format!("expression {}", code),
+            Attribute::Spread(code) => format!("spread {}", code),
+            Attribute::Shorthand(name) => format!("shorthand {}", name),
+            Attribute::Boolean(name) => format!("boolean {}", name),
+            Attribute::DataSet(code) => format!("data:set {}", code),
+            Attribute::SetHTML(code) => format!("set:html {}", code),
+            Attribute::TransitionName(name) => format!("transition:name {}", name),
+            Attribute::TransitionPersist(_) => "transition:persist".to_string(),
+            Attribute::Client(name, _) => format!("client:{}", name),
+        })
+        .collect();
+}
+
+#[test]
+fn attributes_keep_source_order() {
+    let names = attribute_names(
+        r#"<a id="home" {...rest} href={url} hidden {title} data:set={data} class="nav"></a>"#,
+    );
+
+    assert_eq!(
+        names,
+        [
+            "text id=\"home\"",
+            "spread rest",
+            "text href=",
+            "expression url",
+            "boolean hidden",
+            "shorthand title",
+            "data:set data",
+            "text class=\"nav\"",
+        ]
+    );
+}
+
+#[test]
+fn attributes_are_not_sorted() {
+    let names = attribute_names(r#"<div z="1" a="2" m="3"></div>"#);
+
+    assert_eq!(names, ["text z=\"1\"", "text a=\"2\"", "text m=\"3\""]);
+}
+
+#[test]
+fn formatting_keeps_attribute_order() {
+    let source = concat!(
+        r#"<img zeta="a-long-value-that-makes-the-tag-wrap" alpha="another-long-value" "#,
+        r#"{...props} mu={value} src="/image.png" />"#,
+        "\n"
+    );
+
+    let formatted = format(source).unwrap();
+    let order: Vec<&str> = formatted.lines().skip(1).map(str::trim).collect();
+    assert_eq!(
+        order,
+        [
+            r#"zeta="a-long-value-that-makes-the-tag-wrap""#,
+            r#"alpha="another-long-value""#,
+            "{...props}",
+            "mu={value}",
+            r#"src="/image.png""#,
+            "/>",
+        ]
+    );
+}
+
+#[test]
+fn head_content_stays_in_source_order() {
+    let source = concat!(
+        "<head>\n",
+        "  <script src=\"/b.js\"></script>\n",
+        "  <style>b { color: red; }</style>\n",
+        "  <script src=\"/a.js\"></script>\n",
+        "  <style>a { color: blue; }</style>\n",
+        "</head>\n",
+    );
+
+    assert_eq!(format(source).unwrap(), source);
+}
+
+#[test]
+fn same_template_gives_the_same_output() {
+    let source = concat!(
+        "---\nconst url = \"/\";\n---\n",
+        "<nav class=\"nav\" {...props} data:set={data}>\n",
+        "  <a href={url} transition:name=\"home\">Home</a>\n",
+        "</nav>\n",
+    );
+
+    let first = format!("{:?}", attribute_names(source));
+    let formatted = format(source).unwrap();
+    for _ in 0..10 {
+        assert_eq!(format!("{:?}", attribute_names(source)), first);
+        assert_eq!(format(source).unwrap(), formatted);
+    }
+}
+
+#[test]
+fn parallel_scanning_gives_the_same_tokens() {
+    let source = "<section class=\"card\" {...props}>\n  <h2 id={id}>{title}</h2>\n</section>\n"
+        .repeat(2000);
+
+    let mut scanner = Scanner::new(&source);
+    let sequential: Vec<String> = scanner
+        .scan_tokens()
+        .iter()
+        .map(|token| token.to_string())
+        .collect();
+
+    for chunk_count in [2, 3, 8] {
+        let mut scanner = Scanner::new(&source);
+        let parallel: Vec<String> = scanner
+            .scan_tokens_parallel(chunk_count)
+            .iter()
+            .map(|token| token.to_string())
+            .collect();
+        assert_eq!(parallel, sequential);
+    }
+}
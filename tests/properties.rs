@@ -0,0 +1,325 @@
+#![allow(clippy::needless_return)]
+
+// Properties of the scanner on generated templates, well-formed or not: it never panics,
+// the lexemes and the whitespace between them are the source, and line numbers only grow.
+// Scanning in chunks gives the same tokens and errors as scanning in one go. After an edit, scanning and parsing again incrementally gives what scanning and parsing
+// the edited source does.
+
+use proptest::prelude::*;
+use proptest::sample::{select, Index};
+
+use regg::parser::Parser;
+use regg::scanner::{Scanner, TextEdit};
+use regg::span::Span;
+use regg::token::Token;
+use regg::token_type::TokenType;
+
+// Pieces of templates, most of them only make sense next to others
+const PIECES: &[&str] = &[
+    "<",
+    ">",
+    "</",
+    "/>",
+    "<>",
+    "</>",
+    "<{",
+    "{",
+    "}",
+    "{...",
+    "(`",
+    "`)",
+    "---",
+    "-",
+    "=",
+    "\"",
+    "'",
+    " ",
+    "\n",
+    "\t",
+    "\r\n",
+    "div",
+    "x",
+    "é",
+    "😀",
+    "class=\"a\"",
+    "{x}",
+    "<p>",
+    "</p>",
+    "&amp;",
+    "text ",
+    "<img>",
+    "</img>",
+    "<![CDATA[",
+    "]]>",
+    "<script>",
+    "</script>",
+    "<style>",
+    "</style>",
+];
+
+fn identifier() -> impl Strategy<Value = String> {
+    return "[a-z][a-zA-Z0-9]{0,6}";
+}
+
+fn text() -> impl Strategy<Value = String> {
+    return "[a-zA-Z0-9 ,.!?é😀\n]{1,12}";
+}
+
+fn attribute() -> impl Strategy<Value = String> {
+    return prop_oneof![
+        identifier(),
+        (identifier(), "[a-z0-9 ]{0,8}")
+            .prop_map(|(name, value)| format!("{}=\"{}\"", name, value)),
+        (identifier(), identifier()).prop_map(|(name, value)| format!("{}={{{}}}", name, value)),
+        identifier().prop_map(|name| format!("{{...{}}}", name)),
+        identifier().prop_map(|name| format!("{{{}}}", name)),
+    ];
+}
+
+fn node() -> impl Strategy<Value = String> {
+    let leaf = prop_oneof![
+        text(),
+        identifier().prop_map(|name| format!("{{{}}}", name)),
+        identifier().prop_map(|name| format!("<{} />", name)),
+        text().prop_map(|text| format!("<script>{}</script>", text)),
+        text().prop_map(|text| format!("<style>{}</style>", text)),
+        text().prop_map(|text| format!("<![CDATA[{}]]>", text)),
+    ];
+    return leaf.prop_recursive(4, 48, 4, |inner| {
+        prop_oneof![
+            (
+                identifier(),
+                prop::collection::vec(attribute(), 0..3),
+                prop::collection::vec(inner.clone(), 0..4),
+            )
+                .prop_map(|(name, attributes, children)| {
+                    let attributes: String = attributes
+                        .iter()
+                        .map(|attribute| format!(" {}", attribute))
+                        .collect();
+                    format!("<{}{}>{}</{}>", name, attributes, children.concat(), name)
+                }),
+            prop::collection::vec(inner.clone(), 0..4)
+                .prop_map(|children| format!("<>{}</>", children.concat())),
+            // children of a void element, an error
+            (
+                select(&["img", "br"][..]),
+                prop::collection::vec(inner.clone(), 0..3)
+            )
+                .prop_map(|(name, children)| format!(
+                    "<{}>{}</{}>",
+                    name,
+                    children.concat(),
+                    name
+                )),
+            (identifier(), prop::collection::vec(inner, 0..3)).prop_map(|(name, children)| {
+                format!("{{{}.map(item => (`{}`))}}", name, children.concat())
+            }),
+        ]
+    });
+}
+
+fn template() -> impl Strategy<Value = String> {
+    let frontmatter = (identifier(), 0..100)
+        .prop_map(|(name, value)| format!("---\nconst {} = {};\n---\n", name, value));
+    return (
+        prop::option::of(frontmatter),
+        prop::collection::vec(node(), 0..6),
+    )
+        .prop_map(|(frontmatter, nodes)| frontmatter.unwrap_or_default() + &nodes.join("\n"));
+}
+
+fn malformed() -> impl Strategy<Value = String> {
+    return prop_oneof![
+        prop::collection::vec(select(PIECES), 0..40).prop_map(|pieces| pieces.concat()),
+        // a well-formed template cut anywhere
+        (template(), any::<Index>()).prop_map(|(source, index)| {
+            let ends: Vec<usize> = (0..=source.len())
+                .filter(|&end| source.is_char_boundary(end))
+                .collect();
+            source[..ends[index.index(ends.len())]].to_string()
+        }),
+        any::<String>(),
+    ];
+}
+
+fn scan(source: &str) -> (Vec<Token>, usize) {
+    let mut scanner = Scanner::new(source);
+    let tokens = scanner.scan_tokens().to_vec();
+    return (tokens, scanner.diagnostics().len());
+}
+
+fn describe(source: &str, tokens: &[Token]) -> Vec<String> {
+    return tokens
+        .iter()
+        .map(|token| format!("{} {} {}", token.display(source), token.span, token.line))
+        .collect();
+}
+
+// An edit: where it starts, how many chars it replaces and what with
+fn edit() -> impl Strategy<Value = (Index, usize, String)> {
+    return (
+        any::<Index>(),
+        prop_oneof![0..4usize, any::<usize>()],
+        prop_oneof![Just(String::new()), malformed()],
+    );
+}
+
+// Replaces up to `length` chars of `source` from a char boundary with up to 8 chars of `text`
+fn apply(source: &str, (start, length, text): (Index, usize, String)) -> (TextEdit, String) {
+    let ends: Vec<usize> = (0..=source.len())
+        .filter(|&end| source.is_char_boundary(end))
+        .collect();
+    let start = start.index(ends.len());
+    let end = start.saturating_add(length).min(ends.len() - 1);
+    let edit = TextEdit {
+        span: Span::new(ends[start], ends[end]),
+        text: text.chars().take(8).collect(),
+    };
+    let edited = format!(
+        "{}{}{}",
+        &source[..edit.span.start],
+        edit.text,
+        &source[edit.span.end..]
+    );
+    return (edit, edited);
+}
+
+fn check_tokens(source: &str, tokens: &[Token]) -> Result<(), TestCaseError> {
+    let eof = tokens.last().ok_or_else(|| TestCaseError::fail("no EOF"))?;
+    prop_assert!(matches!(eof.kind, TokenType::EOF));
+    prop_assert_eq!((eof.span.start, eof.span.end), (source.len(), source.len()));
+
+    let mut end = 0;
+    let mut line = 1;
+    for token in tokens {
+        // only whitespace is skipped
+        let gap = source.get(end..token.span.start);
+        prop_assert!(
+            gap.is_some_and(|gap| gap
+                .trim_start_matches(|c| c == '\u{feff}' && end == 0)
+                .chars()
+                .all(|c| matches!(c, ' ' | '\t' | '\r' | '\n'))),
+            "{:?} before {}",
+            gap,
+            token.display(source)
+        );
+        prop_assert_eq!(
+            source.get(token.span.start..token.span.end),
+            Some(token.lexeme(source))
+        );
+
+        prop_assert!(
+            token.line() >= line,
+            "line {} after line {}",
+            token.line,
+            line
+        );
+        let newlines = source[..token.span.end].matches('\n').count();
+        prop_assert_eq!(token.line(), 1 + newlines);
+
+        end = token.span.end;
+        line = token.line();
+    }
+    return Ok(());
+}
+
+proptest! {
+    #[test]
+    fn well_formed_templates_scan_without_errors(source in template()) {
+        let (tokens, diagnostics) = scan(&source);
+        check_tokens(&source, &tokens)?;
+        prop_assert_eq!(diagnostics, 0);
+    }
+
+    #[test]
+    fn malformed_templates_scan(source in malformed()) {
+        let (tokens, _) = scan(&source);
+        check_tokens(&source, &tokens)?;
+    }
+
+    #[test]
+    fn tokens_reconstruct_the_source(source in prop_oneof![template(), malformed()]) {
+        let mut scanner = Scanner::new(&source);
+        prop_assert_eq!(scanner.scan_tokens().reconstruct(), source.clone());
+
+        let mut scanner = Scanner::new(&source);
+        prop_assert_eq!(scanner.scan_tokens_parallel(4).reconstruct(), source);
+    }
+
+    #[test]
+    fn parallel_scans_like_scan_tokens(
+        source in prop_oneof![template(), malformed()],
+        chunks in 1..6usize,
+    ) {
+        let mut scanner = Scanner::new(&source);
+        let tokens = describe(&source, scanner.scan_tokens());
+        let diagnostics = format!("{:?}", scanner.diagnostics());
+
+        let mut scanner = Scanner::new(&source);
+        let parallel = describe(&source, scanner.scan_tokens_parallel(chunks));
+        prop_assert_eq!(parallel, tokens);
+        prop_assert_eq!(format!("{:?}", scanner.diagnostics()), diagnostics);
+    }
+
+    #[test]
+    fn relex_scans_like_scan_tokens(
+        source in prop_oneof![template(), malformed()],
+        edit in edit(),
+    ) {
+        let (edit, edited) = apply(&source, edit);
+
+        let mut scanner = Scanner::new(&source);
+        scanner.scan_tokens();
+        let old = scanner.into_tokens();
+        let relexed = Scanner::new(&edited).relex(old, &edit);
+        let (tokens, _) = scan(&edited);
+        prop_assert_eq!(describe(&edited, &relexed), describe(&edited, &tokens));
+        prop_assert_eq!(relexed.reconstruct(), edited);
+    }
+
+    #[test]
+    fn reparse_parses_like_parse(
+        source in prop_oneof![template(), malformed()],
+        edit in edit(),
+    ) {
+        let (edit, edited) = apply(&source, edit);
+        let (reparsed, parsed) = reparse(&source, &edit, &edited);
+        prop_assert_eq!(reparsed, parsed);
+    }
+}
+
+// The nodes and diagnostics of `edited`, parsed again after `edit` and from scratch
+fn reparse(source: &str, edit: &TextEdit, edited: &str) -> (String, String) {
+    let mut scanner = Scanner::new(source);
+    scanner.scan_tokens();
+    let old = scanner.into_tokens();
+    let previous = Parser::new(&old).parse_incremental();
+
+    let mut scanner = Scanner::new(edited);
+    let mut parser = Parser::new(scanner.scan_tokens());
+    let nodes = parser.parse();
+    let parsed = format!("{:?}\n{:?}", nodes, parser.diagnostics());
+
+    let mut scanner = Scanner::new(edited);
+    let relexed = scanner.relex(old, edit);
+    let Some(damage) = scanner.damage() else {
+        return (parsed.clone(), parsed); // nothing changed
+    };
+    let reparsed = Parser::new(&relexed).reparse(previous, damage);
+    return (
+        format!("{:?}\n{:?}", reparsed.nodes, reparsed.diagnostics),
+        parsed,
+    );
+}
+
+#[test]
+fn reparse_checks_closing_tags_of_void_elements_again() {
+    let source = "<img>t<b>x</b>y</img>z";
+    let edit = TextEdit {
+        span: Span::new(0, 1),
+        text: String::new(),
+    };
+    let (reparsed, parsed) = reparse(source, &edit, &source[1..]);
+    assert_eq!(reparsed, parsed);
+}
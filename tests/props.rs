@@ -0,0 +1,136 @@
+#![allow(clippy::needless_return)]
+
+use std::collections::HashMap;
+
+use regg::codes;
+use regg::props::{check_props, props, PropKind, Props};
+use regg::scanner::Scanner;
+
+#[test]
+fn interface_props_are_extracted() {
+    let code = r#"
+import Card from "./Card.regg";
+
+export interface Props {
+  title: string;
+  count?: number
+  readonly open: boolean,
+  variant:
+    | "primary"
+    | "secondary";
+  "aria-label"?: string;
+  subtitle: string | undefined;
+  items: Array<{ id: number; name: string }>;
+  onClick(event: MouseEvent): void;
+}
+
+const { title } = Astro.props;
+"#;
+    let props = props(code).expect("expected props");
+    let schema: Vec<(&str, bool, PropKind, &str)> = props
+        .props
+        .iter()
+        .map(|prop| {
+            (
+                prop.name.as_str(),
+                prop.optional,
+                prop.kind,
+                prop.type_text.as_str(),
+            )
+        })
+        .collect();
+
+    assert_eq!(
+        schema,
+        [
+            ("title", false, PropKind::String, "string"),
+            ("count", true, PropKind::Number, "number"),
+            ("open", false, PropKind::Boolean, "boolean"),
+            (
+                "variant",
+                false,
+                PropKind::String,
+                "| \"primary\"\n    | \"secondary\""
+            ),
+            ("aria-label", true, PropKind::String, "string"),
+            ("subtitle", true, PropKind::String, "string | undefined"),
+            (
+                "items",
+                false,
+                PropKind::Other,
+                "Array<{ id: number; name: string }>"
+            ),
+            ("onClick", false, PropKind::Other, ""),
+        ]
+    );
+    assert!(!props.rest);
+    assert_eq!(&code[props.span.start..props.span.end], "Props");
+    let title = props.get("title").unwrap();
+    assert_eq!(&code[title.span.start..title.span.end], "title");
+}
+
+#[test]
+fn type_alias_props_are_extracted() {
+    let props = props("type Props = { href: string; [key: string]: unknown };").unwrap();
+    assert_eq!(props.props.len(), 1);
+    assert_eq!(props.props[0].name, "href");
+    assert!(props.rest);
+}
+
+#[test]
+fn no_props_without_a_declaration() {
+    assert!(props("const title = 'Home';\ntype Other = { a: string };").is_none());
+    assert!(props("").is_none());
+}
+
+// The codes and the source they point at
+fn check(source: &str, components: &[(&str, &str)]) -> Vec<(String, String)> {
+    let components: HashMap<String, Props> = components
+        .iter()
+        .map(|(name, code)| (name.to_string(), props(code).unwrap()))
+        .collect();
+    let mut scanner = Scanner::new(source);
+    let tokens = scanner.scan_tokens();
+
+    return check_props(tokens, &components)
+        .into_iter()
+        .map(|diagnostic| {
+            let span = diagnostic.span.unwrap();
+            (
+                diagnostic.code.unwrap_or_default().to_string(),
+                source[span.start..span.end].to_string(),
+            )
+        })
+        .collect();
+}
+
+#[test]
+fn call_sites_are_checked_against_props() {
+    let card = "interface Props { title: string; count?: number; tags?: string[] }";
+    let found = check(
+        "<Card count=\"3\" tags=\"a\" titel=\"Hi\" />\n<Card title count={3} />",
+        &[("Card", card)],
+    );
+
+    let expected = [
+        (codes::PROP_TYPE_MISMATCH, "\"3\""),
+        (codes::PROP_TYPE_MISMATCH, "\"a\""),
+        (codes::UNKNOWN_PROP, "titel"),
+        (codes::MISSING_PROP, "<Card"),
+        (codes::PROP_TYPE_MISMATCH, "title"),
+    ];
+    let expected: Vec<(String, String)> = expected
+        .iter()
+        .map(|(code, text)| (code.to_string(), text.to_string()))
+        .collect();
+    assert_eq!(found, expected);
+}
+
+#[test]
+fn spreads_directives_and_index_signatures_are_not_reported() {
+    let strict = "interface Props { href: string }";
+    let open = "interface Props { href: string; [key: string]: unknown }";
+    assert!(check("<A {...link} client:load slot=\"x\" />", &[("A", strict)]).is_empty());
+    assert!(check("<B href={url} data-id=\"1\" />", &[("B", open)]).is_empty());
+    assert!(check("<C anything />", &[]).is_empty());
+}
@@ -0,0 +1,492 @@
+#![allow(clippy::needless_return)]
+
+// The scanner never panics: whatever it's given, truncated templates or random text, it
+// gives tokens ending with an EOF and reports what's wrong as diagnostics.
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use regg::codes;
+use regg::scanner::{Scanner, ScannerOptions, TextEdit};
+use regg::span::Span;
+use regg::token::{Literal, Token, TokenStream};
+use regg::token_type::TokenType;
+use regg::Regg;
+
+// Inputs that used to panic, found by fuzzing
+const REGRESSIONS: &[&str] = &[
+    "---",
+    "---\n",
+    "---\nconst a = 1;\n--",
+    "--- -",
+    "{",
+    "{é",
+    "{a}}",
+    "`)",
+    "<p>`)",
+    "</",
+    "</div",
+    "</{",
+    "<{",
+    "<{é",
+    "<a",
+    "<a b",
+    "<a b=",
+    "<a b='",
+    "<a b=\"é",
+    "<a {",
+    "<a {...",
+    "<a {b",
+    "<a {b}",
+    "(`",
+    "{items.map(x => (`",
+    "é{",
+];
+
+// A template with every kind of token, scanned cut at every character
+const TEMPLATE: &str = "---
+const title = \"Café\";
+const count = a - b;
+---
+<main class=\"home\" data-id={id}>
+  <h1>{title}</h1>
+  <Card {...props} {title} disabled />
+  <{tag} x='1'>ü</{tag}>
+  {items.map(item => (`<li>{item}</li>`))}
+  <>fragment</>
+  <br/>
+</main>
+";
+
+const ALPHABET: &[&str] = &[
+    "<", ">", "/", "{", "}", "(", ")", "`", "-", "=", "\"", "'", ".", " ", "\n", "\t", "a", "é",
+    "😀", "\0", "div", "...",
+];
+
+fn scan(source: &str) -> Vec<Token> {
+    let mut scanner = Scanner::new(source);
+    let tokens = scanner.scan_tokens().to_vec();
+
+    let eof = tokens.last().expect("expected an EOF token");
+    assert!(matches!(eof.kind, TokenType::EOF), "{:?}", source);
+    for token in &tokens {
+        assert!(token.span.start <= token.span.end, "{:?}", source);
+        assert!(source.is_char_boundary(token.span.start), "{:?}", source);
+        assert!(source.is_char_boundary(token.span.end), "{:?}", source);
+        assert!(token.span.end <= source.len(), "{:?}", source);
+    }
+    return tokens;
+}
+
+fn codes(source: &str) -> Vec<&'static str> {
+    let mut scanner = Scanner::new(source);
+    scanner.scan_tokens();
+    return scanner
+        .diagnostics()
+        .iter()
+        .filter_map(|diagnostic| diagnostic.code)
+        .collect();
+}
+
+#[test]
+fn regressions_do_not_panic() {
+    for source in REGRESSIONS {
+        scan(source);
+    }
+}
+
+#[test]
+fn truncated_templates_do_not_panic() {
+    for (end, _) in TEMPLATE.char_indices() {
+        scan(&TEMPLATE[..end]);
+    }
+}
+
+#[test]
+fn random_input_does_not_panic() {
+    // xorshift, the same inputs on every run
+    let mut state: u64 = 0x2545_f491_4f6c_dd1d;
+    let mut next = |bound: usize| {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        return (state % bound as u64) as usize;
+    };
+
+    for _ in 0..20_000 {
+        let length = next(24);
+        let source: String = (0..length)
+            .map(|_| ALPHABET[next(ALPHABET.len())])
+            .collect();
+        scan(&source);
+    }
+}
+
+#[test]
+fn truncated_input_is_reported() {
+    assert_eq!(codes("---"), [codes::UNTERMINATED_FRONTMATTER]);
+    assert_eq!(codes("{title"), [codes::UNTERMINATED_EXPRESSION]);
+    assert_eq!(codes("<{tag"), [codes::UNTERMINATED_DYNAMIC_TAG]);
+    assert_eq!(codes("<a b='c"), [codes::UNTERMINATED_ATTRIBUTE_VALUE]);
+    assert_eq!(
+        codes("<a {...b"),
+        [codes::UNTERMINATED_ATTRIBUTE_EXPRESSION]
+    );
+    assert_eq!(codes("</main"), [codes::UNTERMINATED_CLOSING_TAG]);
+    assert_eq!(codes("<![CDATA[ a"), [codes::UNTERMINATED_CDATA]);
+}
+
+#[test]
+fn unterminated_is_reported_where_it_opens() {
+    let source = "<main>\n  <p>\n    {title\n  </p>\n</main>\n";
+    let mut scanner = Scanner::new(source);
+    scanner.scan_tokens();
+    let diagnostic = &scanner.diagnostics()[0];
+
+    let span = diagnostic.span.expect("expected where scanning gave up");
+    assert_eq!((span.start, span.end), (source.len(), source.len()));
+    let label = &diagnostic.labels[0];
+    assert_eq!(label.line, 3);
+    assert_eq!(&source[label.span.start..label.span.end], "{");
+    assert_eq!(label.message, "expression opened here");
+}
+
+#[test]
+fn frontmatter_ends_at_the_fence() {
+    let source = "---\nconst count = a - b;\n---\n<p>{count}</p>";
+    let tokens = scan(source);
+    assert!(matches!(tokens[0].kind, TokenType::CodeBlock));
+    assert_eq!(
+        tokens[0].literal_str(source).as_deref(),
+        Some("\nconst count = a - b;\n")
+    );
+    assert!(matches!(tokens[1].kind, TokenType::OpeningTagStart));
+}
+
+#[test]
+fn tokens_reconstruct_the_source() {
+    let sources = [
+        TEMPLATE,
+        "",
+        "  \n\t",
+        "<p>\r\n  text  \r\n</p>\r\n\r\n",
+        "<a   href = \"x\"\n  {...b}   />",
+    ];
+    for source in sources.iter().chain(REGRESSIONS) {
+        let mut scanner = Scanner::new(source);
+        assert_eq!(scanner.scan_tokens().reconstruct(), *source);
+    }
+}
+
+#[test]
+fn expression_closed_at_the_end_is_not_reported() {
+    assert!(codes("<p>{title}").is_empty());
+}
+
+#[test]
+fn angle_brackets_and_slashes_in_text_are_text() {
+    let source = "<p>1 > 0, a < b and a/b</p>";
+    let tokens = scan(source);
+    assert!(matches!(tokens[2].kind, TokenType::TextToken));
+    assert_eq!(
+        tokens[2].literal_str(source).as_deref(),
+        Some("1 > 0, a < b and a/b")
+    );
+    assert!(matches!(tokens[3].kind, TokenType::ClosingTag));
+}
+
+#[test]
+fn script_and_style_content_is_raw_text() {
+    for (source, content) in [
+        (
+            "<script>if (a < b && c > d) { go(); }</script>",
+            "if (a < b && c > d) { go(); }",
+        ),
+        (
+            "<STYLE>a > b { color: red }</style >",
+            "a > b { color: red }",
+        ),
+        (
+            "<script>let a = '</scripts>';</script>",
+            "let a = '</scripts>';",
+        ),
+    ] {
+        let tokens = scan(source);
+        assert!(matches!(tokens[2].kind, TokenType::TextToken));
+        assert_eq!(tokens[2].literal_str(source).as_deref(), Some(content));
+        assert!(matches!(tokens[3].kind, TokenType::ClosingTag));
+    }
+}
+
+#[test]
+fn end_tags_in_script_strings_and_comments_are_script() {
+    for (source, content) in [
+        (
+            r#"<script>let a = "</script>";</script>"#,
+            r#"let a = "</script>";"#,
+        ),
+        (
+            "<script>let a = '<\\/script>' + `</SCRIPT>`</script>",
+            "let a = '<\\/script>' + `</SCRIPT>`",
+        ),
+        (
+            "<script>/* </script> */ go()</script>",
+            "/* </script> */ go()",
+        ),
+        ("<script>go() // done</script>", "go() // done"),
+        ("<script>it's </script>", "it's "),
+    ] {
+        let tokens = scan(source);
+        assert_eq!(
+            tokens[2].literal_str(source).as_deref(),
+            Some(content),
+            "{}",
+            source
+        );
+        assert!(matches!(tokens[3].kind, TokenType::ClosingTag));
+    }
+}
+
+#[test]
+fn tokens_borrow_their_text_from_the_source() {
+    let source = r#"<div class="a">text</div>"#;
+    let tokens = scan(source);
+
+    let range = source.as_bytes().as_ptr_range();
+    for token in &tokens {
+        assert!(range.contains(&token.lexeme(source).as_ptr()) || token.span.start == source.len());
+    }
+    // the tag name is a slice of `<div`, not a copy of it
+    assert!(matches!(
+        tokens[0].literal(source),
+        Some(Literal::Str(Cow::Borrowed("div")))
+    ));
+}
+
+#[test]
+fn limits_stop_the_scanner_with_a_diagnostic() {
+    let limited = |source: &str, options: ScannerOptions| {
+        let mut scanner = Scanner::new(source).with_options(options);
+        let count = scanner.scan_tokens().len();
+        let codes: Vec<&str> = scanner
+            .diagnostics()
+            .iter()
+            .filter_map(|diagnostic| diagnostic.code)
+            .collect();
+        return (count, codes);
+    };
+    let options = ScannerOptions::new();
+
+    // 1024 `<div` `>` pairs, then the `<div` whose `>` would be one too many, and EOF
+    let deep = "<div>".repeat(100_000);
+    assert_eq!(
+        limited(&deep, options),
+        (2 * 1024 + 2, vec![codes::LIMIT_EXCEEDED])
+    );
+    // void and self-closing elements don't nest
+    let flat = "<br><img /><p></p>".repeat(5_000);
+    assert_eq!(limited(&flat, options).1, Vec::<&str>::new());
+
+    let many = ScannerOptions {
+        max_tokens: 10,
+        ..options
+    };
+    assert_eq!(limited(&flat, many), (11, vec![codes::LIMIT_EXCEEDED]));
+
+    let long = ScannerOptions {
+        max_token_length: 8,
+        ..options
+    };
+    assert_eq!(limited("<p>{tiny}</p>", long).1, Vec::<&str>::new());
+    assert_eq!(
+        limited("<p>{much_longer}</p>", long),
+        (3, vec![codes::LIMIT_EXCEEDED])
+    );
+
+    let small = ScannerOptions {
+        max_input_size: 4,
+        ..options
+    };
+    assert_eq!(
+        limited("<p>hi</p>", small),
+        (1, vec![codes::LIMIT_EXCEEDED])
+    );
+    assert_eq!(limited("<p>", small).1, Vec::<&str>::new());
+}
+
+#[test]
+fn tokens_and_diagnostics_display_without_debug() {
+    let source = "<p>{title";
+    let mut scanner = Scanner::new(source);
+    let tokens = scanner.scan_tokens();
+    assert_eq!(tokens[0].to_string(), "OpeningTagStart 0..2 on line 1");
+    assert_eq!(
+        tokens[0].display(source).to_string(),
+        "OpeningTagStart <p p"
+    );
+
+    let diagnostic = &scanner.diagnostics()[0];
+    assert_eq!(
+        diagnostic.to_string(),
+        format!(
+            "error[{}]: {} (line 1)",
+            codes::UNTERMINATED_EXPRESSION,
+            diagnostic.message
+        )
+    );
+}
+
+#[test]
+fn tokens_compare_and_hash_by_value() {
+    let tokens = Scanner::new("<br>").scan_tokens().to_vec();
+    assert_eq!(
+        tokens,
+        [
+            Token::new(TokenType::OpeningTagStart, Span::new(0, 3), 1),
+            Token::new(TokenType::OpeningTagEnd, Span::new(3, 4), 1),
+            Token::new(TokenType::EOF, Span::new(4, 4), 1),
+        ]
+    );
+
+    let mut kinds = HashMap::new();
+    for token in Scanner::new("<p></p><p>").scan_tokens().iter() {
+        *kinds.entry(token.kind).or_insert(0) += 1;
+    }
+    assert_eq!(kinds[&TokenType::OpeningTagStart], 2);
+    assert_eq!(kinds[&TokenType::ClosingTag], 1);
+}
+
+#[test]
+fn relex_only_restarts_at_tags_that_start_outside_of_tags() {
+    // `<<` starts a tag inside `<div`, but it's text where relexing would start over
+    let source = &format!("<div <<p{}class=\"a\"", " ".repeat(40));
+    let mut scanner = Scanner::new(source);
+    scanner.scan_tokens();
+    let old = scanner.into_tokens();
+
+    let edit = TextEdit {
+        span: Span::new(source.len() - 1, source.len() - 1),
+        text: String::new(),
+    };
+    let relexed = Scanner::new(source).relex(old, &edit);
+    assert_eq!(relexed.to_vec(), scan(source));
+}
+
+#[test]
+fn relex_scans_edits_in_raw_text_like_scan_tokens() {
+    let padding = "<p>text</p>\n".repeat(8);
+    let sources = [
+        "<script>if (a < b) {}</script>",
+        "<style>p > a { color: red }</style>",
+        "<![CDATA[<div>]]>",
+        "---\nconst a = '<p>';\n---",
+    ];
+    for source in sources {
+        let source = format!("{}{}\n{}", padding, source, padding);
+        let mut scanner = Scanner::new(&source);
+        scanner.scan_tokens();
+        let tokens = scanner.into_tokens();
+
+        // every edit that adds or removes a character in the middle
+        for offset in padding.len()..source.len() - padding.len() {
+            for (span, text) in [
+                (Span::new(offset, offset), "<"),
+                (Span::new(offset, offset + 1), ""),
+            ] {
+                let edited = format!("{}{}{}", &source[..span.start], text, &source[span.end..]);
+                let edit = TextEdit {
+                    span,
+                    text: text.to_string(),
+                };
+                let old = TokenStream::new(&source, tokens.to_vec());
+                let relexed = Scanner::new(&edited).relex(old, &edit);
+                assert_eq!(relexed.to_vec(), scan(&edited), "{:?}", edited);
+            }
+        }
+    }
+}
+
+#[test]
+fn cdata_in_a_tag_scans_like_cdata_outside_of_one() {
+    let source = "\n<p><span\n<![CDATA[<div>]]>";
+    let tokens = scan(source);
+    assert!(matches!(tokens[3].kind, TokenType::CData));
+    let mut scanner = Scanner::new(source);
+    assert_eq!(scanner.scan_tokens_parallel(2).to_vec(), tokens);
+
+    let source = &format!("<span\n<![CDATA[<div>{}]]>", " ".repeat(40));
+    let mut scanner = Scanner::new(source);
+    scanner.scan_tokens();
+    let old = scanner.into_tokens();
+    let edit = TextEdit {
+        span: Span::new(source.len() - 1, source.len() - 1),
+        text: String::new(),
+    };
+    let relexed = Scanner::new(source).relex(old, &edit);
+    assert_eq!(relexed.to_vec(), scan(source));
+}
+
+#[test]
+fn shared_sources_are_not_copied() {
+    let source = Arc::new(String::from("<p>hello</p>"));
+    let mut scanner = Scanner::shared(&source);
+    assert_eq!(scanner.scan_tokens().source().as_ptr(), source.as_ptr());
+
+    let mut scanner = Scanner::shared(&source);
+    assert_eq!(
+        scanner.scan_tokens_parallel(4).source().as_ptr(),
+        source.as_ptr()
+    );
+}
+
+#[test]
+fn parallel_chunks_find_expressions_opened_in_earlier_chunks() {
+    let source = "<div></SCRIPT>(`\n<p>`)<div>";
+    let scan = |parallel: bool| {
+        let mut scanner = Scanner::new(source);
+        let tokens = match parallel {
+            true => scanner.scan_tokens_parallel(2).to_vec(),
+            false => scanner.scan_tokens().to_vec(),
+        };
+        return (tokens, format!("{:?}", scanner.diagnostics()));
+    };
+    assert_eq!(scan(true), scan(false));
+}
+
+#[test]
+fn decoded_files_are_not_copied() {
+    let bytes = b"<p>hello</p>".to_vec();
+    let buffer = bytes.as_ptr();
+    let mut regg = Regg::new();
+    let decoded = regg.decode_owned(bytes);
+    assert_eq!(decoded.as_ptr(), buffer);
+}
+
+#[test]
+fn parallel_chunks_add_up_their_nesting() {
+    // every chunk on its own nests 50 deep, together they nest 200 deep
+    let source = "<div>\n".repeat(200);
+    let options = ScannerOptions {
+        max_depth: 100,
+        ..ScannerOptions::new()
+    };
+    let scan = |parallel: bool| {
+        let mut scanner = Scanner::new(&source).with_options(options);
+        let tokens = match parallel {
+            true => scanner.scan_tokens_parallel(4).to_vec(),
+            false => scanner.scan_tokens().to_vec(),
+        };
+        let diagnostics: Vec<String> = scanner
+            .diagnostics()
+            .iter()
+            .map(|diagnostic| diagnostic.to_string())
+            .collect();
+        return (tokens, diagnostics);
+    };
+
+    let (tokens, diagnostics) = scan(false);
+    assert_eq!(tokens.len(), 2 * 100 + 2);
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(scan(true), (tokens, diagnostics));
+}